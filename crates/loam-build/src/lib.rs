@@ -1,2 +1,3 @@
 pub mod deps;
+pub mod wasm;
 pub use deps::*;
@@ -0,0 +1,314 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+use cargo_metadata::{DependencyKind, Metadata, Package};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+    #[error(
+        "no contract `{0}` in the workspace and no `{0}.wasm` in target/loam; \
+         use the name of a workspace crate that builds to cdylib, with `-` replaced by `_`"
+    )]
+    NotFound(String),
+    #[error("failed to run cargo to build contract `{0}`: {1}")]
+    CargoCmd(String, io::Error),
+    #[error("failed to build contract `{name}`; run `loam build` to see why\n{stderr}")]
+    Build { name: String, stderr: String },
+    #[error("failed to copy the wasm of contract `{0}` to target/loam: {1}")]
+    Copy(String, io::Error),
+}
+
+/// Retrieves the wasm of the contract `name`, where `loam build` writes it: `target/loam/<name>.wasm`.
+///
+/// If `name` is a crate of the workspace of `manifest_path`, with `-` replaced by `_`, and its wasm
+/// is missing or older than any of the files of the crate or of the crates it depends on by path,
+/// the contract is built first, in `target/loam/build` so as not to wait on the lock of a running
+/// build.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace metadata cannot be retrieved.
+/// - `name` is neither a crate of the workspace nor an existing wasm file.
+/// - Building the contract fails, e.g. because the `wasm32-unknown-unknown` target is missing.
+pub fn contract_wasm(manifest_path: &Path, name: &str) -> Result<PathBuf, Error> {
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()?;
+    let target_dir = metadata
+        .target_directory
+        .clone()
+        .into_std_path_buf()
+        .join("loam");
+    let wasm = target_dir.join(format!("{name}.wasm"));
+    let Some(package) = metadata
+        .packages
+        .iter()
+        .find(|p| p.name.replace('-', "_") == name && is_cdylib(p))
+    else {
+        return if wasm.exists() {
+            Ok(wasm)
+        } else {
+            Err(Error::NotFound(name.to_string()))
+        };
+    };
+    if is_stale(&metadata, package, &wasm) {
+        build(package, &target_dir, &wasm)?;
+    }
+    Ok(wasm)
+}
+
+/// Whether `wasm` is missing or older than a file of `package` or of the crates it depends on by
+/// path.
+fn is_stale(metadata: &Metadata, package: &Package, wasm: &Path) -> bool {
+    let Ok(built) = fs::metadata(wasm).and_then(|m| m.modified()) else {
+        return true;
+    };
+    source_dirs(metadata, package)
+        .iter()
+        .filter_map(|dir| newest_change(dir))
+        .any(|changed| changed > built)
+}
+
+/// Directories of `package` and of the crates it depends on by path, following the dependencies of
+/// those that are members of the workspace. Dev-dependencies are left out, as they are not built
+/// into the wasm.
+fn source_dirs(metadata: &Metadata, package: &Package) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut packages = vec![package];
+    if let Some(dir) = package.manifest_path.parent() {
+        dirs.push(dir.to_path_buf().into_std_path_buf());
+    }
+    while let Some(package) = packages.pop() {
+        let paths = package
+            .dependencies
+            .iter()
+            .filter(|dep| dep.kind != DependencyKind::Development)
+            .filter_map(|dep| dep.path.as_ref());
+        for path in paths {
+            let dir = path.clone().into_std_path_buf();
+            if dirs.contains(&dir) {
+                continue;
+            }
+            packages.extend(
+                metadata
+                    .packages
+                    .iter()
+                    .find(|p| p.manifest_path.parent() == Some(path.as_path())),
+            );
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+fn is_cdylib(package: &Package) -> bool {
+    package
+        .targets
+        .iter()
+        .any(|t| t.crate_types.iter().any(|c| c == "cdylib"))
+}
+
+/// Latest modification of the files of a crate, leaving out build output and hidden files.
+fn newest_change(dir: &Path) -> Option<SystemTime> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && name != "target"
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if metadata.is_dir() {
+                newest_change(&entry.path())
+            } else {
+                metadata.modified().ok()
+            }
+        })
+        .max()
+}
+
+fn build(package: &Package, target_dir: &Path, wasm: &Path) -> Result<(), Error> {
+    let name = package.name.replace('-', "_");
+    let build_dir = target_dir.join("build");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(cargo)
+        .arg("rustc")
+        .arg(format!("--manifest-path={}", package.manifest_path))
+        .args([
+            "--crate-type=cdylib",
+            "--target=wasm32-unknown-unknown",
+            "--release",
+        ])
+        .arg("--target-dir")
+        .arg(&build_dir)
+        .output()
+        .map_err(|e| Error::CargoCmd(name.clone(), e))?;
+    if !output.status.success() {
+        return Err(Error::Build {
+            name,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    fs::create_dir_all(target_dir).map_err(|e| Error::Copy(name.clone(), e))?;
+    let built = build_dir
+        .join("wasm32-unknown-unknown")
+        .join("release")
+        .join(format!("{name}.wasm"));
+    install(&built, wasm).map_err(|e| Error::Copy(name, e))
+}
+
+/// Copy the `built` wasm to `wasm`. `loam build` links the wasm from its own build; replace the
+/// link rather than writing through it.
+fn install(built: &Path, wasm: &Path) -> io::Result<()> {
+    if wasm.symlink_metadata().is_ok() {
+        fs::remove_file(wasm)?;
+    }
+    fs::copy(built, wasm)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use assert_fs::{prelude::*, TempDir};
+
+    use super::*;
+
+    /// A workspace with a `contract` crate, building to cdylib, which depends by path on the
+    /// `helper` crate, itself depending by path on `util`.
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        dir.child("Cargo.toml")
+            .write_str("[workspace]\nresolver = \"2\"\nmembers = [\"contract\", \"helper\"]\n")
+            .unwrap();
+        crate_files(
+            &dir,
+            "contract",
+            "[lib]\ncrate-type = [\"cdylib\"]\n\n[dependencies]\nhelper = { path = \"../helper\" }\n",
+        );
+        crate_files(
+            &dir,
+            "helper",
+            "[dependencies]\nutil = { path = \"../util\" }\n",
+        );
+        crate_files(&dir, "util", "");
+        dir
+    }
+
+    fn crate_files(dir: &TempDir, name: &str, manifest: &str) {
+        dir.child(name)
+            .child("Cargo.toml")
+            .write_str(&format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n{manifest}"
+            ))
+            .unwrap();
+        dir.child(name).child("src/lib.rs").write_str("").unwrap();
+    }
+
+    fn metadata(dir: &TempDir) -> Metadata {
+        cargo_metadata::MetadataCommand::new()
+            .manifest_path(dir.path().join("Cargo.toml"))
+            .no_deps()
+            .exec()
+            .unwrap()
+    }
+
+    /// Set the modification time of `path` `secs` seconds from now.
+    fn touch(path: &Path, secs: i64) {
+        let now = SystemTime::now();
+        let time = if secs < 0 {
+            now - Duration::from_secs(secs.unsigned_abs())
+        } else {
+            now + Duration::from_secs(secs.unsigned_abs())
+        };
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn not_found() {
+        let dir = workspace();
+        let manifest = dir.path().join("Cargo.toml");
+        let err = contract_wasm(&manifest, "helper").unwrap_err();
+        assert!(matches!(err, Error::NotFound(name) if name == "helper"));
+
+        let wasm = dir.child("target/loam/published.wasm");
+        wasm.write_binary(b"\0asm").unwrap();
+        assert_eq!(
+            contract_wasm(&manifest, "published").unwrap(),
+            metadata(&dir)
+                .target_directory
+                .join("loam/published.wasm")
+                .into_std_path_buf()
+        );
+    }
+
+    #[test]
+    fn stale_when_a_path_dependency_changed() {
+        let dir = workspace();
+        let metadata = metadata(&dir);
+        let package = metadata
+            .packages
+            .iter()
+            .find(|p| p.name == "contract")
+            .unwrap();
+        let wasm = dir.child("contract.wasm");
+        assert!(is_stale(&metadata, package, &wasm));
+
+        wasm.write_binary(b"\0asm").unwrap();
+        for file in [
+            "contract/src/lib.rs",
+            "helper/src/lib.rs",
+            "util/src/lib.rs",
+        ] {
+            touch(&dir.child(file), -60);
+        }
+        for file in [
+            "Cargo.toml",
+            "contract/Cargo.toml",
+            "helper/Cargo.toml",
+            "util/Cargo.toml",
+        ] {
+            touch(&dir.child(file), -60);
+        }
+        assert!(!is_stale(&metadata, package, &wasm));
+
+        for file in ["helper/src/lib.rs", "util/src/lib.rs"] {
+            touch(&dir.child(file), 60);
+            assert!(is_stale(&metadata, package, &wasm), "{file}");
+            touch(&dir.child(file), -60);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn install_replaces_symlink() {
+        let dir = TempDir::new().unwrap();
+        let linked = dir.child("linked.wasm");
+        linked.write_binary(b"old").unwrap();
+        let built = dir.child("built.wasm");
+        built.write_binary(b"new").unwrap();
+        let wasm = dir.child("contract.wasm");
+        std::os::unix::fs::symlink(&linked, &wasm).unwrap();
+
+        install(&built, &wasm).unwrap();
+        assert!(!wasm.symlink_metadata().unwrap().is_symlink());
+        assert_eq!(fs::read(&wasm).unwrap(), b"new");
+        assert_eq!(fs::read(&linked).unwrap(), b"old");
+    }
+}
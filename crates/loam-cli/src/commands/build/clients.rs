@@ -57,6 +57,8 @@ pub enum Error {
     MissingContractID(String),
     #[error("⛔ ️Unable to parse init script: {0:?}")]
     InitParseFailure(String),
+    #[error("⛔ ️Unable to parse constructor args: {0:?}")]
+    ConstructorArgsParseFailure(String),
     #[error("⛔ ️Failed to execute subcommand: {0:?}\n{1:?}")]
     SubCommandExecutionFailure(String, String),
     #[error(transparent)]
@@ -463,23 +465,9 @@ export default new Client.Client({{
                     }
                 }
 
-                eprintln!("🪞 instantiating {name:?} smart contract");
-                let new_contract_id = cli::contract::deploy::wasm::Cmd::parse_arg_vec(&[
-                    "--alias",
-                    &name,
-                    "--wasm-hash",
-                    &hash,
-                ])?
-                .run_against_rpc_server(None, None)
-                .await?
-                .into_result()
-                .expect("no contract id returned by 'contract deploy'");
-                eprintln!("    ↳ contract_id: {new_contract_id}");
-
-                // Save the alias for future use
-                Self::save_contract_alias(&name, &new_contract_id, network, workspace_root)?;
-
-                new_contract_id
+                let constructor_args = settings.and_then(|s| s.constructor_args.as_deref());
+                Self::deploy_contract(&name, &hash, constructor_args, network, workspace_root)
+                    .await?
             };
 
             // Run init script if we're in development or test environment
@@ -499,6 +487,51 @@ export default new Client.Client({{
         Ok(())
     }
 
+    /// Deploy the installed wasm `hash` as contract `name`, passing `constructor_args` to its
+    /// `__constructor`, and save the new contract's alias.
+    async fn deploy_contract(
+        name: &str,
+        hash: &str,
+        constructor_args: Option<&str>,
+        network: &Network,
+        workspace_root: &std::path::Path,
+    ) -> Result<Contract, Error> {
+        eprintln!("🪞 instantiating {name:?} smart contract");
+        let constructor_args = match constructor_args {
+            Some(args) => Self::parse_constructor_args(args)?,
+            None => Vec::new(),
+        };
+        let mut args = vec!["--alias", name, "--wasm-hash", hash];
+        if !constructor_args.is_empty() {
+            args.push("--");
+            args.extend(constructor_args.iter().map(String::as_str));
+        }
+        let new_contract_id = cli::contract::deploy::wasm::Cmd::parse_arg_vec(&args)?
+            .run_against_rpc_server(None, None)
+            .await?
+            .into_result()
+            .expect("no contract id returned by 'contract deploy'");
+        eprintln!("    ↳ contract_id: {new_contract_id}");
+
+        // Save the alias for future use
+        Self::save_contract_alias(name, &new_contract_id, network, workspace_root)?;
+
+        Ok(new_contract_id)
+    }
+
+    /// Split the `constructor_args` of a contract into the arguments passed to its
+    /// `__constructor` on deploy, resolving any `$()` patterns first.
+    fn parse_constructor_args(constructor_args: &str) -> Result<Vec<String>, Error> {
+        let re = Regex::new(r"\$\((.*?)\)").expect("Invalid regex pattern");
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let resolved = Self::resolve_line(&re, constructor_args.trim(), shell, flag)?;
+        split(&resolved).ok_or(Error::ConstructorArgsParseFailure(resolved))
+    }
+
     fn resolve_line(re: &Regex, line: &str, shell: &str, flag: &str) -> Result<String, Error> {
         let mut result = String::new();
         let mut last_match = 0;
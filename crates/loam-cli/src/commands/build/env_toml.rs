@@ -115,6 +115,9 @@ pub struct Contract {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub init: Option<String>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constructor_args: Option<String>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
 }
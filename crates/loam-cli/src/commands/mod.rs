@@ -5,6 +5,7 @@ use clap::{command, CommandFactory, FromArgMatches, Parser};
 pub mod build;
 pub mod dev;
 pub mod init;
+pub mod storage;
 pub mod update_env;
 
 const ABOUT: &str = "Build contracts and generate front ends";
@@ -43,6 +44,7 @@ impl Root {
             Cmd::Build(build_info) => build_info.run().await?,
             Cmd::UpdateEnv(e) => e.run()?,
             Cmd::Dev(dev_info) => dev_info.run().await?,
+            Cmd::Storage(storage) => storage.run().await?,
         };
         Ok(())
     }
@@ -69,6 +71,10 @@ pub enum Cmd {
 
     /// Monitor contracts and environments.toml for changes and rebuild as needed
     Dev(dev::Cmd),
+
+    /// Inspect the storage of deployed contracts
+    #[command(subcommand)]
+    Storage(storage::Cmd),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -82,4 +88,6 @@ pub enum Error {
     UpdateEnv(#[from] update_env::Error),
     #[error(transparent)]
     Dev(#[from] dev::Error),
+    #[error(transparent)]
+    Storage(#[from] storage::Error),
 }
@@ -0,0 +1,455 @@
+use std::collections::{HashMap, HashSet};
+
+use clap::Parser;
+use stellar_strkey::Contract;
+use stellar_xdr::curr::{
+    ContractCodeEntry, ContractDataDurability, ContractDataEntry, ContractExecutable, Hash,
+    LedgerEntryData, LedgerKey, LedgerKeyContractCode, LedgerKeyContractData, Limits, ReadXdr,
+    ScAddress, ScContractInstance, ScVal, WriteXdr,
+};
+
+use super::layout::{self, Field, Layout};
+
+/// Most keys `getLedgerEntries` accepts in one request.
+const MAX_KEYS: usize = 200;
+
+/// Print the state of a deployed contract, by `#[loamstorage]` struct and field
+///
+/// Reads the storage layouts `#[loamstorage]` records in the contract's metadata, then fetches
+/// the contract instance and the persistent and temporary entries that can be found from it:
+/// items, and the elements of vectors and iterable maps. Keys of plain and double maps cannot
+/// be listed, since the ledger cannot be searched by key prefix. The state of subcontracts
+/// mounted with `as` is looked up under each prefix `derive_contract` records in the metadata.
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Alias or ID of the contract
+    pub contract: String,
+    #[command(flatten)]
+    pub network: soroban_cli::config::network::Args,
+    #[command(flatten)]
+    pub locator: soroban_cli::config::locator::Args,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Network(#[from] Box<soroban_cli::config::network::Error>),
+    #[error(transparent)]
+    Locator(#[from] Box<soroban_cli::config::locator::Error>),
+    #[error("⛔ ️Invalid contract ID: {0:?}")]
+    InvalidContractId(String),
+    #[error("⛔ ️contract {0} not found")]
+    NotFound(String),
+    #[error(transparent)]
+    Rpc(#[from] Box<soroban_rpc::Error>),
+    #[error(transparent)]
+    Xdr(#[from] stellar_xdr::curr::Error),
+    #[error(transparent)]
+    Layout(#[from] layout::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let network = self.network.get(&self.locator).map_err(Box::new)?;
+        let contract = self.contract_id(&network.network_passphrase)?;
+        let rpc = Rpc(network.rpc_client().map_err(Box::new)?);
+
+        let instance_key = data_key(
+            &contract,
+            ScVal::LedgerKeyContractInstance,
+            ContractDataDurability::Persistent,
+        );
+        let Some(Entry {
+            val:
+                ScVal::ContractInstance(ScContractInstance {
+                    executable,
+                    storage,
+                }),
+            ttl,
+        }) = rpc.get_one(instance_key).await?
+        else {
+            return Err(Error::NotFound(contract.to_string()));
+        };
+        let (layouts, prefixes) = match executable {
+            ContractExecutable::Wasm(hash) => {
+                let code_key = LedgerKey::ContractCode(LedgerKeyContractCode { hash });
+                match rpc.get_code(code_key).await? {
+                    Some(code) => (layout::from_wasm(&code)?, layout::prefixes(&code)?),
+                    None => Default::default(),
+                }
+            }
+            ContractExecutable::StellarAsset => Default::default(),
+        };
+        let instance = storage
+            .map(|map| {
+                map.iter()
+                    .map(|entry| (entry.key.clone(), entry.val.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        println!("{contract}");
+        println!("instance{}", ttl_note(ttl));
+        if layouts.is_empty() {
+            println!("no #[loamstorage] layouts in the contract's metadata");
+        }
+        let mut shown = HashSet::new();
+        let mounts =
+            std::iter::once(None).chain(prefixes.iter().map(|prefix| Some(prefix.as_str())));
+        for prefix in mounts {
+            for layout in &layouts {
+                print_layout(&rpc, &contract, layout, prefix, &instance, &mut shown).await?;
+            }
+        }
+        let others = instance
+            .iter()
+            .filter(|(key, _)| !shown.contains(&layout::display(key)))
+            .collect::<Vec<_>>();
+        if !others.is_empty() {
+            println!("other instance entries");
+            for (key, val) in others {
+                println!("    {} = {}", layout::display(key), layout::display(val));
+            }
+        }
+        Ok(())
+    }
+
+    fn contract_id(&self, network_passphrase: &str) -> Result<Contract, Error> {
+        if let Some(contract) = self
+            .locator
+            .get_contract_id(&self.contract, network_passphrase)
+            .map_err(Box::new)?
+        {
+            return Ok(contract);
+        }
+        Contract::from_string(&self.contract)
+            .map_err(|_| Error::InvalidContractId(self.contract.clone()))
+    }
+}
+
+/// Print the fields of `layout` as stored under `prefix`. Since the metadata does not tell which
+/// subcontracts are mounted under a prefix, a layout is only printed for a prefix if some of its
+/// entries exist under it.
+async fn print_layout(
+    rpc: &Rpc,
+    contract: &Contract,
+    layout: &Layout,
+    prefix: Option<&str>,
+    instance: &[(ScVal, ScVal)],
+    shown: &mut HashSet<String>,
+) -> Result<(), Error> {
+    let mut fields = Vec::new();
+    let mut found = false;
+    for field in &layout.fields {
+        let lines = if field.is_instance() {
+            let lines = instance_lines(field, prefix, instance, shown);
+            found |= !lines.is_empty();
+            lines
+        } else {
+            let (lines, field_found) = field_lines(rpc, contract, prefix, field).await?;
+            found |= field_found;
+            lines
+        };
+        fields.push((field, lines));
+    }
+    if prefix.is_some() && !found {
+        return Ok(());
+    }
+    let name = match prefix {
+        Some(prefix) => format!("{} as {prefix}", layout.name),
+        None => layout.name.clone(),
+    };
+    match layout.version {
+        Some(version) => println!("{name} (version {version})"),
+        None => println!("{name}"),
+    }
+    for (field, lines) in fields {
+        println!("  {} ({})", field.name, field.kind);
+        for line in lines {
+            println!("    {line}");
+        }
+    }
+    Ok(())
+}
+
+/// Lines describing the entries of an instance field stored under `prefix`, adding the keys of
+/// every entry the field uses to `shown`.
+fn instance_lines(
+    field: &Field,
+    prefix: Option<&str>,
+    instance: &[(ScVal, ScVal)],
+    shown: &mut HashSet<String>,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (stored, val) in instance {
+        let Some((variant, args)) = layout::unprefixed(prefix, stored).and_then(layout::variant)
+        else {
+            continue;
+        };
+        if field.keys.contains(&variant) {
+            shown.insert(layout::display(stored));
+        }
+        if field.key(0) == Some(variant.as_str()) {
+            lines.push(entry_line(args, val, None));
+        }
+    }
+    lines
+}
+
+/// Lines describing the entries of a persistent or temporary field stored under `prefix`, and
+/// whether any of them exists.
+async fn field_lines(
+    rpc: &Rpc,
+    contract: &Contract,
+    prefix: Option<&str>,
+    field: &Field,
+) -> Result<(Vec<String>, bool), Error> {
+    let durability = if field.is_temporary() {
+        ContractDataDurability::Temporary
+    } else {
+        ContractDataDurability::Persistent
+    };
+    let key = |variant: Option<&str>, args: Vec<ScVal>| -> Result<LedgerKey, Error> {
+        let variant = variant.unwrap_or_default();
+        let key = layout::prefixed(prefix, layout::key(variant, args)?)?;
+        Ok(data_key(contract, key, durability))
+    };
+    let main = field.key(0);
+    let kind = field
+        .kind
+        .trim_start_matches("Persistent")
+        .trim_start_matches("Temporary");
+    let entries = match kind {
+        "Item" => vec![(Vec::new(), rpc.get_one(key(main, Vec::new())?).await?)],
+        "Vec" => {
+            let len = rpc.get_len(key(field.key(1), Vec::new())?).await?;
+            let keys = (0..len)
+                .map(|index| key(main, vec![ScVal::U32(index)]))
+                .collect::<Result<Vec<_>, _>>()?;
+            (0..len)
+                .map(|index| vec![ScVal::U32(index)])
+                .zip(rpc.get(&keys).await?)
+                .collect()
+        }
+        "IterableMap" => {
+            let len = rpc.get_len(key(field.key(1), Vec::new())?).await?;
+            let index_keys = (0..len)
+                .map(|index| key(field.key(2), vec![ScVal::U32(index)]))
+                .collect::<Result<Vec<_>, _>>()?;
+            let map_keys = rpc
+                .get(&index_keys)
+                .await?
+                .into_iter()
+                .flatten()
+                .map(|entry| entry.val)
+                .collect::<Vec<_>>();
+            let keys = map_keys
+                .iter()
+                .map(|map_key| key(main, vec![map_key.clone()]))
+                .collect::<Result<Vec<_>, _>>()?;
+            map_keys
+                .into_iter()
+                .map(|map_key| vec![map_key])
+                .zip(rpc.get(&keys).await?)
+                .collect()
+        }
+        _ => return Ok((vec!["(keys cannot be listed)".to_string()], false)),
+    };
+    let found = entries.iter().any(|(_, entry)| entry.is_some());
+    let lines = entries
+        .into_iter()
+        .map(|(args, entry)| match entry {
+            Some(Entry { val, ttl }) => entry_line(&args, &val, ttl),
+            None => entry_line(&args, &ScVal::Void, None) + " (missing)",
+        })
+        .collect();
+    Ok((lines, found))
+}
+
+fn data_key(contract: &Contract, key: ScVal, durability: ContractDataDurability) -> LedgerKey {
+    LedgerKey::ContractData(LedgerKeyContractData {
+        contract: ScAddress::Contract(Hash(contract.0)),
+        key,
+        durability,
+    })
+}
+
+fn entry_line(args: &[ScVal], val: &ScVal, ttl: Option<u32>) -> String {
+    let val = layout::display(val);
+    let line = match args {
+        [] => val,
+        [arg] => format!("{} = {val}", layout::display(arg)),
+        args => format!(
+            "({}) = {val}",
+            args.iter()
+                .map(layout::display)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    line + &ttl_note(ttl)
+}
+
+fn ttl_note(ttl: Option<u32>) -> String {
+    ttl.map(|ttl| format!(" (live for {ttl} more ledgers)"))
+        .unwrap_or_default()
+}
+
+/// A contract data entry and the number of ledgers it remains live for.
+struct Entry {
+    val: ScVal,
+    ttl: Option<u32>,
+}
+
+/// Ledger entries through the RPC client of `soroban-cli`.
+struct Rpc(soroban_rpc::Client);
+
+impl Rpc {
+    /// Entries for `keys` in the same order, with `None` for entries that do not exist.
+    async fn get_raw(
+        &self,
+        keys: &[LedgerKey],
+    ) -> Result<Vec<Option<(LedgerEntryData, Option<u32>)>>, Error> {
+        let mut found = HashMap::new();
+        for chunk in keys.chunks(MAX_KEYS) {
+            let result = self.0.get_ledger_entries(chunk).await.map_err(Box::new)?;
+            for entry in result.entries.unwrap_or_default() {
+                let ttl = entry.live_until_ledger_seq_ledger_seq.map(|live_until| {
+                    u32::try_from(i64::from(live_until) - result.latest_ledger).unwrap_or(0)
+                });
+                found.insert(entry.key, (entry.xdr, ttl));
+            }
+        }
+        keys.iter()
+            .map(|key| {
+                found
+                    .remove(&key.to_xdr_base64(Limits::none())?)
+                    .map(|(xdr, ttl)| {
+                        Ok((LedgerEntryData::from_xdr_base64(xdr, Limits::none())?, ttl))
+                    })
+                    .transpose()
+            })
+            .collect()
+    }
+
+    /// Contract data entries for `keys` in the same order.
+    async fn get(&self, keys: &[LedgerKey]) -> Result<Vec<Option<Entry>>, Error> {
+        Ok(self
+            .get_raw(keys)
+            .await?
+            .into_iter()
+            .map(|entry| match entry {
+                Some((LedgerEntryData::ContractData(ContractDataEntry { val, .. }), ttl)) => {
+                    Some(Entry { val, ttl })
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn get_one(&self, key: LedgerKey) -> Result<Option<Entry>, Error> {
+        Ok(self.get(&[key]).await?.pop().flatten())
+    }
+
+    /// Length of a vector or iterable map, zero if it was never written.
+    async fn get_len(&self, key: LedgerKey) -> Result<u32, Error> {
+        Ok(match self.get_one(key).await? {
+            Some(Entry {
+                val: ScVal::U32(len),
+                ..
+            }) => len,
+            _ => 0,
+        })
+    }
+
+    async fn get_code(&self, key: LedgerKey) -> Result<Option<Vec<u8>>, Error> {
+        Ok(match self.get_raw(&[key]).await?.pop().flatten() {
+            Some((LedgerEntryData::ContractCode(ContractCodeEntry { code, .. }), _)) => {
+                Some(code.to_vec())
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stellar_xdr::curr::ScSymbol;
+
+    use super::*;
+
+    fn symbol(s: &str) -> ScVal {
+        ScVal::Symbol(ScSymbol(s.try_into().unwrap()))
+    }
+
+    #[test]
+    fn entry_lines() {
+        assert_eq!(entry_line(&[], &ScVal::U32(5), None), "5");
+        assert_eq!(
+            entry_line(&[ScVal::U32(0)], &symbol("a"), Some(100)),
+            "0 = a (live for 100 more ledgers)"
+        );
+        assert_eq!(
+            entry_line(&[symbol("alice"), symbol("bob")], &ScVal::I64(-3), None),
+            "(alice, bob) = -3"
+        );
+    }
+
+    #[test]
+    fn instance_fields_list_their_entries() {
+        let layouts = layout::test::fixture();
+        let admin = &layouts[0].fields[0];
+        let instance = [
+            (layout::key("TokenAdmin", []).unwrap(), symbol("alice")),
+            (layout::key("LogEntries", []).unwrap(), ScVal::U32(1)),
+            (symbol("Other"), ScVal::Void),
+        ];
+        let mut shown = HashSet::new();
+        assert_eq!(
+            instance_lines(admin, None, &instance, &mut shown),
+            ["alice"]
+        );
+        assert_eq!(shown, HashSet::from(["[TokenAdmin]".to_string()]));
+
+        // Entries of the field's other keys are marked shown without a line of their own.
+        let holders = Field {
+            kind: "InstanceIterableMap".to_string(),
+            ..layouts[0].fields[2].clone()
+        };
+        let instance = [
+            (
+                layout::key("TokenHolders", [symbol("bob")]).unwrap(),
+                ScVal::U32(2),
+            ),
+            (layout::key("TokenHoldersLen", []).unwrap(), ScVal::U32(1)),
+        ];
+        let mut shown = HashSet::new();
+        assert_eq!(
+            instance_lines(&holders, None, &instance, &mut shown),
+            ["bob = 2"]
+        );
+        assert_eq!(shown.len(), 2);
+    }
+
+    #[test]
+    fn instance_fields_under_a_prefix() {
+        let layouts = layout::test::fixture();
+        let admin = &layouts[0].fields[0];
+        let stored = |prefix| layout::prefixed(prefix, layout::key("TokenAdmin", []).unwrap());
+        let instance = [
+            (stored(None).unwrap(), symbol("alice")),
+            (stored(Some("lp")).unwrap(), symbol("bob")),
+        ];
+        let mut shown = HashSet::new();
+        assert_eq!(
+            instance_lines(admin, Some("lp"), &instance, &mut shown),
+            ["bob"]
+        );
+        assert_eq!(shown, HashSet::from(["[lp, [TokenAdmin]]".to_string()]));
+        assert!(instance_lines(admin, Some("vault"), &instance, &mut shown).is_empty());
+        assert_eq!(
+            instance_lines(admin, None, &instance, &mut shown),
+            ["alice"]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Storage layouts that `#[loamstorage]` records in a contract's metadata, and the ledger keys
+//! and values they describe.
+use serde_derive::Deserialize;
+use stellar_xdr::curr::{
+    AccountId, Limited, Limits, PublicKey, ReadXdr, ScAddress, ScMetaEntry, ScMetaV0, ScSymbol,
+    ScVal, ScVec, Uint256,
+};
+
+/// Metadata key the layouts are stored under.
+const META_KEY: &str = "loamstorage";
+
+/// Metadata key `derive_contract` stores the prefixes of subcontracts mounted with `as` under.
+const PREFIXES_KEY: &str = "loamprefixes";
+
+/// Custom wasm section holding contract metadata.
+const META_SECTION: &str = "contractmetav0";
+
+/// Layout of one `#[loamstorage]` struct.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Layout {
+    #[serde(rename = "struct")]
+    pub name: String,
+    pub version: Option<u32>,
+    pub fields: Vec<Field>,
+}
+
+/// A field of a `#[loamstorage]` struct and the names of the key variants it uses, main key
+/// first.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub keys: Vec<String>,
+}
+
+impl Field {
+    /// Whether the field lives in instance storage.
+    pub fn is_instance(&self) -> bool {
+        self.kind.starts_with("Instance")
+    }
+
+    /// Whether the field lives in temporary storage.
+    pub fn is_temporary(&self) -> bool {
+        self.kind.starts_with("Temporary")
+    }
+
+    /// Name of the key variant at `index`, e.g. `1` for the length of a vector.
+    pub fn key(&self, index: usize) -> Option<&str> {
+        self.keys.get(index).map(String::as_str)
+    }
+}
+
+/// Every layout in the metadata of `wasm`.
+pub fn from_wasm(wasm: &[u8]) -> Result<Vec<Layout>, Error> {
+    meta_values(wasm, META_KEY)?
+        .iter()
+        .map(|val| Ok(serde_json::from_str(val)?))
+        .collect()
+}
+
+/// Prefixes of the subcontracts mounted with `as` in the contract `wasm`, whose state is stored
+/// under keys paired with the prefix.
+pub fn prefixes(wasm: &[u8]) -> Result<Vec<String>, Error> {
+    let mut prefixes = Vec::new();
+    for val in meta_values(wasm, PREFIXES_KEY)? {
+        prefixes.extend(serde_json::from_str::<Vec<String>>(&val)?);
+    }
+    Ok(prefixes)
+}
+
+/// Values of the metadata entries of `wasm` stored under `meta_key`.
+fn meta_values(wasm: &[u8], meta_key: &str) -> Result<Vec<String>, Error> {
+    let mut values = Vec::new();
+    for section in custom_sections(wasm, META_SECTION)? {
+        let mut limited = Limited::new(section, Limits::none());
+        for entry in ScMetaEntry::read_xdr_iter(&mut limited) {
+            let ScMetaEntry::ScMetaV0(ScMetaV0 { key, val }) = entry?;
+            if key.to_utf8_string_lossy() == meta_key {
+                values.push(val.to_utf8_string_lossy());
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Contents of the custom sections of `wasm` called `name`.
+fn custom_sections<'a>(wasm: &'a [u8], name: &str) -> Result<Vec<&'a [u8]>, Error> {
+    let mut rest = wasm.strip_prefix(b"\0asm").ok_or(Error::NotWasm)?;
+    rest = rest.get(4..).ok_or(Error::NotWasm)?;
+    let mut sections = Vec::new();
+    while let Some((&id, after_id)) = rest.split_first() {
+        let (size, after_size) = read_leb128(after_id)?;
+        let content = after_size.get(..size).ok_or(Error::NotWasm)?;
+        rest = &after_size[size..];
+        if id == 0 {
+            let (name_len, after_len) = read_leb128(content)?;
+            let section_name = after_len.get(..name_len).ok_or(Error::NotWasm)?;
+            if section_name == name.as_bytes() {
+                sections.push(&after_len[name_len..]);
+            }
+        }
+    }
+    Ok(sections)
+}
+
+fn read_leb128(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+    let mut value = 0usize;
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        value |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(Error::NotWasm)
+}
+
+/// Key of an entry stored under `variant` of a `{Struct}Key` enum, with the variant's fields.
+pub fn key(variant: &str, args: impl IntoIterator<Item = ScVal>) -> Result<ScVal, Error> {
+    let symbol = ScVal::Symbol(ScSymbol(variant.try_into()?));
+    let items = std::iter::once(symbol).chain(args).collect::<Vec<_>>();
+    Ok(ScVal::Vec(Some(ScVec(items.try_into()?))))
+}
+
+/// `key` as stored by a subcontract mounted under `prefix`: paired with the prefix, if any.
+pub fn prefixed(prefix: Option<&str>, key: ScVal) -> Result<ScVal, Error> {
+    let Some(prefix) = prefix else {
+        return Ok(key);
+    };
+    let items = vec![ScVal::Symbol(ScSymbol(prefix.try_into()?)), key];
+    Ok(ScVal::Vec(Some(ScVec(items.try_into()?))))
+}
+
+/// The key a subcontract mounted under `prefix` stored as `stored`, if it was stored under that
+/// prefix.
+pub fn unprefixed<'a>(prefix: Option<&str>, stored: &'a ScVal) -> Option<&'a ScVal> {
+    let Some(prefix) = prefix else {
+        return Some(stored);
+    };
+    match stored {
+        ScVal::Vec(Some(ScVec(items))) => match &items[..] {
+            [ScVal::Symbol(symbol), key] if symbol.to_utf8_string_lossy() == prefix => Some(key),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Name of the `{Struct}Key` variant `key` was built from, and the variant's fields.
+pub fn variant(key: &ScVal) -> Option<(String, &[ScVal])> {
+    let ScVal::Vec(Some(ScVec(items))) = key else {
+        return None;
+    };
+    match items.split_first() {
+        Some((ScVal::Symbol(symbol), args)) => Some((symbol.to_utf8_string_lossy(), args)),
+        _ => None,
+    }
+}
+
+/// A value in a compact, human readable form.
+pub fn display(val: &ScVal) -> String {
+    match val {
+        ScVal::Bool(b) => b.to_string(),
+        ScVal::Void => "()".to_string(),
+        ScVal::U32(n) => n.to_string(),
+        ScVal::I32(n) => n.to_string(),
+        ScVal::U64(n) => n.to_string(),
+        ScVal::I64(n) => n.to_string(),
+        ScVal::Timepoint(t) => t.0.to_string(),
+        ScVal::Duration(d) => d.0.to_string(),
+        ScVal::U128(parts) => ((u128::from(parts.hi) << 64) | u128::from(parts.lo)).to_string(),
+        ScVal::I128(parts) => ((i128::from(parts.hi) << 64) | i128::from(parts.lo)).to_string(),
+        ScVal::Bytes(bytes) => format!("0x{}", hex::encode(bytes.as_slice())),
+        ScVal::String(s) => format!("{:?}", s.to_utf8_string_lossy()),
+        ScVal::Symbol(s) => s.to_utf8_string_lossy(),
+        ScVal::Vec(Some(items)) => format!(
+            "[{}]",
+            items.iter().map(display).collect::<Vec<_>>().join(", ")
+        ),
+        ScVal::Map(Some(map)) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|entry| format!("{}: {}", display(&entry.key), display(&entry.val)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ScVal::Address(ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(
+            Uint256(key),
+        )))) => stellar_strkey::ed25519::PublicKey(*key).to_string(),
+        ScVal::Address(ScAddress::Contract(hash)) => stellar_strkey::Contract(hash.0).to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️contract code is not a wasm module")]
+    NotWasm,
+    #[error(transparent)]
+    Xdr(#[from] stellar_xdr::curr::Error),
+    #[error("⛔ ️reading a storage layout: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use stellar_xdr::curr::{Int128Parts, ScMapEntry, StringM, WriteXdr};
+
+    use super::*;
+
+    /// Layouts as `#[loamstorage]` records them for a versioned `Token` and a `Log`.
+    const TOKEN: &str = r#"{"struct":"Token","version":2,"fields":[{"name":"admin","type":"InstanceItem","keys":["TokenAdmin"]},{"name":"balances","type":"PersistentMap","keys":["TokenBalances"]},{"name":"holders","type":"PersistentIterableMap","keys":["TokenHolders","TokenHoldersLen","TokenHoldersIdx","TokenHoldersPos"]}]}"#;
+    const LOG: &str = r#"{"struct":"Log","version":null,"fields":[{"name":"entries","type":"TemporaryVec","keys":["LogEntries","LogEntriesLen"]}]}"#;
+
+    fn meta(key: &str, val: &str) -> Vec<u8> {
+        ScMetaEntry::ScMetaV0(ScMetaV0 {
+            key: StringM::try_from(key).unwrap(),
+            val: StringM::try_from(val).unwrap(),
+        })
+        .to_xdr(Limits::none())
+        .unwrap()
+    }
+
+    fn section(id: u8, content: &[u8]) -> Vec<u8> {
+        let mut section = vec![id];
+        leb128(&mut section, content.len());
+        section.extend_from_slice(content);
+        section
+    }
+
+    fn custom_section(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        leb128(&mut body, name.len());
+        body.extend_from_slice(name.as_bytes());
+        body.extend_from_slice(content);
+        section(0, &body)
+    }
+
+    fn leb128(out: &mut Vec<u8>, mut value: usize) {
+        loop {
+            let byte = u8::try_from(value & 0x7f).unwrap();
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    /// A wasm module whose metadata holds the fixture layouts, split over two sections and mixed
+    /// with other entries and sections.
+    pub(in super::super) fn fixture_wasm() -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend(section(1, &[0x01, 0x60, 0x00, 0x00]));
+        let mut first = meta("rsver", "1.81.0");
+        first.extend(meta(META_KEY, TOKEN));
+        wasm.extend(custom_section(META_SECTION, &first));
+        wasm.extend(custom_section("contractspecv0", &[0; 300]));
+        let mut second = meta(META_KEY, LOG);
+        second.extend(meta(PREFIXES_KEY, r#"["lp","vault"]"#));
+        wasm.extend(custom_section(META_SECTION, &second));
+        wasm
+    }
+
+    pub(in super::super) fn fixture() -> Vec<Layout> {
+        from_wasm(&fixture_wasm()).unwrap()
+    }
+
+    #[test]
+    fn reads_layouts_from_every_meta_section() {
+        let layouts = fixture();
+        let names = layouts.iter().map(|l| l.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Token", "Log"]);
+        assert_eq!(layouts[0].version, Some(2));
+        assert_eq!(layouts[1].version, None);
+
+        let [admin, balances, holders] = &layouts[0].fields[..] else {
+            panic!("Token has three fields");
+        };
+        assert!(admin.is_instance() && !admin.is_temporary());
+        assert!(!balances.is_instance() && !balances.is_temporary());
+        assert_eq!(holders.key(0), Some("TokenHolders"));
+        assert_eq!(holders.key(1), Some("TokenHoldersLen"));
+        assert_eq!(holders.key(4), None);
+        assert!(layouts[1].fields[0].is_temporary());
+    }
+
+    #[test]
+    fn reads_prefixes() {
+        assert_eq!(prefixes(&fixture_wasm()).unwrap(), ["lp", "vault"]);
+    }
+
+    #[test]
+    fn no_layouts_without_metadata() {
+        assert!(from_wasm(b"\0asm\x01\0\0\0").unwrap().is_empty());
+        assert!(prefixes(b"\0asm\x01\0\0\0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_modules() {
+        assert!(matches!(from_wasm(b"\x7fELF"), Err(Error::NotWasm)));
+        let mut truncated = fixture_wasm();
+        truncated.truncate(truncated.len() - 1);
+        assert!(matches!(from_wasm(&truncated), Err(Error::NotWasm)));
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend(custom_section(META_SECTION, &meta(META_KEY, "{")));
+        assert!(matches!(from_wasm(&wasm), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn keys_round_trip_through_variants() {
+        let balance = key("TokenBalances", [ScVal::U32(7)]).unwrap();
+        let (name, args) = variant(&balance).unwrap();
+        assert_eq!(name, "TokenBalances");
+        assert_eq!(args, [ScVal::U32(7)]);
+        assert!(variant(&ScVal::U32(7)).is_none());
+        assert!(variant(&ScVal::Vec(Some(ScVec(
+            vec![ScVal::U32(7)].try_into().unwrap()
+        ))))
+        .is_none());
+    }
+
+    #[test]
+    fn prefixed_keys_round_trip() {
+        let balance = key("TokenBalances", [ScVal::U32(7)]).unwrap();
+        assert_eq!(prefixed(None, balance.clone()).unwrap(), balance);
+        let stored = prefixed(Some("lp"), balance.clone()).unwrap();
+        assert_eq!(display(&stored), "[lp, [TokenBalances, 7]]");
+        assert_eq!(unprefixed(Some("lp"), &stored), Some(&balance));
+        assert_eq!(unprefixed(Some("vault"), &stored), None);
+        assert_eq!(unprefixed(Some("lp"), &balance), None);
+        assert_eq!(unprefixed(None, &stored), Some(&stored));
+    }
+
+    #[test]
+    fn displays_values_compactly() {
+        let map = ScVal::Map(Some(
+            vec![ScMapEntry {
+                key: ScVal::Symbol(ScSymbol("a".try_into().unwrap())),
+                val: ScVal::I128(Int128Parts {
+                    hi: -1,
+                    lo: u64::MAX,
+                }),
+            }]
+            .try_into()
+            .unwrap(),
+        ));
+        let items = vec![
+            ScVal::Bool(true),
+            ScVal::Void,
+            ScVal::String(stellar_xdr::curr::ScString("hi".try_into().unwrap())),
+            ScVal::Bytes(vec![0xab, 0x01].try_into().unwrap()),
+            map,
+        ];
+        let vec = ScVal::Vec(Some(ScVec(items.try_into().unwrap())));
+        assert_eq!(display(&vec), r#"[true, (), "hi", 0xab01, {a: -1}]"#);
+        let contract = ScVal::Address(ScAddress::Contract(stellar_xdr::curr::Hash([0; 32])));
+        assert_eq!(
+            display(&contract),
+            stellar_strkey::Contract([0; 32]).to_string()
+        );
+    }
+}
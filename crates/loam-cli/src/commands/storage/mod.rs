@@ -0,0 +1,25 @@
+use clap::Parser;
+
+pub mod dump;
+pub mod layout;
+
+#[derive(Parser, Debug, Clone)]
+pub enum Cmd {
+    /// Print a contract's state by `#[loamstorage]` struct and field
+    Dump(dump::Cmd),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Dump(#[from] dump::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        match self {
+            Cmd::Dump(cmd) => cmd.run().await?,
+        }
+        Ok(())
+    }
+}
@@ -10,7 +10,7 @@ pub struct StatusMessage(Map<Address, String>);
 
 impl Default for StatusMessage {
     fn default() -> Self {
-        Self(Map::new(env()))
+        Self(Map::new(&env()))
     }
 }
 
@@ -0,0 +1,334 @@
+use std::{collections::BTreeMap, path::Path};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use stellar_xdr::curr as xdr;
+use syn::{
+    parse::{Parse, ParseStream},
+    Error, LitStr, Result, Token,
+};
+
+pub const LOCAL: &str = "Standalone Network ; February 2017";
+pub const TESTNET: &str = "Test SDF Network ; September 2015";
+pub const FUTURENET: &str = "Test SDF Future Network ; October 2022";
+pub const MAINNET: &str = "Public Global Stellar Network ; September 2015";
+
+/// Names `network_passphrase` knows, as listed in errors.
+const NETWORK_NAMES: &str = "`local`, `testnet`, `futurenet` or `mainnet`";
+
+/// Match network names to passphrases
+pub fn network_passphrase(s: &str) -> Option<&'static str> {
+    match s.to_lowercase().as_str() {
+        "local" | "standalone" => Some(LOCAL),
+        "testnet" => Some(TESTNET),
+        "future" | "futurenet" => Some(FUTURENET),
+        "main" | "mainnet" | "pubnet" => Some(MAINNET),
+        _ => None,
+    }
+}
+
+/// Input of `stellar_asset!`: the asset, then at most one of `network = ".."` or
+/// `passphrase = ".."`.
+pub struct AssetArgs {
+    asset: LitStr,
+    network: Option<Network>,
+}
+
+enum Network {
+    Name(LitStr),
+    Passphrase(LitStr),
+}
+
+impl Parse for AssetArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let asset = input.parse()?;
+        let mut network = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            if network.is_some() {
+                return Err(Error::new(
+                    key.span(),
+                    "only one of `network` or `passphrase` can be given",
+                ));
+            }
+            network = Some(match key.to_string().as_str() {
+                "network" => Network::Name(value),
+                "passphrase" => Network::Passphrase(value),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "expected `network = \"..\"` or `passphrase = \"..\"`",
+                    ))
+                }
+            });
+        }
+        Ok(AssetArgs { asset, network })
+    }
+}
+
+/// Where the passphrase of the network to compute the asset's contract id for comes from.
+struct Resolved {
+    passphrase: String,
+    /// `environments.toml`, if read, so that the macro runs again when it changes.
+    env_toml: Option<String>,
+}
+
+impl AssetArgs {
+    fn resolve(&self) -> Result<Resolved> {
+        let passphrase = match &self.network {
+            Some(Network::Passphrase(passphrase)) => passphrase.value(),
+            Some(Network::Name(name)) => named(&name.value())
+                .map_err(|e| Error::new(name.span(), e))?
+                .to_string(),
+            None => return self.resolve_from_env(),
+        };
+        Ok(Resolved {
+            passphrase,
+            env_toml: None,
+        })
+    }
+
+    /// The network when none is given: `STELLAR_NETWORK_PASSPHRASE`, as set by `loam build`, or
+    /// `STELLAR_NETWORK`, or the network of the current `LOAM_ENV` in `environments.toml`, or the
+    /// local network.
+    fn resolve_from_env(&self) -> Result<Resolved> {
+        let err = |e: String| Error::new(self.asset.span(), e);
+        let mut resolved = Resolved {
+            passphrase: LOCAL.to_string(),
+            env_toml: None,
+        };
+        if let Ok(passphrase) = std::env::var("STELLAR_NETWORK_PASSPHRASE") {
+            resolved.passphrase = passphrase;
+        } else if let Ok(name) = std::env::var("STELLAR_NETWORK") {
+            resolved.passphrase = named(&name)
+                .map_err(|e| err(format!("STELLAR_NETWORK: {e}")))?
+                .to_string();
+        } else if let Ok(loam_env) = std::env::var("LOAM_ENV") {
+            let path = workspace_root()
+                .map_err(|e| err(e.to_string()))?
+                .join(ENV_FILE);
+            if path.exists() {
+                resolved.passphrase = env_toml_passphrase(&path, &loam_env)
+                    .map_err(|e| err(format!("{ENV_FILE}: {e}")))?;
+                resolved.env_toml = Some(path.to_string_lossy().into_owned());
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+fn named(name: &str) -> std::result::Result<&'static str, String> {
+    network_passphrase(name).ok_or_else(|| {
+        format!(
+            "unknown network `{name}`; use one of {NETWORK_NAMES}, or give a custom network's \
+             passphrase with `passphrase = \"..\"`"
+        )
+    })
+}
+
+const ENV_FILE: &str = "environments.toml";
+
+#[derive(serde::Deserialize)]
+struct Environment {
+    network: EnvNetwork,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct EnvNetwork {
+    name: Option<String>,
+    network_passphrase: Option<String>,
+}
+
+fn workspace_root() -> std::result::Result<std::path::PathBuf, cargo_metadata::Error> {
+    Ok(cargo_metadata::MetadataCommand::new()
+        .manifest_path(crate::manifest())
+        .no_deps()
+        .exec()?
+        .workspace_root
+        .into_std_path_buf())
+}
+
+/// Passphrase of the network of `loam_env` in `environments.toml`.
+fn env_toml_passphrase(path: &Path, loam_env: &str) -> std::result::Result<String, String> {
+    let toml_str = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut environments: BTreeMap<String, Environment> =
+        toml::from_str(&toml_str).map_err(|e| e.to_string())?;
+    let network = environments
+        .remove(loam_env)
+        .ok_or_else(|| format!("no settings for current LOAM_ENV ({loam_env:?})"))?
+        .network;
+    match network {
+        EnvNetwork {
+            network_passphrase: Some(passphrase),
+            ..
+        } => Ok(passphrase),
+        EnvNetwork {
+            name: Some(name), ..
+        } => named(&name).map(ToString::to_string),
+        _ => Err(format!(
+            "the network of {loam_env:?} needs a `name` or a `network-passphrase`"
+        )),
+    }
+}
+
+pub fn generate_asset_id(
+    asset: &str,
+    network_passphrase: &str,
+) -> std::result::Result<stellar_strkey::Contract, xdr::Error> {
+    use sha2::{Digest, Sha256};
+    use xdr::WriteXdr;
+    let asset = parse_asset(asset)?;
+    let network_id = xdr::Hash(Sha256::digest(network_passphrase.as_bytes()).into());
+    let preimage = xdr::HashIdPreimage::ContractId(xdr::HashIdPreimageContractId {
+        network_id,
+        contract_id_preimage: xdr::ContractIdPreimage::Asset(asset.clone()),
+    });
+    let preimage_xdr = preimage.to_xdr(xdr::Limits::none())?;
+    Ok(stellar_strkey::Contract(
+        Sha256::digest(preimage_xdr).into(),
+    ))
+}
+
+pub fn parse_asset(str: &str) -> std::result::Result<xdr::Asset, xdr::Error> {
+    if str == "native" {
+        return Ok(xdr::Asset::Native);
+    }
+    let Some((code, issuer)) = str.split_once(':') else {
+        return Err(xdr::Error::Invalid);
+    };
+    let issuer: xdr::AccountId = issuer.parse()?;
+    let re = regex::Regex::new("^[[:alnum:]]{1,12}$").expect("regex failed");
+    if !re.is_match(code) {
+        return Err(xdr::Error::Invalid);
+    }
+    let asset_code: xdr::AssetCode = code.parse()?;
+    Ok(match asset_code {
+        xdr::AssetCode::CreditAlphanum4(asset_code) => {
+            xdr::Asset::CreditAlphanum4(xdr::AlphaNum4 { asset_code, issuer })
+        }
+        xdr::AssetCode::CreditAlphanum12(asset_code) => {
+            xdr::Asset::CreditAlphanum12(xdr::AlphaNum12 { asset_code, issuer })
+        }
+    })
+}
+
+/// A client of the asset's contract on the resolved network: `token::Client`, or
+/// `token::StellarAssetClient` for the admin interface.
+pub fn generate(args: &AssetArgs, admin: bool) -> Result<TokenStream> {
+    let Resolved {
+        passphrase,
+        env_toml,
+    } = args.resolve()?;
+    let asset_id = generate_asset_id(&args.asset.value(), &passphrase).map_err(|_| {
+        Error::new(
+            args.asset.span(),
+            "invalid asset; expected \"native\" or \"CODE:ISSUER\", e.g. \"USDC:G...\"",
+        )
+    })?;
+    let asset_id = asset_id.to_string();
+    let client = if admin {
+        quote!(StellarAssetClient)
+    } else {
+        quote!(Client)
+    };
+    let client = quote! {
+        loam_sdk::soroban_sdk::token::#client::new(
+            loam_sdk::soroban_sdk::env(),
+            &loam_sdk::soroban_sdk::Address::from_string(
+                &loam_sdk::soroban_sdk::String::from_str(loam_sdk::soroban_sdk::env(), #asset_id,)
+            )
+        )
+    };
+    Ok(match env_toml {
+        Some(path) => quote! {{
+            const _: &[u8] = include_bytes!(#path);
+            #client
+        }},
+        None => client,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::equal_tokens;
+
+    #[test]
+    fn test_generate_asset_id() {
+        let asset_id = generate_asset_id("native", LOCAL).unwrap();
+        assert_eq!(
+            asset_id.to_string(),
+            "CDMLFMKMMD7MWZP3FKUBZPVHTUEDLSX4BYGYKH4GCESXYHS3IHQ4EIG4"
+        );
+    }
+
+    #[test]
+    fn test_generate_asset_id_code() {
+        let args: AssetArgs = syn::parse_quote!("native", network = "local");
+        equal_tokens(
+            &generate(&args, false).unwrap(),
+            &quote! {
+                loam_sdk::soroban_sdk::token::Client::new(loam_sdk::soroban_sdk::env(),
+                    &loam_sdk::soroban_sdk::Address::from_string(
+                    &loam_sdk::soroban_sdk::String::from_str( loam_sdk::soroban_sdk::env(), "CDMLFMKMMD7MWZP3FKUBZPVHTUEDLSX4BYGYKH4GCESXYHS3IHQ4EIG4"))
+                )
+            },
+        );
+        let args: AssetArgs =
+            syn::parse_quote!("native", passphrase = "Standalone Network ; February 2017");
+        assert!(generate(&args, true)
+            .unwrap()
+            .to_string()
+            .contains("StellarAssetClient :: new"));
+    }
+
+    #[test]
+    fn test_asset_errors() {
+        let args: AssetArgs = syn::parse_quote!("native", network = "mynet");
+        let err = generate(&args, false).unwrap_err().to_string();
+        assert!(err.contains("unknown network `mynet`"), "{err}");
+        let args: AssetArgs = syn::parse_quote!("USDC", network = "testnet");
+        assert!(generate(&args, false).is_err());
+        assert!(
+            syn::parse_str::<AssetArgs>(r#""native", network = "testnet", passphrase = "x""#)
+                .is_err()
+        );
+        assert!(syn::parse_str::<AssetArgs>(r#""native", net = "testnet""#).is_err());
+    }
+
+    #[test]
+    fn test_env_toml_passphrase() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join(ENV_FILE);
+        std::fs::write(
+            &path,
+            r#"
+[development.network]
+name = "local"
+rpc-url = "http://localhost:8000/rpc"
+
+[staging.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "My Network ; 2024"
+
+[production.network]
+name = "mynet"
+"#,
+        )
+        .unwrap();
+        assert_eq!(env_toml_passphrase(&path, "development").unwrap(), LOCAL);
+        assert_eq!(
+            env_toml_passphrase(&path, "staging").unwrap(),
+            "My Network ; 2024"
+        );
+        assert!(env_toml_passphrase(&path, "production").is_err());
+        assert!(env_toml_passphrase(&path, "testing").is_err());
+    }
+}
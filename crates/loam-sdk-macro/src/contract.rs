@@ -1,6 +1,6 @@
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::Ident;
+use quote::{format_ident, quote};
+use syn::{Ident, Type};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -15,19 +15,69 @@ impl From<Error> for TokenStream {
     }
 }
 
-/// Find all riff deps then use `syn_file_expand` to generate the needed functions from each dep
-pub fn generate(contract: &Ident, methods: &[&TokenStream]) -> TokenStream {
+/// The contract struct along with the soroban contract exporting `methods`
+pub fn generate(
+    contract: &syn::ItemStruct,
+    soroban_vis: &syn::Visibility,
+    soroban: &Ident,
+    methods: &[&TokenStream],
+) -> TokenStream {
     quote! {
-        struct #contract;
+        #contract
         #[loam_sdk::soroban_sdk::contract(crate_path = "loam_sdk::soroban_sdk")]
-        struct SorobanContract__;
+        #soroban_vis struct #soroban;
         #[loam_sdk::soroban_sdk::contractimpl(crate_path = "loam_sdk::soroban_sdk")]
-        impl SorobanContract__ {
+        impl #soroban {
                 #(#methods)*
         }
     }
 }
 
-pub fn generate_boilerplate(name: &syn::Ident, methods: &[&TokenStream]) -> TokenStream {
-    generate(name, methods)
+pub fn generate_boilerplate(
+    contract: &syn::ItemStruct,
+    soroban_vis: &syn::Visibility,
+    soroban: &Ident,
+    methods: &[&TokenStream],
+    constructor_args: &[(Ident, Type)],
+) -> TokenStream {
+    let contract_impl = generate(contract, soroban_vis, soroban, methods);
+    let test_client = generate_test_client(contract, soroban, constructor_args);
+    quote! {
+        #contract_impl
+        #test_client
+    }
+}
+
+/// The entry point of native tests: `register_test`, registering the soroban contract in a test
+/// `Env` with the arguments of its constructor, and the type of the client it returns, named
+/// after the contract struct, e.g. `ContractClient`.
+pub fn generate_test_client(
+    contract: &syn::ItemStruct,
+    soroban: &Ident,
+    constructor_args: &[(Ident, Type)],
+) -> TokenStream {
+    let ident = &contract.ident;
+    let vis = &contract.vis;
+    let client = format_ident!("{ident}Client");
+    let soroban_client = format_ident!("{soroban}Client");
+    let (impl_generics, ty_generics, where_clause) = contract.generics.split_for_impl();
+    let (names, types): (Vec<_>, Vec<_>) = constructor_args.iter().cloned().unzip();
+    quote! {
+        /// Client of the contract in native tests, as returned by `register_test`.
+        #[cfg(not(target_family = "wasm"))]
+        #vis type #client<'a> = #soroban_client<'a>;
+
+        #[cfg(not(target_family = "wasm"))]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Register the contract in the test `env`, passing the given arguments to its
+            /// constructor, and get a client calling it.
+            pub fn register_test<'a>(
+                env: &'a loam_sdk::soroban_sdk::Env,
+                #(#names: #types),*
+            ) -> #soroban_client<'a> {
+                let id = env.register(#soroban, (#(#names,)*));
+                #soroban_client::new(env, &id)
+            }
+        }
+    }
 }
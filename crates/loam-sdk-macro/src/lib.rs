@@ -8,12 +8,25 @@ use subcontract::storage;
 use quote::quote;
 use syn::Item;
 
+mod asset;
 mod contract;
 mod subcontract;
 mod util;
 
 /// Generates a companion Trait which has a default type `Impl`, which implements this trait.
 ///
+/// Methods marked `#[constructor]` are not exported by `derive_contract` but called from the
+/// contract's `__constructor`. They cannot return a value.
+///
+/// The events the subcontract publishes can be declared on the trait with
+/// `#[events(my_crate::Transfer, ..)]`, so that contracts mounting it describe them in their spec.
+///
+/// Methods can require authorization with `#[require_auth(address)]`,
+/// `#[require_auth_for_args(address, arg, ..)]` or `#[only_admin]`, and be rejected while the
+/// contract is paused with `#[when_not_paused]`, which the companion trait's methods check before
+/// calling into `Impl`. A `&self` method returning `bool` marked `#[pause_state]` provides that
+/// state to the contracts mounting the subcontract.
+///
 /// # Panics
 ///
 /// This macro will panic if:
@@ -34,7 +47,16 @@ pub fn into_key(item: TokenStream) -> TokenStream {
         .map_or_else(|e| e.to_compile_error().into(), Into::into)
 }
 
-#[proc_macro_derive(Lazy)]
+/// Load and store a type as a single storage entry.
+///
+/// Unit structs hold no state. Other structs, which must be `#[contracttype]`s, are kept under a
+/// `Symbol` key chosen with `#[lazy(instance, key = "NAME")]`: one of `persistent` (the default),
+/// `instance` or `temporary`, and a key defaulting to the struct's name. A
+/// `#[ttl(threshold = .., extend_to = ..)]` attribute extends the entry as with `#[loamstorage]`.
+///
+/// A `&mut self` subcontract method only writes the entry back if it changed, so a TTL extended
+/// on write is not extended by calls that leave the struct as it was.
+#[proc_macro_derive(Lazy, attributes(lazy, ttl))]
 pub fn lazy(item: TokenStream) -> TokenStream {
     syn::parse::<Item>(item)
         .and_then(subcontract::lazy::from_item)
@@ -51,28 +73,31 @@ pub(crate) fn manifest() -> std::path::PathBuf {
 /// Generates a contract Client for a given contract.
 /// It is expected that the name should be the same as the published contract or a contract in your current workspace.
 ///
-/// # Panics
-///
-/// This function may panic in the following situations:
-/// - If `loam_build::get_target_dir()` fails to retrieve the target directory
-/// - If the input tokens cannot be parsed as a valid identifier
-/// - If the directory path cannot be canonicalized
-/// - If the canonical path cannot be converted to a string
+/// A workspace contract is built first if its wasm is missing from `target/loam` or older than
+/// its sources or those of its path dependencies, so tests and editors work before `loam build`
+/// has run. When the contract cannot be found or built, the error is reported on the name.
 #[proc_macro]
 pub fn import_contract(tokens: TokenStream) -> TokenStream {
-    let cargo_file = manifest();
-    let mut dir = loam_build::get_target_dir(&cargo_file)
-        .unwrap()
-        .join(tokens.to_string());
-    let name = syn::parse::<syn::Ident>(tokens).expect("The input must be a valid identifier");
-    dir.set_extension("wasm");
-    let binding = dir.canonicalize().unwrap();
-    let file = binding.to_str().unwrap();
+    let name = match syn::parse::<syn::Ident>(tokens) {
+        Ok(name) => name,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let wasm = match loam_build::wasm::contract_wasm(&manifest(), &name.to_string()) {
+        Ok(wasm) => wasm,
+        Err(e) => {
+            return syn::Error::new(name.span(), format!("cannot import contract `{name}`: {e}"))
+                .to_compile_error()
+                .into()
+        }
+    };
+    let file = wasm.to_string_lossy();
     quote! {
         mod #name {
             #![allow(clippy::ref_option)]
             use loam_sdk::soroban_sdk;
             loam_sdk::soroban_sdk::contractimport!(file = #file);
+            // Tracks the wasm, so that the client is generated again when it is rebuilt.
+            const _: &[u8] = include_bytes!(#file);
         }
     }
     .into()
@@ -103,6 +128,19 @@ pub fn import_contract(tokens: TokenStream) -> TokenStream {
 ///
 /// ```
 ///
+/// Methods can be left out of the contract or exported under another name, e.g.
+/// `Core(Admin, exclude(redeploy))` or `Sep41(MyToken, rename(balance = token_balance))`.
+/// Two subcontracts exporting a method under the same name is a compile error.
+///
+/// The generated soroban contract is named `SorobanContract__`, or as given with
+/// `soroban_contract = Name`.
+///
+/// The `#[constructor]` methods of the subcontracts are called, in the order they are mounted, by
+/// a generated `__constructor` taking all their arguments.
+///
+/// Outside wasm, the struct also gets `register_test(&env, constructor args..)`, which registers
+/// the contract in a test `Env` and returns a client for it, of type `{Struct}Client`.
+///
 /// # Panics
 /// This function may panic if the input tokens cannot be parsed as a valid Rust item.
 ///
@@ -112,25 +150,72 @@ pub fn derive_contract(args: TokenStream, item: TokenStream) -> TokenStream {
     derive_contract_impl(proc_macro2::TokenStream::from(args), parsed).into()
 }
 
+/// Continues `derive_contract` once the macro of a mounted subcontract has added its trait.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __derive_contract(input: TokenStream) -> TokenStream {
+    syn::parse::<subcontract::Collected>(input)
+        .map_or_else(|e| e.to_compile_error(), subcontract::collect_traits)
+        .into()
+}
+
 /// Generates a contract Client for a given asset.
 /// It is expected that the name of an asset, e.g. "native" or "USDC:G1...."
 ///
-/// # Panics
-///
+/// The asset's contract id depends on the network, which can be given as
+/// `stellar_asset!("USDC:G1....", network = "testnet")`, one of `local`, `testnet`, `futurenet`
+/// or `mainnet`, or for a custom network as `stellar_asset!("native", passphrase = "..")`.
+/// Otherwise it is read at compile time from `STELLAR_NETWORK_PASSPHRASE`, as set by
+/// `loam build`, then `STELLAR_NETWORK`, then the network of the current `LOAM_ENV` in
+/// `environments.toml`, and defaults to `local`. Unknown networks and invalid assets are compile
+/// errors.
 #[proc_macro]
 pub fn stellar_asset(input: TokenStream) -> TokenStream {
-    // Parse the input as a string literal
-    let input_str = syn::parse_macro_input!(input as syn::LitStr);
-    let network = std::env::var("STELLAR_NETWORK").unwrap_or_else(|_| "local".to_owned());
-    let asset = util::parse_asset_literal(&input_str, &network);
+    let args = syn::parse_macro_input!(input as asset::AssetArgs);
+    asset::generate(&args, false)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Like [`stellar_asset!`], but generates a `StellarAssetClient`, the admin interface of the
+/// asset's contract, e.g. to `mint` or `set_admin`.
+#[proc_macro]
+pub fn stellar_asset_admin(input: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(input as asset::AssetArgs);
+    asset::generate(&args, true)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
 
-    // Return the generated code as a TokenStream
-    asset.into()
+/// Turns a struct into a typed contract event, published with `publish()`:
+///
+/// ```ignore
+/// #[loam_event]
+/// pub struct Transfer {
+///     #[topic]
+///     from: Address,
+///     #[topic]
+///     to: Address,
+///     amount: i128,
+/// }
+///
+/// Transfer { from, to, amount }.publish();
+/// ```
+///
+/// The first topic is the event's name, the struct's name in snake case unless set with
+/// `#[loam_event(name = "..")]`, followed by the `#[topic]` fields. The other fields are the
+/// data: a single value, or a tuple of them. The struct is a `contracttype`, so it is described in
+/// the contract spec of contracts mounting a subcontract that declares it with `#[events(..)]`.
+#[proc_macro_attribute]
+pub fn loam_event(attr: TokenStream, item: TokenStream) -> TokenStream {
+    syn::parse::<Item>(item)
+        .and_then(|item| subcontract::event::from_item(attr.into(), item))
+        .map_or_else(|e| e.to_compile_error().into(), Into::into)
 }
 
 #[proc_macro_attribute]
-pub fn loamstorage(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn loamstorage(attr: TokenStream, item: TokenStream) -> TokenStream {
     syn::parse::<Item>(item)
-        .and_then(storage::from_item)
+        .and_then(|item| storage::from_item(attr.into(), item))
         .map_or_else(|e| e.to_compile_error().into(), Into::into)
 }
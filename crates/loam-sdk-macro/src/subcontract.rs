@@ -1,17 +1,32 @@
+use std::{collections::BTreeMap, fmt};
+
+use heck::ToUpperCamelCase;
 use itertools::Itertools;
-use proc_macro2::{Group, Ident, TokenStream, TokenTree};
-use quote::quote;
-use syn::{punctuated::Punctuated, Attribute, FnArg, Item, Signature, Token};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    bracketed,
+    fold::Fold,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    AngleBracketedGenericArguments, Attribute, FnArg, Item, Signature, Token, Type,
+};
 
 use crate::{
     contract,
-    util::{TraitVisitor, Traits},
+    util::{generate_constructor, generate_methods, Traits},
 };
 
+pub mod auth;
+pub mod event;
 pub mod into_key;
 pub mod lazy;
 pub mod storage;
 
+use auth::Auth;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("{0}")]
@@ -40,36 +55,110 @@ fn is_result_type(output: &syn::ReturnType) -> bool {
     }
     false
 }
-fn generate_method(trait_item: &syn::TraitItem) -> Option<TokenStream> {
-    if let syn::TraitItem::Fn(method) = trait_item {
-        let sig = &method.sig;
-        let name = &sig.ident;
-        let output = &sig.output;
-        let self_ty = get_receiver(sig.inputs.iter().next()?)?;
-
-        let is_result = is_result_type(output);
-        let args_without_self = get_args_without_self(&sig.inputs);
-        let attrs = &method.attrs;
-        let return_question_mark = if is_result { Some(quote!(?)) } else { None };
-
-        if is_mutable_method(self_ty) {
-            Some(generate_mutable_method(
-                sig,
-                attrs,
-                name,
-                &args_without_self,
-                return_question_mark.as_ref(),
-            ))
-        } else {
-            Some(generate_immutable_method(
-                sig,
-                attrs,
-                name,
-                &args_without_self,
-            ))
+/// Items of the companion trait forwarding to the subcontract trait `as_trait`, which is
+/// `<Self::Impl as IsX>`. Associated types stay on the subcontract trait, so paths to them are
+/// qualified with `as_trait`.
+fn generate_item(
+    trait_item: &syn::TraitItem,
+    as_trait: &TokenStream,
+    assoc_types: &[Ident],
+) -> syn::Result<Option<TokenStream>> {
+    let mut qualify = QualifySelf {
+        as_trait,
+        assoc_types,
+    };
+    match trait_item {
+        syn::TraitItem::Const(item) => {
+            let syn::TraitItemConst {
+                attrs, ident, ty, ..
+            } = item;
+            let ty = qualify.fold_type(ty.clone());
+            Ok(Some(quote! {
+                #(#attrs)*
+                const #ident: #ty = #as_trait::#ident;
+            }))
         }
-    } else {
-        None
+        syn::TraitItem::Fn(method) => {
+            let auths = Auth::from_method(method)?;
+            let mut sig = qualify.fold_signature(method.sig.clone());
+            if auths.iter().any(|auth| matches!(auth, Auth::Admin)) {
+                sig.generics
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote!(Self: loam_sdk::soroban_sdk::HasAdmin));
+            }
+            if auths.iter().any(|auth| matches!(auth, Auth::NotPaused)) {
+                sig.generics
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote!(Self: loam_sdk::soroban_sdk::HasPause));
+            }
+            let sig = &sig;
+            let checks = auths.iter().map(Auth::check).collect::<TokenStream>();
+            let name = &sig.ident;
+            let attrs = method
+                .attrs
+                .iter()
+                .filter(|attr| !is_loam_attr(attr))
+                .cloned()
+                .collect::<Vec<_>>();
+            let attrs = &attrs;
+            let args_without_self = get_args_without_self(&sig.inputs);
+            let Some(self_ty) = sig.inputs.iter().next().and_then(get_receiver) else {
+                return Ok(Some(generate_static_method(
+                    sig,
+                    attrs,
+                    &checks,
+                    as_trait,
+                    &args_without_self,
+                )));
+            };
+            let is_result = is_result_type(&sig.output);
+            let return_question_mark = if is_result { Some(quote!(?)) } else { None };
+
+            if is_mutable_method(self_ty) {
+                Ok(Some(generate_mutable_method(
+                    sig,
+                    attrs,
+                    &checks,
+                    name,
+                    &args_without_self,
+                    return_question_mark.as_ref(),
+                )))
+            } else {
+                Ok(Some(generate_immutable_method(
+                    sig,
+                    attrs,
+                    &checks,
+                    name,
+                    &args_without_self,
+                )))
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Rewrites `Self::Assoc` to `<Self::Impl as IsX>::Assoc` for the associated types of `IsX`.
+struct QualifySelf<'a> {
+    as_trait: &'a TokenStream,
+    assoc_types: &'a [Ident],
+}
+
+impl Fold for QualifySelf<'_> {
+    fn fold_type_path(&mut self, ty: syn::TypePath) -> syn::TypePath {
+        let ty = syn::fold::fold_type_path(self, ty);
+        let segments = &ty.path.segments;
+        if ty.qself.is_none()
+            && segments.len() > 1
+            && segments[0].ident == "Self"
+            && self.assoc_types.contains(&segments[1].ident)
+        {
+            let as_trait = self.as_trait;
+            let rest = segments.iter().skip(1);
+            return syn::parse_quote!(#as_trait::#(#rest)::*);
+        }
+        ty
     }
 }
 
@@ -84,7 +173,6 @@ fn get_receiver(arg: &syn::FnArg) -> Option<&syn::Receiver> {
 pub fn get_args_without_self(inputs: &Punctuated<FnArg, Token!(,)>) -> Vec<Ident> {
     inputs
         .iter()
-        .skip(1)
         .filter_map(|arg| {
             if let syn::FnArg::Typed(syn::PatType { pat, .. }) = arg {
                 match &**pat {
@@ -101,17 +189,45 @@ pub fn get_args_without_self(inputs: &Punctuated<FnArg, Token!(,)>) -> Vec<Ident
 fn is_mutable_method(receiver: &syn::Receiver) -> bool {
     receiver.reference.is_some() && receiver.mutability.is_some()
 }
+fn generate_static_method(
+    sig: &Signature,
+    attrs: &[Attribute],
+    checks: &TokenStream,
+    as_trait: &TokenStream,
+    args_without_self: &[Ident],
+) -> TokenStream {
+    let Signature {
+        ident,
+        generics,
+        inputs,
+        output,
+        ..
+    } = sig;
+    let where_clause = &generics.where_clause;
+    quote! {
+        #(#attrs)*
+        fn #ident #generics(#inputs) #output #where_clause {
+            #checks
+            #as_trait::#ident(#(#args_without_self),*)
+        }
+    }
+}
+
 fn generate_immutable_method(
     sig: &Signature,
     attrs: &[Attribute],
+    checks: &TokenStream,
     name: &Ident,
     args_without_self: &[Ident],
 ) -> TokenStream {
     let inputs = sig.inputs.iter().skip(1);
     let output = &sig.output;
+    let generics = &sig.generics;
+    let where_clause = &generics.where_clause;
     quote! {
         #(#attrs)*
-        fn #name(#(#inputs),*) #output {
+        fn #name #generics(#(#inputs),*) #output #where_clause {
+            #checks
             Self::Impl::get_lazy().unwrap_or_default().#name(#(#args_without_self),*)
         }
     }
@@ -120,12 +236,15 @@ fn generate_immutable_method(
 fn generate_mutable_method(
     sig: &Signature,
     attrs: &[Attribute],
+    checks: &TokenStream,
     name: &Ident,
     args_without_self: &[Ident],
     return_question_mark: Option<&TokenStream>,
 ) -> TokenStream {
     let inputs = sig.inputs.iter().skip(1);
     let output = &sig.output;
+    let generics = &sig.generics;
+    let where_clause = &generics.where_clause;
     let result = if return_question_mark.is_some() {
         quote!(Ok(res))
     } else {
@@ -133,10 +252,12 @@ fn generate_mutable_method(
     };
     quote! {
         #(#attrs)*
-        fn #name(#(#inputs),*) #output {
+        fn #name #generics(#(#inputs),*) #output #where_clause {
+            #checks
             let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+            let snapshot = Self::Impl::snapshot(&impl_);
             let res = impl_.#name(#(#args_without_self),*) #return_question_mark;
-            Self::Impl::set_lazy(impl_);
+            Self::Impl::set_lazy_if_changed(impl_, snapshot);
             #result
         }
     }
@@ -144,31 +265,59 @@ fn generate_mutable_method(
 
 fn inner_generate(item: &Item) -> Result<TokenStream, Error> {
     if let Item::Trait(input_trait) = &item {
-        let generated_methods = input_trait
+        // `derive_contract` gets the trait as written; the `#[constructor]` and authorization
+        // attributes only mean something to the macros.
+        let original = input_trait;
+        let input_trait = &without_loam_attrs(input_trait)?;
+        let trait_ident = &input_trait.ident;
+        let (impl_generics, ty_generics, where_clause) = input_trait.generics.split_for_impl();
+        let as_trait = quote!(<Self::Impl as #trait_ident #ty_generics>);
+        let assoc_types = input_trait
             .items
             .iter()
-            .filter_map(generate_method)
+            .filter_map(|item| match item {
+                syn::TraitItem::Type(ty) => Some(ty.ident.clone()),
+                _ => None,
+            })
             .collect::<Vec<_>>();
+        let generated_items = original
+            .items
+            .iter()
+            .filter_map(|item| generate_item(item, &as_trait, &assoc_types).transpose())
+            .collect::<syn::Result<Vec<_>>>()
+            .map_err(|e| Error::Stream(e.to_compile_error()))?;
 
-        let trait_ident = &input_trait.ident;
         let new_trait_ident = syn::Ident::new(
             trait_ident.to_string().strip_prefix("Is").ok_or_else(|| {
                 Error::Stream(quote! { compile_error!("Trait must start with `Is`"); })
             })?,
             trait_ident.span(),
         );
-        let (_, ty_generics, _) = input_trait.generics.split_for_impl();
 
         let attrs = input_trait.attrs.as_slice();
+        let macro_ident = format_ident!("__loam_subcontract_{new_trait_ident}");
         let output = quote! {
-            #item
+            #input_trait
             #(#attrs)*
-            pub trait #new_trait_ident #ty_generics {
+            pub trait #new_trait_ident #impl_generics #where_clause {
                 /// Type that implments the instance type
                 type Impl: Lazy + #trait_ident #ty_generics + Default;
-                #(#generated_methods)*
+                #(#generated_items)*
             }
 
+            // Passes the definition of the subcontract trait on to `derive_contract`, which
+            // finds this macro under the companion trait's name. Paths in the trait, such as
+            // declared events, are meant to resolve where it is mounted.
+            #[doc(hidden)]
+            #[allow(clippy::crate_in_macro_def)]
+            #[macro_export]
+            macro_rules! #macro_ident {
+                ([$($callback:tt)*] { $($state:tt)* }) => {
+                    $($callback)*! { $($state)* #item }
+                };
+            }
+            #[doc(hidden)]
+            pub use #macro_ident as #new_trait_ident;
         };
         Ok(output)
     } else {
@@ -178,73 +327,645 @@ fn inner_generate(item: &Item) -> Result<TokenStream, Error> {
     }
 }
 
-pub fn derive_contract_impl(args: TokenStream, trait_impls: Item) -> TokenStream {
-    let Item::Struct(strukt) = trait_impls else {
+/// Whether `method` is a constructor hook, marked with `#[constructor]`. `derive_contract` calls
+/// these from the contract's `__constructor` instead of exporting them.
+pub fn is_constructor(method: &syn::TraitItemFn) -> bool {
+    method
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("constructor"))
+}
+
+/// Whether `attr` is one of the attributes `#[subcontract]` and `derive_contract` read on the
+/// methods of a subcontract trait.
+pub fn is_loam_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("constructor")
+        || attr.path().is_ident("pause_state")
+        || auth::is_auth_attr(attr)
+}
+
+/// The events a subcontract trait declares it publishes, with `#[events(my_crate::Transfer, ..)]`.
+/// The paths are resolved where the subcontract is mounted.
+pub fn declared_events(item: &syn::ItemTrait) -> syn::Result<Vec<syn::Path>> {
+    let mut events = Vec::new();
+    for attr in item
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("events"))
+    {
+        events.extend(attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)?);
+    }
+    Ok(events)
+}
+
+fn without_loam_attrs(input_trait: &syn::ItemTrait) -> Result<syn::ItemTrait, Error> {
+    declared_events(input_trait).map_err(|e| Error::Stream(e.to_compile_error()))?;
+    let mut input_trait = input_trait.clone();
+    input_trait
+        .attrs
+        .retain(|attr| !attr.path().is_ident("events"));
+    for item in &mut input_trait.items {
+        let syn::TraitItem::Fn(method) = item else {
+            continue;
+        };
+        if is_constructor(method) {
+            if let syn::ReturnType::Type(_, ty) = &method.sig.output {
+                return Err(Error::Stream(quote::quote_spanned! {ty.span()=>
+                    compile_error!("constructor hooks cannot return a value");
+                }));
+            }
+        }
+        method.attrs.retain(|attr| !is_loam_attr(attr));
+    }
+    Ok(input_trait)
+}
+
+/// A subcontract listed in `derive_contract`, e.g. `Sep41(MyToken)` or `Sep41(MyToken) as lp`.
+/// Generic subcontracts are mounted with concrete types, e.g. `Vault<Usdc>(MyVault<Usdc>)`.
+pub struct Mount {
+    pub trait_ident: Ident,
+    /// Generic arguments of the subcontract trait, if it has any.
+    pub trait_args: Option<AngleBracketedGenericArguments>,
+    pub impl_ty: Type,
+    /// Methods not exported by the contract, from `exclude(..)`.
+    pub exclude: Vec<Ident>,
+    /// Methods exported under another name, from `rename(method = name, ..)`.
+    pub rename: Vec<(Ident, Ident)>,
+    /// Prefix of the mount's storage keys and exported method names.
+    pub prefix: Option<Ident>,
+}
+
+impl Mount {
+    /// Type implementing the subcontract trait: the `contract` struct, or a marker type per
+    /// prefix, e.g. `ContractLpMount__`.
+    pub fn implementor(&self, contract: &Ident) -> Ident {
+        match &self.prefix {
+            Some(prefix) => format_ident!(
+                "{contract}{}Mount__",
+                prefix.to_string().to_upper_camel_case()
+            ),
+            None => contract.clone(),
+        }
+    }
+
+    /// Name the contract exports `method` under, or `None` if it is excluded. Renamed methods
+    /// are exported under exactly the new name; others get the mount's prefix, if any.
+    pub fn exported_name(&self, method: &Ident) -> Option<Ident> {
+        if self.exclude.contains(method) {
+            return None;
+        }
+        if let Some((_, name)) = self.rename.iter().find(|(from, _)| from == method) {
+            return Some(name.clone());
+        }
+        Some(match &self.prefix {
+            Some(prefix) => format_ident!("{prefix}_{method}"),
+            None => method.clone(),
+        })
+    }
+
+    /// The companion trait with its generic arguments, e.g. `Vault<Usdc>`.
+    pub fn trait_path(&self) -> TokenStream {
+        let Mount {
+            trait_ident,
+            trait_args,
+            ..
+        } = self;
+        quote!(#trait_ident #trait_args)
+    }
+}
+
+impl fmt::Display for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compact = |tokens: TokenStream| tokens.to_string().replace(' ', "");
+        write!(
+            f,
+            "{}({})",
+            compact(self.trait_path()),
+            compact(self.impl_ty.to_token_stream())
+        )?;
+        if let Some(prefix) = &self.prefix {
+            write!(f, " as {prefix}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for Mount {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_ident = input.parse()?;
+        let trait_args = if input.peek(Token![<]) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let content;
+        parenthesized!(content in input);
+        let impl_ty = content.parse()?;
+        let mut exclude = Vec::new();
+        let mut rename = Vec::new();
+        while !content.is_empty() {
+            content.parse::<Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let option: Ident = content.parse()?;
+            let methods;
+            parenthesized!(methods in content);
+            match option.to_string().as_str() {
+                "exclude" => {
+                    exclude.extend(Punctuated::<Ident, Token![,]>::parse_terminated(&methods)?);
+                }
+                "rename" => {
+                    let pairs = methods.parse_terminated(
+                        |pair| {
+                            let from: Ident = pair.parse()?;
+                            pair.parse::<Token![=]>()?;
+                            Ok((from, pair.parse::<Ident>()?))
+                        },
+                        Token![,],
+                    )?;
+                    rename.extend(pairs);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        option.span(),
+                        "expected `exclude(method, ..)` or `rename(method = name, ..)`",
+                    ))
+                }
+            }
+        }
+        let prefix = if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Mount {
+            trait_ident,
+            trait_args,
+            impl_ty,
+            exclude,
+            rename,
+            prefix,
+        })
+    }
+}
+
+pub fn derive_contract_impl(args: TokenStream, item: Item) -> TokenStream {
+    let Item::Struct(_) = &item else {
         panic!("Expected a struct")
     };
-    let mut impls = TokenStream::new();
-    let idents: Vec<(Ident, Ident)> = parse_idents(args);
+    let contract_args = match syn::parse2::<ContractArgs>(args.clone()) {
+        Ok(contract_args) => contract_args,
+        Err(e) => return e.to_compile_error(),
+    };
+    let remaining = contract_args
+        .mounts
+        .iter()
+        .map(|mount| mount.trait_ident.clone())
+        .unique()
+        .collect();
+    collect_traits(Collected {
+        args,
+        item,
+        remaining,
+        traits: Vec::new(),
+    })
+}
+
+/// State passed along while the macros of the mounted subcontracts add their trait definitions.
+pub struct Collected {
+    args: TokenStream,
+    item: Item,
+    /// Companion traits whose definitions are still to be added.
+    remaining: Vec<Ident>,
+    traits: Vec<syn::ItemTrait>,
+}
+
+impl Parse for Collected {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (args, item, remaining);
+        bracketed!(args in input);
+        bracketed!(item in input);
+        bracketed!(remaining in input);
+        let mut traits = Vec::new();
+        while !input.is_empty() {
+            traits.push(input.parse()?);
+        }
+        Ok(Collected {
+            args: args.parse()?,
+            item: item.parse()?,
+            remaining: std::iter::from_fn(|| remaining.parse().ok()).collect(),
+            traits,
+        })
+    }
+}
 
-    let all_traits = find_deps();
+/// Calls the macro `#[subcontract]` generates for the next remaining trait, which adds the trait's
+/// definition and calls `__derive_contract` again, until all are found and the contract can be
+/// generated.
+pub fn collect_traits(collected: Collected) -> TokenStream {
+    let Collected {
+        args,
+        item,
+        mut remaining,
+        traits,
+    } = collected;
+    if remaining.is_empty() {
+        let Item::Struct(strukt) = item else {
+            panic!("Expected a struct")
+        };
+        let contract_args = syn::parse2(args).expect("arguments were parsed by derive_contract");
+        let all_traits = traits
+            .into_iter()
+            .map(|item| (item.ident.to_string(), item))
+            .collect();
+        return generate_contract(&contract_args, &strukt, &all_traits);
+    }
+    let next = remaining.remove(0);
+    quote! {
+        #next! { [loam_sdk::__derive_contract] { [#args] [#item] [#(#remaining)*] #(#traits)* } }
+    }
+}
 
-    let methods = idents
+fn generate_contract(
+    ContractArgs {
+        mounts,
+        soroban_contract,
+    }: &ContractArgs,
+    strukt: &syn::ItemStruct,
+    all_traits: &Traits,
+) -> TokenStream {
+    let contract = &strukt.ident;
+    // On a conflict, export no methods so that the error is the only one reported.
+    let checked = check_exported_methods(mounts, all_traits);
+    let methods = mounts
         .iter()
-        .filter_map(|(first, _)| all_traits.get(&format!("Is{first}")))
+        .filter(|_| checked.is_ok())
+        .filter_map(|mount| {
+            all_traits
+                .get(&format!("Is{}", mount.trait_ident))
+                .map(|item| generate_methods(item, mount, strukt))
+        })
         .flatten()
         .collect::<Vec<_>>();
+    let (constructor, constructor_args) = checked
+        .is_ok()
+        .then(|| constructor(mounts, strukt, all_traits))
+        .flatten()
+        .unzip();
+    let methods = methods.into_iter().chain(constructor).collect::<Vec<_>>();
+    let methods = methods.iter().collect::<Vec<_>>();
 
-    for (first, second) in idents {
+    let mut impls = TokenStream::new();
+    for implementor in mounts
+        .iter()
+        .filter(|mount| mount.prefix.is_some())
+        .map(|mount| mount.implementor(contract))
+        .unique()
+    {
         impls.extend(quote! {
-            impl #first for Contract {
-                type Impl = #second;
+            struct #implementor;
+        });
+    }
+    let (impl_generics, ty_generics, where_clause) = strukt.generics.split_for_impl();
+    for mount in mounts {
+        let trait_path = mount.trait_path();
+        let impl_ty = &mount.impl_ty;
+        impls.extend(if mount.prefix.is_some() {
+            let implementor = mount.implementor(contract);
+            quote! {
+                impl #trait_path for #implementor {
+                    type Impl = #impl_ty;
+                }
+            }
+        } else {
+            quote! {
+                impl #impl_generics #trait_path for #contract #ty_generics #where_clause {
+                    type Impl = #impl_ty;
+                }
             }
         });
     }
-    let outer_impl = contract::generate_boilerplate(&strukt.ident, &methods);
+    impls.extend(generate_has_admin(mounts, strukt));
+    impls.extend(generate_has_pause(mounts, strukt, all_traits));
+    let key_checks = generate_key_checks(mounts, contract);
+    let events = generate_event_specs(mounts, all_traits);
+    let prefixes = generate_prefix_meta(mounts);
+    // A named soroban contract is meant to be used, so it is as visible as the struct.
+    let (soroban_vis, soroban_contract) = match soroban_contract {
+        Some(name) => (strukt.vis.clone(), name.clone()),
+        None => (
+            syn::Visibility::Inherited,
+            format_ident!("SorobanContract__"),
+        ),
+    };
+    let outer_impl = contract::generate_boilerplate(
+        strukt,
+        &soroban_vis,
+        &soroban_contract,
+        &methods,
+        constructor_args.as_deref().unwrap_or_default(),
+    );
+    let checked = checked.err().map(|e| e.to_compile_error());
     quote! {
+        #checked
         #outer_impl
         #impls
+        #key_checks
+        #events
+        #prefixes
+    }
+}
+
+/// `HasAdmin` for the contract, and each prefix, with a `Core` mount: `#[only_admin]` methods get
+/// the admin from the `Core` mounted under the same prefix.
+fn generate_has_admin(mounts: &[Mount], strukt: &syn::ItemStruct) -> TokenStream {
+    let contract = &strukt.ident;
+    let (impl_generics, ty_generics, where_clause) = strukt.generics.split_for_impl();
+    let mut impls = TokenStream::new();
+    for mount in mounts
+        .iter()
+        .filter(|mount| mount.trait_ident == "Core")
+        .unique_by(|mount| mount.prefix.as_ref().map(ToString::to_string))
+    {
+        let trait_path = mount.trait_path();
+        let admin = quote! {
+            fn admin() -> Option<loam_sdk::soroban_sdk::Address> {
+                <Self as #trait_path>::admin_get()
+            }
+        };
+        impls.extend(if mount.prefix.is_some() {
+            let implementor = mount.implementor(contract);
+            quote! {
+                impl loam_sdk::soroban_sdk::HasAdmin for #implementor {
+                    #admin
+                }
+            }
+        } else {
+            quote! {
+                impl #impl_generics loam_sdk::soroban_sdk::HasAdmin for #contract #ty_generics #where_clause {
+                    #admin
+                }
+            }
+        });
     }
+    impls
 }
 
-fn find_deps() -> Traits {
-    let cargo_file = crate::manifest();
-    loam_build::deps::subcontract(&cargo_file)
-        .map_err(|e| panic!("{e:#?}"))
-        .expect("failed to find all contract deps")
+/// `HasPause` for the contract, and each prefix, with `#[when_not_paused]` methods or pause state:
+/// a subcontract with a `#[pause_state]` method, such as `Pausable`. It asks the pause state mounted
+/// under the same prefix, or else the one mounted at the root. Without either, the contract cannot
+/// be paused, so guarded methods always run.
+fn generate_has_pause(
+    mounts: &[Mount],
+    strukt: &syn::ItemStruct,
+    all_traits: &Traits,
+) -> TokenStream {
+    let contract = &strukt.ident;
+    let (impl_generics, ty_generics, where_clause) = strukt.generics.split_for_impl();
+    let methods = |mount: &Mount| {
+        all_traits
+            .get(&format!("Is{}", mount.trait_ident))
+            .into_iter()
+            .flat_map(|item| &item.items)
+            .filter_map(|item| match item {
+                syn::TraitItem::Fn(method) => Some(method),
+                _ => None,
+            })
+    };
+    let has_attr = |method: &syn::TraitItemFn, name| {
+        method.attrs.iter().any(|attr| attr.path().is_ident(name))
+    };
+    let is_guarded =
+        |mount: &Mount| methods(mount).any(|method| has_attr(method, "when_not_paused"));
+    let pause_states = mounts
         .iter()
-        .map(|i| i.manifest_path.as_std_path())
-        .chain([cargo_file.as_path()])
-        .filter_map(|path| {
-            let path = path.parent().unwrap().join("src/lib.rs");
-            let res = crate::util::parse_crate_as_file(&path)?;
-            Some(TraitVisitor::find_traits_in_file(&res))
+        .filter_map(|mount| {
+            let method = methods(mount).find(|method| has_attr(method, "pause_state"))?;
+            Some((mount, &method.sig.ident))
+        })
+        .collect::<Vec<_>>();
+    let pause_state = |prefix: &Option<Ident>| {
+        pause_states
+            .iter()
+            .find(|(state, _)| &state.prefix == prefix)
+    };
+    mounts
+        .iter()
+        .filter(|mount| is_guarded(mount) || pause_state(&mount.prefix).is_some())
+        .unique_by(|mount| mount.prefix.as_ref().map(ToString::to_string))
+        .map(|mount| {
+            let paused = match pause_state(&mount.prefix).or_else(|| pause_state(&None)) {
+                Some((state, method)) if state.prefix == mount.prefix => {
+                    let trait_path = state.trait_path();
+                    quote!(<Self as #trait_path>::#method())
+                }
+                Some((state, method)) => {
+                    let trait_path = state.trait_path();
+                    let implementor = state.implementor(contract);
+                    quote!(<#implementor as #trait_path>::#method())
+                }
+                None => quote!(false),
+            };
+            if mount.prefix.is_some() {
+                let implementor = mount.implementor(contract);
+                quote! {
+                    impl loam_sdk::soroban_sdk::HasPause for #implementor {
+                        fn paused() -> bool {
+                            #paused
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    impl #impl_generics loam_sdk::soroban_sdk::HasPause for #contract #ty_generics #where_clause {
+                        fn paused() -> bool {
+                            #paused
+                        }
+                    }
+                }
+            }
         })
-        .flatten()
         .collect()
 }
 
-fn parse_idents(item: TokenStream) -> Vec<(Ident, Ident)> {
-    item.into_iter()
-        .filter_map(extract_ident)
-        .tuples()
-        .collect()
+/// Keeps the spec entries of the events the mounted subcontracts declare in the contract, so that
+/// they are described in its spec and bindings even when defined in another crate.
+fn generate_event_specs(mounts: &[Mount], all_traits: &Traits) -> Option<TokenStream> {
+    let events = mounts
+        .iter()
+        .filter_map(|mount| all_traits.get(&format!("Is{}", mount.trait_ident)))
+        .flat_map(|item| declared_events(item).unwrap_or_default())
+        .unique_by(|path| path.to_token_stream().to_string())
+        .collect::<Vec<_>>();
+    if events.is_empty() {
+        return None;
+    }
+    let len = events.len();
+    Some(quote! {
+        #[doc(hidden)]
+        #[used]
+        pub static __LOAM_EVENT_SPECS: [fn() -> &'static [u8]; #len] = [
+            #(<#events as loam_sdk::soroban_sdk::Event>::spec_xdr),*
+        ];
+    })
 }
 
-pub fn extract_ident(tt: TokenTree) -> Option<Ident> {
-    match tt {
-        TokenTree::Group(g) => Some(group_to_ident(&g)),
-        TokenTree::Ident(ident) => Some(ident),
-        _ => None,
+/// The prefixes subcontracts are mounted under, as a JSON list in the contract's metadata, which
+/// lets tools such as `loam storage dump` find the state stored under them.
+fn generate_prefix_meta(mounts: &[Mount]) -> Option<TokenStream> {
+    let prefixes = mounts
+        .iter()
+        .filter_map(|mount| mount.prefix.as_ref().map(|prefix| format!(r#""{prefix}""#)))
+        .unique()
+        .collect::<Vec<_>>();
+    if prefixes.is_empty() {
+        return None;
     }
+    let prefixes = format!("[{}]", prefixes.join(","));
+    Some(quote! {
+        loam_sdk::soroban_sdk::contractmeta!(key = "loamprefixes", val = #prefixes);
+    })
 }
 
-fn group_to_ident(g: &Group) -> Ident {
-    let mut iter = g.stream().into_iter();
-    if let Some(TokenTree::Ident(ident)) = iter.next() {
-        ident
-    } else {
-        panic!("Expected an ident")
+/// The `__constructor` calling the constructor hooks of the mounted subcontracts, if any.
+fn constructor(
+    mounts: &[Mount],
+    strukt: &syn::ItemStruct,
+    all_traits: &Traits,
+) -> Option<(TokenStream, Vec<(Ident, Type)>)> {
+    let mounted = mounts
+        .iter()
+        .filter_map(|mount| {
+            all_traits
+                .get(&format!("Is{}", mount.trait_ident))
+                .map(|item| (mount, item))
+        })
+        .collect::<Vec<_>>();
+    generate_constructor(&mounted, strukt)
+}
+
+/// Checks that the methods each mount excludes or renames exist, and that no two mounts export
+/// a method under the same name.
+fn check_exported_methods(mounts: &[Mount], all_traits: &Traits) -> syn::Result<()> {
+    let mut exported = BTreeMap::<String, &Mount>::new();
+    for mount in mounts {
+        let Some(item) = all_traits.get(&format!("Is{}", mount.trait_ident)) else {
+            continue;
+        };
+        let methods = item
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::TraitItem::Fn(method) => Some(method),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for method in mount
+            .exclude
+            .iter()
+            .chain(mount.rename.iter().map(|(from, _)| from))
+        {
+            if !methods.iter().any(|m| m.sig.ident == *method) {
+                return Err(syn::Error::new(
+                    method.span(),
+                    format!("`{}` has no method `{method}`", item.ident),
+                ));
+            }
+        }
+        // Constructor hooks are not exported, they are called from `__constructor`.
+        for method in methods.into_iter().filter(|m| !is_constructor(m)) {
+            let method = &method.sig.ident;
+            let Some(name) = mount.exported_name(method) else {
+                continue;
+            };
+            if let Some(other) = exported.insert(name.to_string(), mount) {
+                return Err(syn::Error::new(
+                    mount.trait_ident.span(),
+                    format!(
+                        "`{other}` and `{mount}` both export a method named `{name}`; \
+                         use `exclude({method})` or `rename({method} = ..)` on one of them"
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compile-time assertions that no two subcontract implementations mounted under the same prefix
+/// share a storage key. Mounts under different prefixes never share keys.
+fn generate_key_checks(mounts: &[Mount], contract: &Ident) -> TokenStream {
+    mounts
+        .iter()
+        .unique_by(|mount| {
+            (
+                mount.prefix.as_ref().map(ToString::to_string),
+                mount.impl_ty.to_token_stream().to_string(),
+            )
+        })
+        .tuple_combinations()
+        .filter(|(a, b)| a.prefix == b.prefix)
+        .map(|(a, b)| {
+            // Through the contract, so that impl types using its generics get their defaults.
+            let impl_of = |mount: &Mount| {
+                let implementor = mount.implementor(contract);
+                let trait_path = mount.trait_path();
+                quote!(<#implementor as #trait_path>::Impl)
+            };
+            let (impl_a, impl_b) = (impl_of(a), impl_of(b));
+            let message = format!(
+                "storage keys of `{a}` and `{b}` overlap; \
+                 rename one of them or its storage fields"
+            );
+            quote! {
+                const _: () = assert!(
+                    !loam_sdk::soroban_sdk::keys_overlap(
+                        <#impl_a as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                        <#impl_b as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                    ),
+                    #message
+                );
+            }
+        })
+        .collect()
+}
+
+/// Arguments of `derive_contract`: the mounted subcontracts and, optionally,
+/// `soroban_contract = Name` naming the generated soroban contract type.
+pub struct ContractArgs {
+    pub mounts: Vec<Mount>,
+    pub soroban_contract: Option<Ident>,
+}
+
+impl Parse for ContractArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut mounts = Vec::new();
+        let mut soroban_contract = None;
+        while !input.is_empty() {
+            if input.peek2(Token![=]) {
+                let option: Ident = input.parse()?;
+                if option != "soroban_contract" {
+                    return Err(syn::Error::new(
+                        option.span(),
+                        "expected a subcontract such as `Core(Admin)` or `soroban_contract = Name`",
+                    ));
+                }
+                input.parse::<Token![=]>()?;
+                soroban_contract = Some(input.parse()?);
+            } else {
+                mounts.push(input.parse()?);
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(ContractArgs {
+            mounts,
+            soroban_contract,
+        })
     }
 }
 
@@ -254,6 +975,10 @@ mod tests {
     use super::*;
     use crate::util::*;
 
+    fn parse_args(args: TokenStream) -> syn::Result<Vec<Mount>> {
+        syn::parse2::<ContractArgs>(args).map(|args| args.mounts)
+    }
+
     #[test]
     fn first() {
         let input: Item = syn::parse_quote! {
@@ -283,18 +1008,37 @@ mod tests {
                 }
                 fn admin_set(new_admin: Address) -> Result<(), Error> {
                     let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                    let snapshot = Self::Impl::snapshot(&impl_);
                     let res = impl_.admin_set(new_admin)?;
-                    Self::Impl::set_lazy(impl_);
+                    Self::Impl::set_lazy_if_changed(impl_, snapshot);
                     Ok(res)
                 }
                 fn admin_set_two(new_admin: Address) {
                     let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                    let snapshot = Self::Impl::snapshot(&impl_);
                     let res = impl_.admin_set_two(new_admin);
-                    Self::Impl::set_lazy(impl_);
+                    Self::Impl::set_lazy_if_changed(impl_, snapshot);
                     res
                 }
             }
-
+            #[doc(hidden)]
+            #[allow(clippy::crate_in_macro_def)]
+            #[macro_export]
+            macro_rules! __loam_subcontract_Ownable {
+                ([$($callback:tt)*] { $($state:tt)* }) => {
+                    $($callback)*! {
+                        $($state)*
+                        pub trait IsOwnable {
+                            /// Get current admin
+                            fn admin_get(&self) -> Option<Address>;
+                            fn admin_set(&mut self, new_admin: Address) -> Result<(), Error>;
+                            fn admin_set_two(&mut self, new_admin: Address);
+                        }
+                    }
+                };
+            }
+            #[doc(hidden)]
+            pub use __loam_subcontract_Ownable as Ownable;
         };
         equal_tokens(&output, &result);
         // let impl_ = syn::parse_str::<ItemImpl>(result.as_str()).unwrap();
@@ -330,21 +1074,722 @@ mod tests {
                 }
                 fn riff_set(new_riff: Address) -> Result<(), Error> {
                     let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                    let snapshot = Self::Impl::snapshot(&impl_);
                     let res = impl_.riff_set(new_riff)?;
-                    Self::Impl::set_lazy(impl_);
+                    Self::Impl::set_lazy_if_changed(impl_, snapshot);
                     Ok(res)
                 }
                 fn riff_set_two(new_riff: Address) {
                     let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                    let snapshot = Self::Impl::snapshot(&impl_);
                     let res = impl_.riff_set_two(new_riff);
-                    Self::Impl::set_lazy(impl_);
+                    Self::Impl::set_lazy_if_changed(impl_, snapshot);
                     res
                 }
             }
-
+            #[doc(hidden)]
+            #[allow(clippy::crate_in_macro_def)]
+            #[macro_export]
+            macro_rules! __loam_subcontract_Subcontract {
+                ([$($callback:tt)*] { $($state:tt)* }) => {
+                    $($callback)*! {
+                        $($state)*
+                        pub trait IsSubcontract {
+                            /// Get current admin
+                            fn riff_get(&self) -> Option<String>;
+                            fn riff_set(&mut self, new_riff: Address) -> Result<(), Error>;
+                            fn riff_set_two(&mut self, new_riff: Address);
+                        }
+                    }
+                };
+            }
+            #[doc(hidden)]
+            pub use __loam_subcontract_Subcontract as Subcontract;
         };
         equal_tokens(&output, &result);
         // let impl_ = syn::parse_str::<ItemImpl>(result.as_str()).unwrap();
         // println!("{impl_:#?}");
     }
+
+    #[test]
+    fn key_checks() {
+        let mounts = parse_args(quote! {
+            Core(Admin), Sep41(Token), Sep41Burnable(Token), Sep41(Token) as lp, Core(Admin) as lp
+        })
+        .unwrap();
+        let result = generate_key_checks(&mounts, &format_ident!("Contract"));
+        let output = quote! {
+            const _: () = assert!(
+                !loam_sdk::soroban_sdk::keys_overlap(
+                    < <Contract as Core>::Impl as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                    < <Contract as Sep41>::Impl as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                ),
+                "storage keys of `Core(Admin)` and `Sep41(Token)` overlap; rename one of them or its storage fields"
+            );
+            const _: () = assert!(
+                !loam_sdk::soroban_sdk::keys_overlap(
+                    < <ContractLpMount__ as Sep41>::Impl as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                    < <ContractLpMount__ as Core>::Impl as loam_sdk::soroban_sdk::Lazy>::STORAGE_KEYS,
+                ),
+                "storage keys of `Sep41(Token) as lp` and `Core(Admin) as lp` overlap; rename one of them or its storage fields"
+            );
+        };
+        equal_tokens(&output, &result);
+    }
+
+    #[test]
+    fn mounted_methods() {
+        let item: syn::ItemTrait = syn::parse_quote! {
+            #[subcontract]
+            pub trait IsSep41 {
+                /// Balance of `id`
+                fn balance(&self, id: Address) -> i128;
+            }
+        };
+        let mount = parse_args(quote! { Sep41(MyToken) as lp })
+            .unwrap()
+            .remove(0);
+        let result = generate_methods(
+            &item,
+            &mount,
+            &syn::parse_quote!(
+                pub struct Contract;
+            ),
+        );
+        let output = quote! {
+            /// Balance of `id`
+            pub fn lp_balance(env: loam_sdk::soroban_sdk::Env, id: Address) -> i128 {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                loam_sdk::soroban_sdk::store::with_prefix("lp", || {
+                    <ContractLpMount__ as Sep41>::balance(id)
+                })
+            }
+        };
+        equal_tokens(&output, &quote!(#(#result)*));
+    }
+
+    #[test]
+    fn generics_consts_and_defaults() {
+        let input: Item = syn::parse_quote! {
+            pub trait IsVault<T: Asset> {
+                type Share;
+                const FEE: u32 = 30;
+                fn name() -> String;
+                fn deposit(&mut self, from: Address, amount: i128) -> Self::Share;
+                fn fee(&self) -> u32 {
+                    Self::FEE
+                }
+            }
+        };
+        let result = generate(&input);
+        let output = quote! {
+            pub trait IsVault<T: Asset> {
+                type Share;
+                const FEE: u32 = 30;
+                fn name() -> String;
+                fn deposit(&mut self, from: Address, amount: i128) -> Self::Share;
+                fn fee(&self) -> u32 {
+                    Self::FEE
+                }
+            }
+            pub trait Vault<T: Asset> {
+                /// Type that implments the instance type
+                type Impl: Lazy + IsVault<T> + Default;
+                const FEE: u32 = <Self::Impl as IsVault<T> >::FEE;
+                fn name() -> String {
+                    <Self::Impl as IsVault<T> >::name()
+                }
+                fn deposit(from: Address, amount: i128) -> <Self::Impl as IsVault<T> >::Share {
+                    let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                    let snapshot = Self::Impl::snapshot(&impl_);
+                    let res = impl_.deposit(from, amount);
+                    Self::Impl::set_lazy_if_changed(impl_, snapshot);
+                    res
+                }
+                fn fee() -> u32 {
+                    Self::Impl::get_lazy().unwrap_or_default().fee()
+                }
+            }
+            #[doc(hidden)]
+            #[allow(clippy::crate_in_macro_def)]
+            #[macro_export]
+            macro_rules! __loam_subcontract_Vault {
+                ([$($callback:tt)*] { $($state:tt)* }) => {
+                    $($callback)*! {
+                        $($state)*
+                        pub trait IsVault<T: Asset> {
+                            type Share;
+                            const FEE: u32 = 30;
+                            fn name() -> String;
+                            fn deposit(&mut self, from: Address, amount: i128) -> Self::Share;
+                            fn fee(&self) -> u32 {
+                                Self::FEE
+                            }
+                        }
+                    }
+                };
+            }
+            #[doc(hidden)]
+            pub use __loam_subcontract_Vault as Vault;
+        };
+        equal_tokens(&output, &result);
+    }
+
+    #[test]
+    fn generic_mount() {
+        let item: syn::ItemTrait = syn::parse_quote! {
+            #[subcontract]
+            pub trait IsVault<T: Asset> {
+                fn deposit(&mut self, asset: T, amount: i128);
+                fn name() -> String;
+            }
+        };
+        let mount = parse_args(quote! { Vault<Usdc>(MyVault<Usdc>) })
+            .unwrap()
+            .remove(0);
+        assert_eq!(mount.to_string(), "Vault<Usdc>(MyVault<Usdc>)");
+        let result = generate_methods(
+            &item,
+            &mount,
+            &syn::parse_quote!(
+                pub struct Contract;
+            ),
+        );
+        let output = quote! {
+            pub fn deposit(env: loam_sdk::soroban_sdk::Env, asset: Usdc, amount: i128) {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                <Contract as Vault<Usdc> >::deposit(asset, amount)
+            }
+            pub fn name(env: loam_sdk::soroban_sdk::Env) -> String {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                <Contract as Vault<Usdc> >::name()
+            }
+        };
+        equal_tokens(&output, &quote!(#(#result)*));
+    }
+
+    #[test]
+    fn exclude_and_rename() {
+        let item: syn::ItemTrait = syn::parse_quote! {
+            #[subcontract]
+            pub trait IsSep41 {
+                fn balance(&self, id: Address) -> i128;
+                fn decimals(&self) -> u32;
+                fn name(&self) -> String;
+            }
+        };
+        let mount = parse_args(quote! {
+            Sep41(MyToken, exclude(name), rename(balance = token_balance))
+        })
+        .unwrap()
+        .remove(0);
+        let result = generate_methods(
+            &item,
+            &mount,
+            &syn::parse_quote!(
+                pub struct Contract;
+            ),
+        );
+        let output = quote! {
+            pub fn token_balance(env: loam_sdk::soroban_sdk::Env, id: Address) -> i128 {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::balance(id)
+            }
+            pub fn decimals(env: loam_sdk::soroban_sdk::Env) -> u32 {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::decimals()
+            }
+        };
+        equal_tokens(&output, &quote!(#(#result)*));
+        assert!(parse_args(quote! { Sep41(MyToken, hide(name)) }).is_err());
+    }
+
+    #[test]
+    fn exported_method_collisions() {
+        let traits: Traits = [
+            syn::parse_quote! {
+                pub trait IsSep41 {
+                    fn balance(&self, id: Address) -> i128;
+                }
+            },
+            syn::parse_quote! {
+                pub trait IsBank {
+                    fn balance(&self, id: Address) -> i128;
+                    fn deposit(&mut self, id: Address, amount: i128);
+                }
+            },
+        ]
+        .into_iter()
+        .map(|item: syn::ItemTrait| (item.ident.to_string(), item))
+        .collect();
+        let check = |mounts| check_exported_methods(&parse_args(mounts).unwrap(), &traits);
+
+        let err = check(quote! { Sep41(MyToken), Bank(MyBank) }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`Sep41(MyToken)` and `Bank(MyBank)` both export a method named `balance`; \
+             use `exclude(balance)` or `rename(balance = ..)` on one of them"
+        );
+        check(quote! { Sep41(MyToken), Bank(MyBank, exclude(balance)) }).unwrap();
+        check(quote! { Sep41(MyToken), Bank(MyBank, rename(balance = bank_balance)) }).unwrap();
+        check(quote! { Sep41(MyToken), Bank(MyBank) as savings }).unwrap();
+        let err = check(quote! { Bank(MyBank, exclude(withdraw)) }).unwrap_err();
+        assert_eq!(err.to_string(), "`IsBank` has no method `withdraw`");
+    }
+
+    #[test]
+    fn constructor_hooks() {
+        let traits: Traits = [
+            syn::parse_quote! {
+                pub trait IsCore {
+                    fn admin_get(&self) -> Option<Address>;
+                    #[constructor]
+                    fn admin_init(&mut self, admin: Address);
+                }
+            },
+            syn::parse_quote! {
+                pub trait IsInitable {
+                    #[constructor]
+                    fn ft_init(&mut self, admin: Address, name: String);
+                }
+            },
+        ]
+        .into_iter()
+        .map(|item: syn::ItemTrait| (item.ident.to_string(), item))
+        .collect();
+        let contract: syn::ItemStruct = syn::parse_quote!(
+            pub struct Contract;
+        );
+        let mounts =
+            parse_args(quote! { Core(Admin), Initable(MyToken), Initable(MyToken) as lp }).unwrap();
+        check_exported_methods(&mounts, &traits).unwrap();
+
+        let methods = generate_methods(&traits["IsCore"], &mounts[0], &contract);
+        let output = quote! {
+            pub fn admin_get(env: loam_sdk::soroban_sdk::Env) -> Option<Address> {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::admin_get()
+            }
+        };
+        equal_tokens(&output, &quote!(#(#methods)*));
+
+        let (result, args) = constructor(&mounts, &contract, &traits).unwrap();
+        let output = quote! {
+            pub fn __constructor(
+                env: loam_sdk::soroban_sdk::Env,
+                admin: Address,
+                name: String,
+                lp_admin: Address,
+                lp_name: String
+            ) {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::admin_init(::core::clone::Clone::clone(&admin));
+                Contract::ft_init(admin, name);
+                loam_sdk::soroban_sdk::store::with_prefix("lp", || {
+                    <ContractLpMount__ as Initable>::ft_init(lp_admin, lp_name)
+                });
+            }
+        };
+        equal_tokens(&output, &result);
+
+        let result =
+            contract::generate_test_client(&contract, &format_ident!("SorobanContract__"), &args);
+        let output = quote! {
+            /// Client of the contract in native tests, as returned by `register_test`.
+            #[cfg(not(target_family = "wasm"))]
+            pub type ContractClient<'a> = SorobanContract__Client<'a>;
+
+            #[cfg(not(target_family = "wasm"))]
+            impl Contract {
+                /// Register the contract in the test `env`, passing the given arguments to its
+                /// constructor, and get a client calling it.
+                pub fn register_test<'a>(
+                    env: &'a loam_sdk::soroban_sdk::Env,
+                    admin: Address,
+                    name: String,
+                    lp_admin: Address,
+                    lp_name: String
+                ) -> SorobanContract__Client<'a> {
+                    let id = env.register(SorobanContract__, (admin, name, lp_admin, lp_name,));
+                    SorobanContract__Client::new(env, &id)
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+
+        let mounts = parse_args(quote! { Core(Admin, exclude(admin_init)) }).unwrap();
+        assert!(constructor(&mounts, &contract, &traits).is_none());
+
+        let item: Item = syn::parse_quote! {
+            pub trait IsInitable {
+                #[constructor]
+                fn ft_init(&mut self, admin: Address) -> bool;
+            }
+        };
+        assert!(inner_generate(&item).is_err());
+    }
+
+    #[test]
+    fn auth_attributes() {
+        let input: Item = syn::parse_quote! {
+            pub trait IsBank {
+                /// Move funds
+                #[require_auth(from)]
+                fn transfer(&mut self, from: Address, to: Address, amount: i128);
+                #[require_auth_for_args(from, amount)]
+                fn burn(&mut self, from: Address, amount: i128);
+                #[only_admin]
+                fn pause(&mut self);
+            }
+        };
+        let syn::Item::Trait(item) = &input else {
+            unreachable!()
+        };
+        let as_trait = quote!(<Self::Impl as IsBank>);
+        let companion = item
+            .items
+            .iter()
+            .filter_map(|item| generate_item(item, &as_trait, &[]).unwrap())
+            .collect::<Vec<_>>();
+        let output = quote! {
+            /// Move funds
+            fn transfer(from: Address, to: Address, amount: i128) {
+                from.require_auth();
+                let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                let snapshot = Self::Impl::snapshot(&impl_);
+                let res = impl_.transfer(from, to, amount);
+                Self::Impl::set_lazy_if_changed(impl_, snapshot);
+                res
+            }
+            fn burn(from: Address, amount: i128) {
+                from.require_auth_for_args(loam_sdk::soroban_sdk::vec![
+                    loam_sdk::soroban_sdk::env(),
+                    loam_sdk::soroban_sdk::IntoVal::into_val(&amount, loam_sdk::soroban_sdk::env())
+                ]);
+                let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                let snapshot = Self::Impl::snapshot(&impl_);
+                let res = impl_.burn(from, amount);
+                Self::Impl::set_lazy_if_changed(impl_, snapshot);
+                res
+            }
+            fn pause()
+            where
+                Self: loam_sdk::soroban_sdk::HasAdmin
+            {
+                <Self as loam_sdk::soroban_sdk::HasAdmin>::require_admin();
+                let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                let snapshot = Self::Impl::snapshot(&impl_);
+                let res = impl_.pause();
+                Self::Impl::set_lazy_if_changed(impl_, snapshot);
+                res
+            }
+        };
+        equal_tokens(&output, &quote!(#(#companion)*));
+
+        let mount = parse_args(quote! { Bank(MyBank) }).unwrap().remove(0);
+        let methods = generate_methods(
+            item,
+            &mount,
+            &syn::parse_quote!(
+                pub struct Contract;
+            ),
+        );
+        let output = quote! {
+            /// Move funds
+            #[doc = ""]
+            #[doc = " Requires authorization from `from`."]
+            pub fn transfer(env: loam_sdk::soroban_sdk::Env, from: Address, to: Address, amount: i128) {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::transfer(from, to, amount)
+            }
+            #[doc = " Requires authorization from `from` for `amount`."]
+            pub fn burn(env: loam_sdk::soroban_sdk::Env, from: Address, amount: i128) {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::burn(from, amount)
+            }
+            #[doc = " Requires authorization from the contract admin."]
+            pub fn pause(env: loam_sdk::soroban_sdk::Env) {
+                let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                Contract::pause()
+            }
+        };
+        equal_tokens(&output, &quote!(#(#methods)*));
+    }
+
+    #[test]
+    fn auth_attribute_errors() {
+        for (attr, message) in [
+            (
+                quote!(#[require_auth(to)]),
+                "`transfer` has no argument `to`",
+            ),
+            (
+                quote!(#[require_auth(from, amount)]),
+                "`require_auth` takes a single address; \
+                 use `require_auth_for_args` to authorize some arguments only",
+            ),
+            (quote!(#[only_admin(from)]), "unexpected token in attribute"),
+        ] {
+            let method: syn::TraitItem = syn::parse_quote! {
+                #attr
+                fn transfer(&mut self, from: Address, amount: i128);
+            };
+            let syn::TraitItem::Fn(method) = &method else {
+                unreachable!()
+            };
+            let err = Auth::from_method(method).err().unwrap();
+            assert!(err.to_string().starts_with(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn pause_guard() {
+        let method: syn::TraitItem = syn::parse_quote! {
+            #[when_not_paused]
+            #[require_auth(from)]
+            fn transfer(&mut self, from: Address, amount: i128);
+        };
+        let output = quote! {
+            fn transfer(from: Address, amount: i128)
+            where
+                Self: loam_sdk::soroban_sdk::HasPause
+            {
+                <Self as loam_sdk::soroban_sdk::HasPause>::require_not_paused();
+                from.require_auth();
+                let mut impl_ = Self::Impl::get_lazy().unwrap_or_default();
+                let snapshot = Self::Impl::snapshot(&impl_);
+                let res = impl_.transfer(from, amount);
+                Self::Impl::set_lazy_if_changed(impl_, snapshot);
+                res
+            }
+        };
+        let as_trait = quote!(<Self::Impl as IsToken>);
+        let result = generate_item(&method, &as_trait, &[]).unwrap().unwrap();
+        equal_tokens(&output, &result);
+    }
+
+    #[test]
+    fn has_pause() {
+        let traits: Traits = [
+            syn::parse_quote! {
+                pub trait IsToken {
+                    #[when_not_paused]
+                    fn transfer(&mut self, from: Address, amount: i128);
+                }
+            },
+            syn::parse_quote! {
+                pub trait IsPausable {
+                    #[pause_state]
+                    fn paused(&self) -> bool;
+                }
+            },
+            syn::parse_quote! {
+                pub trait IsHalt {
+                    #[pause_state]
+                    fn halted(&self) -> bool;
+                }
+            },
+        ]
+        .into_iter()
+        .map(|item: syn::ItemTrait| (item.ident.to_string(), item))
+        .collect();
+        let contract: syn::ItemStruct = syn::parse_quote!(
+            pub struct Contract;
+        );
+        let mounts =
+            parse_args(quote! { Token(MyToken), Pausable(Pause), Token(MyToken) as lp }).unwrap();
+        let result = generate_has_pause(&mounts, &contract, &traits);
+        let output = quote! {
+            impl loam_sdk::soroban_sdk::HasPause for Contract {
+                fn paused() -> bool {
+                    <Self as Pausable>::paused()
+                }
+            }
+            impl loam_sdk::soroban_sdk::HasPause for ContractLpMount__ {
+                fn paused() -> bool {
+                    <Contract as Pausable>::paused()
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+
+        let mounts = parse_args(quote! {
+            Pausable(Pause), Token(MyToken) as lp, Halt(Stop) as lp, Token(MyToken) as vault
+        })
+        .unwrap();
+        let result = generate_has_pause(&mounts, &contract, &traits);
+        let output = quote! {
+            impl loam_sdk::soroban_sdk::HasPause for Contract {
+                fn paused() -> bool {
+                    <Self as Pausable>::paused()
+                }
+            }
+            impl loam_sdk::soroban_sdk::HasPause for ContractLpMount__ {
+                fn paused() -> bool {
+                    <Self as Halt>::halted()
+                }
+            }
+            impl loam_sdk::soroban_sdk::HasPause for ContractVaultMount__ {
+                fn paused() -> bool {
+                    <Contract as Pausable>::paused()
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+
+        let mounts = parse_args(quote! { Token(MyToken) as lp, Pausable(Pause) as vault }).unwrap();
+        let result = generate_has_pause(&mounts, &contract, &traits);
+        let output = quote! {
+            impl loam_sdk::soroban_sdk::HasPause for ContractLpMount__ {
+                fn paused() -> bool {
+                    false
+                }
+            }
+            impl loam_sdk::soroban_sdk::HasPause for ContractVaultMount__ {
+                fn paused() -> bool {
+                    <Self as Pausable>::paused()
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+
+        let mounts = parse_args(quote! { Token(MyToken) }).unwrap();
+        let result = generate_has_pause(&mounts, &contract, &traits);
+        let output = quote! {
+            impl loam_sdk::soroban_sdk::HasPause for Contract {
+                fn paused() -> bool {
+                    false
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+    }
+
+    #[test]
+    fn event_specs() {
+        let traits: Traits = [
+            syn::parse_quote! {
+                #[events(my_token::Transfer, my_token::Burn)]
+                pub trait IsSep41 {
+                    fn burn(&mut self, from: Address, amount: i128);
+                }
+            },
+            syn::parse_quote! {
+                #[events(my_token::Transfer)]
+                pub trait IsFungible {
+                    fn mint(&mut self, to: Address, amount: i128);
+                }
+            },
+        ]
+        .into_iter()
+        .map(|item: syn::ItemTrait| (item.ident.to_string(), item))
+        .collect();
+        let mounts = parse_args(quote! { Sep41(MyToken), Fungible(MyToken) }).unwrap();
+        let output = quote! {
+            #[doc(hidden)]
+            #[used]
+            pub static __LOAM_EVENT_SPECS: [fn() -> &'static [u8]; 2usize] = [
+                <my_token::Transfer as loam_sdk::soroban_sdk::Event>::spec_xdr,
+                <my_token::Burn as loam_sdk::soroban_sdk::Event>::spec_xdr
+            ];
+        };
+        equal_tokens(&output, &generate_event_specs(&mounts, &traits).unwrap());
+        let mounts = parse_args(quote! { Core(Admin) }).unwrap();
+        assert!(generate_event_specs(&mounts, &traits).is_none());
+
+        let item: Item = syn::parse_quote! {
+            #[events(my_token::Transfer)]
+            pub trait IsSep41 {}
+        };
+        let result = inner_generate(&item).unwrap().to_string();
+        assert_eq!(
+            result.matches("events").count(),
+            1,
+            "only passed on: {result}"
+        );
+        let item: Item = syn::parse_quote! {
+            #[events(my_token::Transfer = 1)]
+            pub trait IsSep41 {}
+        };
+        assert!(inner_generate(&item).is_err());
+    }
+
+    #[test]
+    fn prefix_meta() {
+        let mounts = parse_args(quote! {
+            Core(Admin), Sep41(MyToken) as lp, Core(Admin) as lp, Sep41(MyToken) as vault
+        })
+        .unwrap();
+        let output = quote! {
+            loam_sdk::soroban_sdk::contractmeta!(key = "loamprefixes", val = "[\"lp\",\"vault\"]");
+        };
+        equal_tokens(&output, &generate_prefix_meta(&mounts).unwrap());
+        let mounts = parse_args(quote! { Core(Admin), Sep41(MyToken) }).unwrap();
+        assert!(generate_prefix_meta(&mounts).is_none());
+    }
+
+    #[test]
+    fn collects_mounted_traits() {
+        let args = quote! { Core(Admin), Sep41(Token), Core(Admin) as lp };
+        let result = derive_contract_impl(args, syn::parse_quote! { pub struct Contract; });
+        let output = quote! {
+            Core! {
+                [loam_sdk::__derive_contract] {
+                    [Core(Admin), Sep41(Token), Core(Admin) as lp]
+                    [pub struct Contract;]
+                    [Sep41]
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+    }
+
+    #[test]
+    fn named_generic_contract() {
+        let result = collect_traits(Collected {
+            args: quote! { Core(Admin<T>), soroban_contract = TokenContract },
+            item: syn::parse_quote! { pub struct Token<T: Kind = Fixed>(PhantomData<T>); },
+            remaining: Vec::new(),
+            traits: vec![syn::parse_quote! {
+                pub trait IsCore {
+                    fn admin_get(&self) -> Option<Address>;
+                }
+            }],
+        });
+        let output = quote! {
+            pub struct Token<T: Kind = Fixed>(PhantomData<T>);
+            #[loam_sdk::soroban_sdk::contract(crate_path = "loam_sdk::soroban_sdk")]
+            pub struct TokenContract;
+            #[loam_sdk::soroban_sdk::contractimpl(crate_path = "loam_sdk::soroban_sdk")]
+            impl TokenContract {
+                pub fn admin_get(env: loam_sdk::soroban_sdk::Env) -> Option<Address> {
+                    let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+                    <Token as Core>::admin_get()
+                }
+            }
+            /// Client of the contract in native tests, as returned by `register_test`.
+            #[cfg(not(target_family = "wasm"))]
+            pub type TokenClient<'a> = TokenContractClient<'a>;
+            #[cfg(not(target_family = "wasm"))]
+            impl<T: Kind> Token<T> {
+                /// Register the contract in the test `env`, passing the given arguments to its
+                /// constructor, and get a client calling it.
+                pub fn register_test<'a>(
+                    env: &'a loam_sdk::soroban_sdk::Env
+                ) -> TokenContractClient<'a> {
+                    let id = env.register(TokenContract, ());
+                    TokenContractClient::new(env, &id)
+                }
+            }
+            impl<T: Kind> Core for Token<T> {
+                type Impl = Admin<T>;
+            }
+            impl<T: Kind> loam_sdk::soroban_sdk::HasAdmin for Token<T> {
+                fn admin() -> Option<loam_sdk::soroban_sdk::Address> {
+                    <Self as Core>::admin_get()
+                }
+            }
+        };
+        equal_tokens(&output, &result);
+    }
 }
@@ -0,0 +1,121 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, Attribute, Ident, Token, TraitItemFn};
+
+/// Authorization a subcontract method requires before its implementation is called.
+pub enum Auth {
+    /// `#[only_admin]`: the contract's admin, as given by `HasAdmin`.
+    Admin,
+    /// `#[when_not_paused]`: no authorization, but the contract must not be paused, as given by
+    /// `HasPause`.
+    NotPaused,
+    /// `#[require_auth(from)]`: the address passed as `from`.
+    Address(Ident),
+    /// `#[require_auth_for_args(from, amount)]`: the address passed as `from`, for the other
+    /// arguments only.
+    AddressForArgs(Ident, Vec<Ident>),
+}
+
+/// Whether `attr` is one of the authorization attributes, which only mean something to
+/// `#[subcontract]`.
+pub fn is_auth_attr(attr: &Attribute) -> bool {
+    let path = attr.path();
+    path.is_ident("only_admin")
+        || path.is_ident("when_not_paused")
+        || path.is_ident("require_auth")
+        || path.is_ident("require_auth_for_args")
+}
+
+impl Auth {
+    /// The authorization attributes of `method`, checking that the arguments they name exist.
+    pub fn from_method(method: &TraitItemFn) -> syn::Result<Vec<Auth>> {
+        let args = super::get_args_without_self(&method.sig.inputs);
+        let check_arg = |ident: &Ident| {
+            if args.contains(ident) {
+                Ok(())
+            } else {
+                Err(syn::Error::new(
+                    ident.span(),
+                    format!("`{}` has no argument `{ident}`", method.sig.ident),
+                ))
+            }
+        };
+        let mut auths = Vec::new();
+        for attr in method.attrs.iter().filter(|attr| is_auth_attr(attr)) {
+            let auth = if attr.path().is_ident("only_admin") {
+                attr.meta.require_path_only()?;
+                Auth::Admin
+            } else if attr.path().is_ident("when_not_paused") {
+                attr.meta.require_path_only()?;
+                Auth::NotPaused
+            } else {
+                let mut idents = attr
+                    .parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)?
+                    .into_iter();
+                let Some(address) = idents.next() else {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "expected the address argument",
+                    ));
+                };
+                check_arg(&address)?;
+                let rest = idents.collect::<Vec<_>>();
+                rest.iter().try_for_each(check_arg)?;
+                if attr.path().is_ident("require_auth_for_args") {
+                    Auth::AddressForArgs(address, rest)
+                } else if rest.is_empty() {
+                    Auth::Address(address)
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "`require_auth` takes a single address; \
+                         use `require_auth_for_args` to authorize some arguments only",
+                    ));
+                }
+            };
+            auths.push(auth);
+        }
+        Ok(auths)
+    }
+
+    /// Statement enforcing the authorization in the companion trait's method.
+    pub fn check(&self) -> TokenStream {
+        match self {
+            Auth::Admin => quote! {
+                <Self as loam_sdk::soroban_sdk::HasAdmin>::require_admin();
+            },
+            Auth::NotPaused => quote! {
+                <Self as loam_sdk::soroban_sdk::HasPause>::require_not_paused();
+            },
+            Auth::Address(address) => quote! {
+                #address.require_auth();
+            },
+            Auth::AddressForArgs(address, args) => quote! {
+                #address.require_auth_for_args(loam_sdk::soroban_sdk::vec![
+                    loam_sdk::soroban_sdk::env(),
+                    #(loam_sdk::soroban_sdk::IntoVal::into_val(&#args, loam_sdk::soroban_sdk::env())),*
+                ]);
+            },
+        }
+    }
+
+    /// Line added to the docs of the contract method, and so to the contract spec.
+    pub fn doc(&self) -> String {
+        match self {
+            Auth::Admin => "Requires authorization from the contract admin.".to_string(),
+            Auth::NotPaused => "Fails while the contract is paused.".to_string(),
+            Auth::Address(address) => format!("Requires authorization from `{address}`."),
+            Auth::AddressForArgs(address, args) if args.is_empty() => {
+                format!("Requires authorization from `{address}`, without arguments.")
+            }
+            Auth::AddressForArgs(address, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| format!("`{arg}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("Requires authorization from `{address}` for {args}.")
+            }
+        }
+    }
+}
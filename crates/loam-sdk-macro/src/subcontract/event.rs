@@ -0,0 +1,244 @@
+use darling::{ast::NestedMeta, FromMeta};
+use heck::ToSnakeCase;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Error, Fields, FieldsNamed, Item, ItemStruct, Result};
+
+/// Longest `Symbol`, and so event name, soroban supports.
+const MAX_NAME_LEN: usize = 32;
+
+/// Topics an event can have besides its name.
+const MAX_TOPICS: usize = 3;
+
+/// Arguments of `#[loam_event(..)]`.
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct EventArgs {
+    /// Name of the event, its first topic. Defaults to the struct's name in snake case.
+    name: Option<String>,
+}
+
+pub(crate) fn from_item(attr: TokenStream, item: Item) -> Result<TokenStream> {
+    let args = EventArgs::from_list(&NestedMeta::parse_meta_list(attr)?)?;
+    match item {
+        Item::Struct(item_struct) => generate_event(item_struct, &args),
+        _ => Err(Error::new_spanned(
+            item,
+            "loam_event can only be applied to structs",
+        )),
+    }
+}
+
+fn generate_event(mut item_struct: ItemStruct, args: &EventArgs) -> Result<TokenStream> {
+    let ident = item_struct.ident.clone();
+    let name = args
+        .name
+        .clone()
+        .unwrap_or_else(|| ident.to_string().to_snake_case());
+    if name.is_empty()
+        || name.len() > MAX_NAME_LEN
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(Error::new_spanned(
+            &ident,
+            format!(
+                "event name `{name}` must be at most {MAX_NAME_LEN} characters of a-z, A-Z, 0-9 \
+                 or `_`; set one with `#[loam_event(name = \"..\")]`"
+            ),
+        ));
+    }
+    let Fields::Named(FieldsNamed { named: fields, .. }) = &mut item_struct.fields else {
+        return Err(Error::new_spanned(
+            &item_struct,
+            "Only named fields are supported",
+        ));
+    };
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            &item_struct,
+            "events need at least one field, as contract types cannot be empty",
+        ));
+    }
+    let (mut topics, mut data) = (Vec::new(), Vec::new());
+    for field in fields.iter_mut() {
+        let len = field.attrs.len();
+        field.attrs.retain(|attr| !attr.path().is_ident("topic"));
+        let field_ident = field.ident.clone().expect("named field");
+        if field.attrs.len() == len {
+            data.push(field_ident);
+        } else {
+            topics.push(field_ident);
+        }
+    }
+    if topics.len() > MAX_TOPICS {
+        return Err(Error::new_spanned(
+            &topics[MAX_TOPICS],
+            format!("events can have at most {MAX_TOPICS} `#[topic]` fields besides their name"),
+        ));
+    }
+
+    let doc = format!(" {}", describe(&name, &topics, &data));
+    if item_struct
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("doc"))
+    {
+        item_struct.attrs.push(syn::parse_quote!(#[doc = ""]));
+    }
+    item_struct.attrs.push(syn::parse_quote!(#[doc = #doc]));
+    let data_value = match data.as_slice() {
+        [] => quote!(()),
+        [field] => quote!(self.#field),
+        fields => quote!((#(self.#fields),*)),
+    };
+    let spec_ident = format_ident!("__SPEC_XDR_TYPE_{}", ident.to_string().to_uppercase());
+    Ok(quote! {
+        #[soroban_sdk::contracttype]
+        #item_struct
+
+        impl soroban_sdk::Event for #ident {
+            const NAME: &'static str = #name;
+
+            fn spec_xdr() -> &'static [u8] {
+                &#spec_ident
+            }
+
+            fn publish(self) {
+                let env = soroban_sdk::env();
+                env.events().publish(
+                    (soroban_sdk::Symbol::new(env, Self::NAME), #(self.#topics),*),
+                    #data_value,
+                );
+            }
+        }
+    })
+}
+
+/// Docs of the event type, so that its topics and data show in the contract spec.
+fn describe(name: &str, topics: &[syn::Ident], data: &[syn::Ident]) -> String {
+    let list = |idents: &[syn::Ident]| {
+        idents
+            .iter()
+            .map(|ident| format!("`{ident}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match (topics.is_empty(), data.is_empty()) {
+        (false, true) => format!(
+            "Published as the `{name}` event, with topics {}.",
+            list(topics)
+        ),
+        (true, _) => format!("Published as the `{name}` event, with data {}.", list(data)),
+        (false, false) => format!(
+            "Published as the `{name}` event, with topics {} and data {}.",
+            list(topics),
+            list(data)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::equal_tokens;
+
+    #[test]
+    fn test_generate_event() {
+        let input: Item = syn::parse_quote! {
+            /// Tokens moved
+            pub struct Transfer {
+                #[topic]
+                pub from: Address,
+                #[topic]
+                pub to: Address,
+                pub amount: i128,
+            }
+        };
+        let result = from_item(TokenStream::new(), input).unwrap();
+        let expected = quote! {
+            #[soroban_sdk::contracttype]
+            /// Tokens moved
+            #[doc = ""]
+            #[doc = " Published as the `transfer` event, with topics `from`, `to` and data `amount`."]
+            pub struct Transfer {
+                pub from: Address,
+                pub to: Address,
+                pub amount: i128,
+            }
+
+            impl soroban_sdk::Event for Transfer {
+                const NAME: &'static str = "transfer";
+
+                fn spec_xdr() -> &'static [u8] {
+                    &__SPEC_XDR_TYPE_TRANSFER
+                }
+
+                fn publish(self) {
+                    let env = soroban_sdk::env();
+                    env.events().publish(
+                        (soroban_sdk::Symbol::new(env, Self::NAME), self.from, self.to),
+                        self.amount,
+                    );
+                }
+            }
+        };
+        equal_tokens(&expected, &result);
+    }
+
+    #[test]
+    fn test_generate_event_data() {
+        let input: Item = syn::parse_quote! {
+            pub struct Approved {
+                #[topic]
+                from: Address,
+                amount: i128,
+                live_until_ledger: u32,
+            }
+        };
+        let result = from_item(quote!(name = "approve"), input)
+            .unwrap()
+            .to_string();
+        assert!(result.contains(
+            &quote!(
+                (soroban_sdk::Symbol::new(env, Self::NAME), self.from),
+                (self.amount, self.live_until_ledger)
+            )
+            .to_string()
+        ));
+        assert!(result.contains("Published as the `approve` event, with topics `from` and data `amount`, `live_until_ledger`."));
+
+        let input: Item = syn::parse_quote! {
+            pub struct Paused {
+                #[topic]
+                admin: Address,
+            }
+        };
+        let result = from_item(TokenStream::new(), input).unwrap().to_string();
+        assert!(result.contains(
+            &quote!((soroban_sdk::Symbol::new(env, Self::NAME), self.admin), ()).to_string()
+        ));
+    }
+
+    #[test]
+    fn test_event_errors() {
+        let input: Item = syn::parse_quote! {
+            pub struct Moved {
+                #[topic] a: u32,
+                #[topic] b: u32,
+                #[topic] c: u32,
+                #[topic] d: u32,
+            }
+        };
+        assert!(from_item(TokenStream::new(), input).is_err());
+        let input: Item = syn::parse_quote! {
+            pub struct Moved {}
+        };
+        assert!(from_item(TokenStream::new(), input).is_err());
+        let input: Item = syn::parse_quote! {
+            pub struct Moved {
+                a: u32,
+            }
+        };
+        assert!(from_item(quote!(name = "moved!"), input).is_err());
+    }
+}
@@ -2,6 +2,8 @@ use proc_macro2::{Span, TokenStream};
 use quote::quote;
 use syn::{Fields, Item};
 
+use super::storage::storage_key;
+
 pub(crate) fn from_item(item: Item) -> Result<TokenStream, syn::Error> {
     let mut is_unit = false;
     let (name, generics) = match item {
@@ -22,16 +24,25 @@ pub(crate) fn from_item(item: Item) -> Result<TokenStream, syn::Error> {
     };
     let name_str = name.to_string();
     let string = quote! { loam_sdk::soroban_sdk::String};
-    let body = if is_unit {
-        quote! {}
+    let (body, storage_keys) = if is_unit {
+        (quote! {}, quote! {})
     } else {
-        quote! { #string::from_slice(loam_sdk::soroban_sdk::env(), #name_str)}
+        (
+            quote! { #string::from_slice(loam_sdk::soroban_sdk::env(), #name_str)},
+            storage_key(
+                &quote!(loam_sdk::soroban_sdk),
+                "persistent",
+                "String",
+                &name_str,
+            ),
+        )
     };
 
     let (impl_generics, ty_generics, _) = generics.split_for_impl();
     Ok(quote! {
         impl #impl_generics loam_sdk::soroban_sdk::IntoKey for #name #ty_generics {
             type Key = #string;
+            const STORAGE_KEYS: &'static [loam_sdk::soroban_sdk::StorageKey] = &[#storage_keys];
             fn into_key() -> Self::Key {
                 #body
             }
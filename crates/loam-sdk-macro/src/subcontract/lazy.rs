@@ -1,36 +1,138 @@
+use darling::{util::Flag, FromMeta};
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{Fields, Item, ItemStruct};
+use syn::{Attribute, Fields, Item, ItemStruct};
+
+use super::storage::{storage_key, ttl_policy};
+
+/// Longest key a `Symbol` can hold.
+const MAX_KEY_LEN: usize = 32;
+
+/// Arguments of a `#[lazy(instance, key = "..")]` attribute.
+///
+/// Without a tier the value is kept in persistent storage, and without a key it is stored under
+/// the name of the struct.
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct LazyArgs {
+    persistent: Flag,
+    instance: Flag,
+    temporary: Flag,
+    key: Option<String>,
+}
 
 pub(crate) fn from_item(item: Item) -> Result<TokenStream, syn::Error> {
     let Item::Struct(ItemStruct {
-        fields: Fields::Unit,
+        fields,
         ident,
         generics,
+        attrs,
         ..
     }) = item
     else {
         return Err(syn::Error::new(
             Span::call_site(),
-            "Lazy can only be derived on empty structs.",
+            "Lazy can only be derived on structs.",
         ));
     };
 
     let (impl_generics, ty_generics, _) = generics.split_for_impl();
-    Ok(quote! {
-        impl #impl_generics loam_sdk::soroban_sdk::Lazy for #ident #ty_generics {
-            fn get_lazy() -> Option<Self> {
-                Some(Self)
+    if let Fields::Unit = fields {
+        return Ok(quote! {
+            impl #impl_generics loam_sdk::soroban_sdk::Lazy for #ident #ty_generics {
+                const STORAGE_KEYS: &'static [loam_sdk::soroban_sdk::StorageKey] = &[];
+
+                fn get_lazy() -> Option<Self> {
+                    Some(Self)
+                }
+
+                fn set_lazy(self) {}
             }
+        });
+    }
 
-            fn set_lazy(self) {}
+    let args = lazy_args(&attrs)?;
+    let (tier, item_type) = match (
+        args.persistent.is_present(),
+        args.instance.is_present(),
+        args.temporary.is_present(),
+    ) {
+        (_, false, false) => ("persistent", quote!(PersistentItem)),
+        (false, true, false) => ("instance", quote!(InstanceItem)),
+        (false, false, true) => ("temporary", quote!(TemporaryItem)),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &ident,
+                "choose one of `persistent`, `instance` or `temporary` storage",
+            ))
         }
+    };
+    let key = args.key.unwrap_or_else(|| ident.to_string());
+    if key.is_empty()
+        || key.len() > MAX_KEY_LEN
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(syn::Error::new_spanned(
+            &ident,
+            format!(
+                "storage key `{key}` must be 1 to {MAX_KEY_LEN} characters of a-z, A-Z, 0-9 or _"
+            ),
+        ));
+    }
+    let storage_key = storage_key(&quote!(soroban_sdk), tier, "Symbol", &key);
+    let ttl = ttl_policy(&attrs)?;
+
+    Ok(quote! {
+        const _: () = {
+            use loam_sdk::soroban_sdk;
+
+            #[derive(Default)]
+            struct LazyKey;
+
+            impl soroban_sdk::LoamKey for LazyKey {
+                #ttl
+
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(
+                        &soroban_sdk::Symbol::new(soroban_sdk::env(), #key),
+                        soroban_sdk::env(),
+                    )
+                }
+            }
+
+            impl #impl_generics soroban_sdk::Lazy for #ident #ty_generics {
+                const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[#storage_key];
+
+                fn get_lazy() -> Option<Self> {
+                    soroban_sdk::#item_type::<Self, LazyKey>::default().get()
+                }
+
+                fn set_lazy(self) {
+                    soroban_sdk::#item_type::<Self, LazyKey>::default().set(&self);
+                }
+
+                fn snapshot(&self) -> Option<soroban_sdk::Val> {
+                    Some(soroban_sdk::IntoVal::into_val(self, soroban_sdk::env()))
+                }
+            }
+        };
     })
 }
 
+fn lazy_args(attrs: &[Attribute]) -> Result<LazyArgs, syn::Error> {
+    attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("lazy"))
+        .map_or_else(
+            || Ok(LazyArgs::default()),
+            |attr| Ok(LazyArgs::from_meta(&attr.meta)?),
+        )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::util::equal_tokens;
 
     #[test]
     fn test_into_key() {
@@ -42,4 +144,70 @@ mod test {
         let impl_ = syn::parse_str::<syn::ItemImpl>(result.as_str()).unwrap();
         println!("{impl_:#?}");
     }
+
+    #[test]
+    fn test_instance_with_key_and_ttl() {
+        let input: Item = syn::parse_quote! {
+            #[contracttype]
+            #[lazy(instance, key = "ADMIN")]
+            #[ttl(threshold = 100, extend_to = 1000, write)]
+            pub struct Admin(Address);
+        };
+        let result = from_item(input).unwrap();
+        let expected = quote! {
+            const _: () = {
+                use loam_sdk::soroban_sdk;
+
+                #[derive(Default)]
+                struct LazyKey;
+
+                impl soroban_sdk::LoamKey for LazyKey {
+                    const TTL: Option<soroban_sdk::TtlPolicy> = Some(soroban_sdk::TtlPolicy {
+                        threshold: 100,
+                        extend_to: 1000,
+                        on_read: false,
+                        on_write: true,
+                    });
+
+                    fn to_key(&self) -> soroban_sdk::Val {
+                        soroban_sdk::IntoVal::into_val(
+                            &soroban_sdk::Symbol::new(soroban_sdk::env(), "ADMIN"),
+                            soroban_sdk::env(),
+                        )
+                    }
+                }
+
+                impl soroban_sdk::Lazy for Admin {
+                    const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[
+                        soroban_sdk::StorageKey::new(
+                            soroban_sdk::StorageTier::Instance,
+                            soroban_sdk::KeyName::Symbol("ADMIN"),
+                        )
+                    ];
+
+                    fn get_lazy() -> Option<Self> {
+                        soroban_sdk::InstanceItem::<Self, LazyKey>::default().get()
+                    }
+
+                    fn set_lazy(self) {
+                        soroban_sdk::InstanceItem::<Self, LazyKey>::default().set(&self);
+                    }
+
+                    fn snapshot(&self) -> Option<soroban_sdk::Val> {
+                        Some(soroban_sdk::IntoVal::into_val(self, soroban_sdk::env()))
+                    }
+                }
+            };
+        };
+        equal_tokens(&expected, &result);
+    }
+
+    #[test]
+    fn test_conflicting_tiers() {
+        let input: Item = syn::parse_quote! {
+            #[lazy(instance, temporary)]
+            pub struct Config { value: u32 }
+        };
+        assert!(from_item(input).is_err());
+    }
 }
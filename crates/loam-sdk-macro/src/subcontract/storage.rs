@@ -1,11 +1,26 @@
+use darling::{ast::NestedMeta, util::Flag, FromMeta};
 use heck::{ToSnakeCase, ToUpperCamelCase};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
-use syn::{Error, Fields, FieldsNamed, Ident, Item, ItemStruct, Result, Type};
+use syn::{Attribute, Error, Expr, Fields, FieldsNamed, Ident, Item, ItemStruct, Result, Type};
 
-pub(crate) fn from_item(item: Item) -> Result<TokenStream> {
+/// Longest name a `contracttype` enum variant, and so a generated storage key, can have.
+const MAX_KEY_LEN: usize = 32;
+
+const STORAGE_TYPES: &str = "Must use one of PersistentMap, PersistentIterableMap, InstanceMap, TemporaryMap, PersistentItem, InstanceItem, TemporaryItem, PersistentVec, InstanceVec, TemporaryVec, PersistentDoubleMap, InstanceDoubleMap, or TemporaryDoubleMap";
+
+/// Arguments of `#[loamstorage(..)]`.
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct StorageArgs {
+    /// Version of the storage layout, which makes the struct `Versioned`.
+    version: Option<u32>,
+}
+
+pub(crate) fn from_item(attr: TokenStream, item: Item) -> Result<TokenStream> {
+    let args = StorageArgs::from_list(&NestedMeta::parse_meta_list(attr)?)?;
     match item {
-        Item::Struct(item_struct) => generate_storage(&item_struct),
+        Item::Struct(item_struct) => generate_storage(&item_struct, &args),
         _ => Err(Error::new_spanned(
             item,
             "loamstorage can only be applied to structs",
@@ -13,8 +28,43 @@ pub(crate) fn from_item(item: Item) -> Result<TokenStream> {
     }
 }
 
-fn generate_storage(item_struct: &ItemStruct) -> Result<TokenStream> {
+/// Reject generated key variants that collide or are too long to be a `Symbol`.
+fn check_key_variants(variants: &[(Ident, TokenStream)]) -> Result<()> {
+    let mut seen = std::collections::BTreeSet::new();
+    if let Some((ident, _)) = variants
+        .iter()
+        .find(|(ident, _)| !seen.insert(ident.to_string()))
+    {
+        return Err(Error::new(
+            ident.span(),
+            format!(
+                "storage key `{ident}` is generated for two fields, as with a field `foo_len` next to \
+                 an indexed field `foo`; rename one of them"
+            ),
+        ));
+    }
+    if let Some((ident, _)) = variants
+        .iter()
+        .find(|(ident, _)| ident.to_string().len() > MAX_KEY_LEN)
+    {
+        return Err(Error::new(
+            ident.span(),
+            format!(
+                "storage key `{ident}` is longer than {MAX_KEY_LEN} characters; shorten the struct or field name"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn generate_storage(item_struct: &ItemStruct, args: &StorageArgs) -> Result<TokenStream> {
     let struct_name = &item_struct.ident;
+    if args.version == Some(0) {
+        return Err(Error::new_spanned(
+            struct_name,
+            "storage versions start at 1",
+        ));
+    }
     let Fields::Named(FieldsNamed { named: fields, .. }) = &item_struct.fields else {
         return Err(Error::new_spanned(
             item_struct,
@@ -25,30 +75,46 @@ fn generate_storage(item_struct: &ItemStruct) -> Result<TokenStream> {
 
     let (struct_fields, additional_items): (Vec<TokenStream>, Vec<TokenStream>) = fields
         .iter()
-        .map(|field| {
-            let field_name = field.ident.as_ref();
-            let field_type = &field.ty;
-            let Type::Path(type_path) = field_type else {
-                return Err(Error::new_spanned(field_type, "Must use one of PersistentMap, InstanceMap, TemporaryMap, PersistentItem, InstanceItem, or TemporaryItem"));
-            };
-
-            let last_segment = type_path.path.segments.last().unwrap();
-            let key_wrapper = format_ident!("{}{}Key", struct_name, field_name.as_ref().unwrap().to_string().to_upper_camel_case());
-
-            match last_segment.ident.to_string().as_str() {
-                ident @ ("PersistentMap" | "InstanceMap" | "TemporaryMap") => {
-                    generate_map_field(field_name, field_type, &key_wrapper, ident, &module_name, struct_name)
-                },
-                ident @ ("PersistentItem" | "InstanceItem" | "TemporaryItem") => {
-                    generate_store_field(field_name, field_type, &key_wrapper, ident, &module_name, struct_name)
-                },
-                _ => Err(Error::new_spanned(field_type, "Must use one of PersistentMap, InstanceMap, TemporaryMap, PersistentItem, InstanceItem, or TemporaryItem")),
-            }
-        })
+        .map(|field| generate_field(field, struct_name, &module_name))
         .collect::<Result<Vec<_>>>()?
         .into_iter()
         .unzip();
 
+    let data_key = format_ident!("{struct_name}Key");
+    let FieldKeys {
+        mut data_key_variants,
+        mut storage_keys,
+        layouts,
+    } = field_keys(fields, struct_name)?;
+    let layout = storage_layout(struct_name, args.version, &layouts);
+    let versioned = args.version.map(|version| {
+        let version_case = format_ident!("{struct_name}SchemaVersion");
+        storage_keys.push(storage_key(
+            &quote!(soroban_sdk),
+            "instance",
+            "Variant",
+            &version_case.to_string(),
+        ));
+        data_key_variants.push((version_case.clone(), quote! { #version_case }));
+        quote! {
+            impl soroban_sdk::Versioned for #struct_name {
+                const VERSION: u32 = #version;
+
+                fn stored_version() -> Option<u32> {
+                    soroban_sdk::store::instance().get(&#data_key::#version_case)
+                }
+
+                fn set_stored_version(version: u32) {
+                    soroban_sdk::store::instance().set(&#data_key::#version_case, &version);
+                }
+            }
+        }
+    });
+
+    check_key_variants(&data_key_variants)?;
+    let data_key_variants = data_key_variants.into_iter().map(|(_, variant)| variant);
+
+    let extend_ttl = extend_ttl_methods(struct_name, fields, args.version.is_some());
     let main_struct = quote! {
         #[derive(Clone, Default)]
         pub struct #struct_name {
@@ -56,16 +122,17 @@ fn generate_storage(item_struct: &ItemStruct) -> Result<TokenStream> {
         }
 
         impl soroban_sdk::Lazy for #struct_name {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[#(#storage_keys),*];
+
             fn get_lazy() -> Option<Self> {
                 Some(#struct_name::default())
             }
 
             fn set_lazy(self) {}
         }
-    };
 
-    let data_key_variants = generate_data_key_variants(fields, struct_name)?;
-    let data_key = format_ident!("{struct_name}Key");
+        #extend_ttl
+    };
 
     let additional_items = quote! {
         #[derive(Clone)]
@@ -75,6 +142,10 @@ fn generate_storage(item_struct: &ItemStruct) -> Result<TokenStream> {
         }
 
         #(#additional_items)*
+
+        #versioned
+
+        #layout
     };
 
     Ok(quote! {
@@ -86,6 +157,239 @@ fn generate_storage(item_struct: &ItemStruct) -> Result<TokenStream> {
     })
 }
 
+/// Methods extending the TTL of the instance and of the items of a `#[loamstorage]` struct, and
+/// its `ExtendTtl` implementation.
+fn extend_ttl_methods(
+    struct_name: &Ident,
+    fields: &syn::punctuated::Punctuated<syn::Field, syn::Token![,]>,
+    versioned: bool,
+) -> TokenStream {
+    let items = fields
+        .iter()
+        .filter(|field| {
+            matches!(
+                type_name(&field.ty).as_str(),
+                "PersistentItem" | "TemporaryItem"
+            )
+        })
+        .map(|field| {
+            let name = &field.ident;
+            quote! {
+                if self.#name.has() {
+                    self.#name.extend_ttl(threshold, extend_to);
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let unused = items
+        .is_empty()
+        .then(|| quote! { let _ = (threshold, extend_to); });
+    let uses_instance = versioned
+        || fields
+            .iter()
+            .any(|field| storage_tier(&field.ty) == "instance");
+    let extend_instance =
+        uses_instance.then(|| quote! { storage.extend_all_instance(threshold, extend_to); });
+    quote! {
+        impl #struct_name {
+            /// Extend the TTL of the contract instance, which holds the instance fields.
+            #[allow(clippy::unused_self)]
+            pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+            }
+
+            /// Extend the TTL of the persistent and temporary items that have a value.
+            pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                #unused
+                #(#items)*
+            }
+        }
+
+        impl soroban_sdk::ExtendTtl for #struct_name {
+            fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                let storage = Self::default();
+                #extend_instance
+                storage.extend_item_fields(threshold, extend_to);
+            }
+        }
+    }
+}
+
+/// Storage keys generated for the fields of a `#[loamstorage]` struct.
+struct FieldKeys {
+    /// Variants of the key enum, with their names.
+    data_key_variants: Vec<(Ident, TokenStream)>,
+    /// Entries of `Lazy::STORAGE_KEYS`.
+    storage_keys: Vec<TokenStream>,
+    /// Layout of each field, as JSON.
+    layouts: Vec<String>,
+}
+
+fn field_keys(
+    fields: &syn::punctuated::Punctuated<syn::Field, syn::Token![,]>,
+    struct_name: &Ident,
+) -> Result<FieldKeys> {
+    let mut data_key_variants = Vec::new();
+    let mut storage_keys = Vec::new();
+    let mut layouts = Vec::new();
+    for (field, variants) in fields
+        .iter()
+        .zip(generate_data_key_variants(fields, struct_name)?)
+    {
+        let tier = storage_tier(&field.ty);
+        let mut keys = Vec::new();
+        for variant in variants {
+            let ident = syn::parse2::<syn::Variant>(variant.clone())?.ident;
+            storage_keys.push(storage_key(
+                &quote!(soroban_sdk),
+                tier,
+                "Variant",
+                &ident.to_string(),
+            ));
+            keys.push(format!("\"{ident}\""));
+            data_key_variants.push((ident, variant));
+        }
+        layouts.push(format!(
+            r#"{{"name":"{}","type":"{}","keys":[{}]}}"#,
+            field
+                .ident
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            type_name(&field.ty),
+            keys.join(",")
+        ));
+    }
+    Ok(FieldKeys {
+        data_key_variants,
+        storage_keys,
+        layouts,
+    })
+}
+
+/// Layout of the struct as JSON in the contract's metadata, which lets tools such as
+/// `loam storage dump` tell which field an entry belongs to.
+fn storage_layout(struct_name: &Ident, version: Option<u32>, fields: &[String]) -> TokenStream {
+    let version = version.map_or_else(|| "null".to_string(), |version| version.to_string());
+    let layout = format!(
+        r#"{{"struct":"{struct_name}","version":{version},"fields":[{}]}}"#,
+        fields.join(",")
+    );
+    quote! {
+        soroban_sdk::contractmeta!(key = "loamstorage", val = #layout);
+    }
+}
+
+/// Name of a field's storage type, e.g. `PersistentMap`.
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// An entry of `Lazy::STORAGE_KEYS`: the key `name` of kind `kind`, a variant of `KeyName`, in
+/// `tier` storage. `sdk` is the path to `soroban_sdk` that the rest of the generated code uses.
+pub(crate) fn storage_key(sdk: &TokenStream, tier: &str, kind: &str, name: &str) -> TokenStream {
+    let tier = match tier {
+        "instance" => quote!(Instance),
+        "temporary" => quote!(Temporary),
+        _ => quote!(Persistent),
+    };
+    let kind = format_ident!("{kind}");
+    quote! {
+        #sdk::StorageKey::new(#sdk::StorageTier::#tier, #sdk::KeyName::#kind(#name))
+    }
+}
+
+/// Storage tier of a field, from the prefix of its type name.
+fn storage_tier(ty: &Type) -> &'static str {
+    let name = type_name(ty);
+    if name.starts_with("Instance") {
+        "instance"
+    } else if name.starts_with("Temporary") {
+        "temporary"
+    } else {
+        "persistent"
+    }
+}
+
+fn generate_field(
+    field: &syn::Field,
+    struct_name: &Ident,
+    module_name: &Ident,
+) -> Result<(TokenStream, TokenStream)> {
+    let field_name = field.ident.as_ref();
+    let field_type = &field.ty;
+    let Type::Path(type_path) = field_type else {
+        return Err(Error::new_spanned(field_type, STORAGE_TYPES));
+    };
+
+    let last_segment = type_path.path.segments.last().unwrap();
+    let key_wrapper = format_ident!(
+        "{}{}Key",
+        struct_name,
+        field_name
+            .as_ref()
+            .unwrap()
+            .to_string()
+            .to_upper_camel_case()
+    );
+
+    let ttl = ttl_policy(&field.attrs)?;
+
+    let (struct_field, additional_item) = match last_segment.ident.to_string().as_str() {
+        ident @ ("PersistentMap" | "PersistentIterableMap" | "InstanceMap" | "TemporaryMap") => {
+            generate_map_field(
+                field_name,
+                field_type,
+                &key_wrapper,
+                ident,
+                module_name,
+                struct_name,
+                ttl.as_ref(),
+            )
+        }
+        ident @ ("PersistentItem" | "InstanceItem" | "TemporaryItem") => generate_store_field(
+            field_name,
+            field_type,
+            &key_wrapper,
+            ident,
+            module_name,
+            struct_name,
+            ttl.as_ref(),
+        ),
+        ident @ ("PersistentVec" | "InstanceVec" | "TemporaryVec") => generate_vec_field(
+            field_name,
+            field_type,
+            &key_wrapper,
+            ident,
+            module_name,
+            struct_name,
+            ttl.as_ref(),
+        ),
+        ident @ ("PersistentDoubleMap" | "InstanceDoubleMap" | "TemporaryDoubleMap") => {
+            generate_double_map_field(
+                field_name,
+                field_type,
+                &key_wrapper,
+                ident,
+                module_name,
+                struct_name,
+                ttl.as_ref(),
+            )
+        }
+        _ => Err(Error::new_spanned(field_type, STORAGE_TYPES)),
+    }?;
+    let vis = &field.vis;
+    Ok((quote! { #vis #struct_field }, additional_item))
+}
+
 fn generate_map_field(
     field_name: Option<&syn::Ident>,
     field_type: &Type,
@@ -93,6 +397,7 @@ fn generate_map_field(
     map_type: &str,
     module_name: &Ident,
     struct_name: &Ident,
+    ttl: Option<&TokenStream>,
 ) -> Result<(TokenStream, TokenStream)> {
     let Type::Path(type_path) = field_type else {
         return Err(Error::new_spanned(
@@ -124,6 +429,29 @@ fn generate_map_field(
     let value_type = &generic_args.args[1];
     let data_key = format_ident!("{struct_name}Key");
 
+    let index_items = (map_type == "PersistentIterableMap").then(|| {
+        let len_case = format_ident!("{}Len", enum_case_name.as_ref().unwrap());
+        let index_case = format_ident!("{}Idx", enum_case_name.as_ref().unwrap());
+        let position_case = format_ident!("{}Pos", enum_case_name.as_ref().unwrap());
+        quote! {
+            impl soroban_sdk::LoamIndexKey for #key_wrapper {
+                fn len_key() -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&#data_key::#len_case, soroban_sdk::env())
+                }
+
+                fn index_key(index: u32) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&#data_key::#index_case(index), soroban_sdk::env())
+                }
+            }
+
+            impl soroban_sdk::LoamIterableKey for #key_wrapper {
+                fn position_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&#data_key::#position_case(self.0.clone()), soroban_sdk::env())
+                }
+            }
+        }
+    });
+
     let additional_item = quote! {
         #[derive(Clone)]
         pub struct #key_wrapper(#key_type);
@@ -135,10 +463,13 @@ fn generate_map_field(
         }
 
         impl soroban_sdk::LoamKey for #key_wrapper {
+            #ttl
             fn to_key(&self) -> soroban_sdk::Val {
                 soroban_sdk::IntoVal::into_val(&#data_key::#enum_case_name(self.0.clone()),soroban_sdk::env())
             }
         }
+
+        #index_items
     };
     let map_type_ident = format_ident!("{}", map_type);
     let struct_field =
@@ -153,6 +484,7 @@ fn generate_store_field(
     store_type: &str,
     module_name: &Ident,
     struct_name: &Ident,
+    ttl: Option<&TokenStream>,
 ) -> Result<(TokenStream, TokenStream)> {
     let Type::Path(type_path) = field_type else {
         return Err(Error::new_spanned(
@@ -185,6 +517,7 @@ fn generate_store_field(
         pub struct #key_wrapper;
 
         impl soroban_sdk::LoamKey for #key_wrapper {
+            #ttl
             fn to_key(&self) -> soroban_sdk::Val {
                 soroban_sdk::IntoVal::into_val(&#data_key::#enum_case_name, soroban_sdk::env())
             }
@@ -193,6 +526,197 @@ fn generate_store_field(
     Ok((struct_field, additional_item))
 }
 
+fn generate_vec_field(
+    field_name: Option<&syn::Ident>,
+    field_type: &Type,
+    key_wrapper: &syn::Ident,
+    vec_type: &str,
+    module_name: &Ident,
+    struct_name: &Ident,
+    ttl: Option<&TokenStream>,
+) -> Result<(TokenStream, TokenStream)> {
+    let Type::Path(type_path) = field_type else {
+        return Err(Error::new_spanned(
+            field_type,
+            format!("{vec_type} must be a path type"),
+        ));
+    };
+    let last_segment = type_path.path.segments.last().unwrap();
+    let syn::PathArguments::AngleBracketed(generic_args) = &last_segment.arguments else {
+        return Err(Error::new_spanned(
+            field_type,
+            format!("{vec_type} must contain value type"),
+        ));
+    };
+
+    let enum_case_name = field_to_enum_case(field_name, struct_name);
+    let len_case = format_ident!("{}Len", enum_case_name.as_ref().unwrap());
+    let value_type = &generic_args.args[0];
+    let vec_type_ident = format_ident!("{}", vec_type);
+    let struct_field =
+        quote! { #field_name: #vec_type_ident<#value_type, #module_name::#key_wrapper> };
+    let data_key = format_ident!("{struct_name}Key");
+    let additional_item = quote! {
+        #[derive(Clone, Default)]
+        pub struct #key_wrapper;
+
+        impl soroban_sdk::LoamKey for #key_wrapper {
+            #ttl
+            fn to_key(&self) -> soroban_sdk::Val {
+                <Self as soroban_sdk::LoamIndexKey>::len_key()
+            }
+        }
+
+        impl soroban_sdk::LoamIndexKey for #key_wrapper {
+            fn len_key() -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#len_case, soroban_sdk::env())
+            }
+
+            fn index_key(index: u32) -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#enum_case_name(index), soroban_sdk::env())
+            }
+        }
+    };
+    Ok((struct_field, additional_item))
+}
+
+fn generate_double_map_field(
+    field_name: Option<&syn::Ident>,
+    field_type: &Type,
+    key_wrapper: &syn::Ident,
+    map_type: &str,
+    module_name: &Ident,
+    struct_name: &Ident,
+    ttl: Option<&TokenStream>,
+) -> Result<(TokenStream, TokenStream)> {
+    let Type::Path(type_path) = field_type else {
+        return Err(Error::new_spanned(
+            field_type,
+            format!("{map_type} must be a path type"),
+        ));
+    };
+    let last_segment = type_path.path.segments.last().unwrap();
+    let syn::PathArguments::AngleBracketed(generic_args) = &last_segment.arguments else {
+        return Err(Error::new_spanned(
+            field_type,
+            format!("{map_type} must contain two key types and a value type"),
+        ));
+    };
+    if generic_args.args.len() != 3 {
+        return Err(Error::new_spanned(
+            field_type,
+            format!("{map_type} must contain two key types and a value type"),
+        ));
+    }
+    let enum_case_name = field_to_enum_case(field_name, struct_name);
+    let len_case = format_ident!("{}Len", enum_case_name.as_ref().unwrap());
+    let index_case = format_ident!("{}Idx", enum_case_name.as_ref().unwrap());
+    let position_case = format_ident!("{}Pos", enum_case_name.as_ref().unwrap());
+    let first_type = &generic_args.args[0];
+    let second_type = &generic_args.args[1];
+    let value_type = &generic_args.args[2];
+    let data_key = format_ident!("{struct_name}Key");
+
+    let additional_item = quote! {
+        #[derive(Clone)]
+        pub struct #key_wrapper(#first_type, #second_type);
+
+        impl From<(#first_type, #second_type)> for #key_wrapper {
+            fn from((first, second): (#first_type, #second_type)) -> Self {
+                Self(first, second)
+            }
+        }
+
+        impl soroban_sdk::LoamKey for #key_wrapper {
+            #ttl
+            fn to_key(&self) -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#enum_case_name(self.0.clone(), self.1.clone()), soroban_sdk::env())
+            }
+        }
+
+        impl soroban_sdk::LoamDoubleKey<#first_type> for #key_wrapper {
+            fn len_key(first: &#first_type) -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#len_case(first.clone()), soroban_sdk::env())
+            }
+
+            fn index_key(first: &#first_type, index: u32) -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#index_case(first.clone(), index), soroban_sdk::env())
+            }
+
+            fn position_key(&self) -> soroban_sdk::Val {
+                soroban_sdk::IntoVal::into_val(&#data_key::#position_case(self.0.clone(), self.1.clone()), soroban_sdk::env())
+            }
+        }
+    };
+    let map_type_ident = format_ident!("{}", map_type);
+    let struct_field = quote! {
+        #field_name: #map_type_ident<#first_type, #second_type, #value_type, #module_name::#key_wrapper>
+    };
+    Ok((struct_field, additional_item))
+}
+
+/// Arguments of a `#[ttl(threshold = .., extend_to = .., read, write)]` attribute.
+///
+/// Without `read` or `write` the entry is extended on both.
+#[derive(FromMeta)]
+struct TtlArgs {
+    threshold: Expr,
+    extend_to: Expr,
+    read: Flag,
+    write: Flag,
+}
+
+/// The `TTL` const for a key wrapper, if the field or struct has a `#[ttl(..)]` attribute.
+pub(crate) fn ttl_policy(attrs: &[Attribute]) -> Result<Option<TokenStream>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("ttl")) else {
+        return Ok(None);
+    };
+    let TtlArgs {
+        threshold,
+        extend_to,
+        read,
+        write,
+    } = TtlArgs::from_meta(&attr.meta)?;
+    let both = !read.is_present() && !write.is_present();
+    let on_read = both || read.is_present();
+    let on_write = both || write.is_present();
+    let policy = quote! {
+        Some(soroban_sdk::TtlPolicy {
+            threshold: #threshold,
+            extend_to: #extend_to,
+            on_read: #on_read,
+            on_write: #on_write,
+        })
+    };
+    // The host rejects extensions with a threshold above `extend_to`, so catch that at build time:
+    // here for literals, and in the const itself for anything else, such as `DAY * 30`.
+    let policy = match (int_literal(&threshold), int_literal(&extend_to)) {
+        (Some(low), Some(high)) if low > high => {
+            return Err(Error::new_spanned(&threshold, TTL_ORDER));
+        }
+        (Some(_), Some(_)) => policy,
+        _ => quote! {{
+            assert!(#threshold <= #extend_to, #TTL_ORDER);
+            #policy
+        }},
+    };
+    Ok(Some(quote! {
+        const TTL: Option<soroban_sdk::TtlPolicy> = #policy;
+    }))
+}
+
+const TTL_ORDER: &str = "the `threshold` of `#[ttl]` must not be greater than its `extend_to`";
+
+fn int_literal(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_parse().ok(),
+        _ => None,
+    }
+}
+
 fn field_to_enum_case(field_name: Option<&Ident>, struct_name: &Ident) -> Option<Ident> {
     field_name.map(|name| {
         let enum_case = format!("{}{}", struct_name, name.to_string().to_upper_camel_case());
@@ -203,36 +727,104 @@ fn field_to_enum_case(field_name: Option<&Ident>, struct_name: &Ident) -> Option
 fn generate_data_key_variants(
     fields: &syn::punctuated::Punctuated<syn::Field, syn::Token![,]>,
     struct_name: &Ident,
-) -> Result<Vec<TokenStream>> {
-    fields.iter().map(|field| {
-        let field_name = field.ident.as_ref();
-        let field_name = field_to_enum_case(field_name, struct_name);
-        let field_type = &field.ty;
-
-        let Type::Path(type_path) = field_type else {
-            return Err(Error::new_spanned(field_type, "Must use one of PersistentMap, InstanceMap, TemporaryMap, PersistentItem, InstanceItem, or TemporaryItem"));
-        };
-        let last_segment = type_path.path.segments.last().unwrap();
-        match last_segment.ident.to_string().as_str() {
-            "PersistentMap" | "InstanceMap" | "TemporaryMap" => {
-                let args = &last_segment.arguments;
-                if let syn::PathArguments::AngleBracketed(generic_args) = args {
-                    if generic_args.args.len() == 2 {
-                        let key_type = &generic_args.args[0];
-                        Ok(quote! { #field_name(#key_type) })
+) -> Result<Vec<Vec<TokenStream>>> {
+    fields
+        .iter()
+        .map(|field| {
+            let field_name = field.ident.as_ref();
+            let field_name = field_to_enum_case(field_name, struct_name);
+            let field_type = &field.ty;
+
+            let Type::Path(type_path) = field_type else {
+                return Err(Error::new_spanned(field_type, STORAGE_TYPES));
+            };
+            let last_segment = type_path.path.segments.last().unwrap();
+            match last_segment.ident.to_string().as_str() {
+                "PersistentMap" | "InstanceMap" | "TemporaryMap" => {
+                    let args = &last_segment.arguments;
+                    if let syn::PathArguments::AngleBracketed(generic_args) = args {
+                        if generic_args.args.len() == 2 {
+                            let key_type = &generic_args.args[0];
+                            Ok(vec![quote! { #field_name(#key_type) }])
+                        } else {
+                            Err(Error::new_spanned(
+                                field_type,
+                                "Map must contain key and value types",
+                            ))
+                        }
                     } else {
-                        Err(Error::new_spanned(field_type, "Map must contain key and value types"))
+                        Err(Error::new_spanned(
+                            field_type,
+                            "Map must contain key and value types",
+                        ))
                     }
-                } else {
-                    Err(Error::new_spanned(field_type, "Map must contain key and value types"))
                 }
-            },
-            "PersistentItem" | "InstanceItem" | "TemporaryItem" => {
-                Ok(quote! { #field_name })
-            },
-            _ => Err(Error::new_spanned(field_type, "Must use one of PersistentMap, InstanceMap, TemporaryMap, PersistentItem, InstanceItem, or TemporaryItem")),
-        }
-    }).collect()
+                "PersistentIterableMap" => {
+                    let args = &last_segment.arguments;
+                    if let syn::PathArguments::AngleBracketed(generic_args) = args {
+                        if generic_args.args.len() == 2 {
+                            let key_type = &generic_args.args[0];
+                            let field_name = field_name.as_ref().unwrap();
+                            let len_case = format_ident!("{field_name}Len");
+                            let index_case = format_ident!("{field_name}Idx");
+                            let position_case = format_ident!("{field_name}Pos");
+                            Ok(vec![
+                                quote! { #field_name(#key_type) },
+                                quote! { #len_case },
+                                quote! { #index_case(u32) },
+                                quote! { #position_case(#key_type) },
+                            ])
+                        } else {
+                            Err(Error::new_spanned(
+                                field_type,
+                                "Map must contain key and value types",
+                            ))
+                        }
+                    } else {
+                        Err(Error::new_spanned(
+                            field_type,
+                            "Map must contain key and value types",
+                        ))
+                    }
+                }
+                "PersistentItem" | "InstanceItem" | "TemporaryItem" => {
+                    Ok(vec![quote! { #field_name }])
+                }
+                "PersistentDoubleMap" | "InstanceDoubleMap" | "TemporaryDoubleMap" => {
+                    let args = &last_segment.arguments;
+                    let syn::PathArguments::AngleBracketed(generic_args) = args else {
+                        return Err(Error::new_spanned(
+                            field_type,
+                            "DoubleMap must contain two key types and a value type",
+                        ));
+                    };
+                    if generic_args.args.len() != 3 {
+                        return Err(Error::new_spanned(
+                            field_type,
+                            "DoubleMap must contain two key types and a value type",
+                        ));
+                    }
+                    let first_type = &generic_args.args[0];
+                    let second_type = &generic_args.args[1];
+                    let field_name = field_name.as_ref().unwrap();
+                    let len_case = format_ident!("{field_name}Len");
+                    let index_case = format_ident!("{field_name}Idx");
+                    let position_case = format_ident!("{field_name}Pos");
+                    Ok(vec![
+                        quote! { #field_name(#first_type, #second_type) },
+                        quote! { #len_case(#first_type) },
+                        quote! { #index_case(#first_type, u32) },
+                        quote! { #position_case(#first_type, #second_type) },
+                    ])
+                }
+                "PersistentVec" | "InstanceVec" | "TemporaryVec" => {
+                    let len_case = format_ident!("{}Len", field_name.as_ref().unwrap());
+                    Ok(vec![quote! { #field_name(u32) }, quote! { #len_case }])
+                }
+                _ => Err(Error::new_spanned(field_type, STORAGE_TYPES)),
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -249,7 +841,7 @@ mod test {
                 baz: TemporaryItem<u64>,
             }
         };
-        let generated = from_item(input).unwrap();
+        let generated = from_item(TokenStream::new(), input).unwrap();
         let expected = quote! {
         #[derive(Clone, Default)]
         pub struct Foo {
@@ -257,11 +849,34 @@ mod test {
             baz: TemporaryItem<u64, foo_keys__::FooBazKey>,
         }
         impl soroban_sdk::Lazy for Foo {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("FooBar")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Temporary, soroban_sdk::KeyName::Variant("FooBaz"))];
             fn get_lazy() -> Option<Self> {
                 Some(Foo::default())
             }
             fn set_lazy(self) {}
         }
+
+            impl Foo {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    if self.baz.has() {
+                        self.baz.extend_ttl(threshold, extend_to);
+                    }
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Foo {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
         mod foo_keys__ {
             use super::*;
             #[derive(Clone)]
@@ -289,9 +904,447 @@ mod test {
                     soroban_sdk::IntoVal::into_val(&FooKey::FooBaz, soroban_sdk::env())
                 }
             }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Foo\",\"version\":null,\"fields\":[{\"name\":\"bar\",\"type\":\"PersistentMap\",\"keys\":[\"FooBar\"]},{\"name\":\"baz\",\"type\":\"TemporaryItem\",\"keys\":[\"FooBaz\"]}]}");
         }
 
                 };
         equal_tokens(&expected, &generated);
     }
+
+    #[test]
+    fn test_generate_iterable_storage() {
+        let input: Item = syn::parse_quote! {
+            struct Registry {
+                entries: PersistentIterableMap<Address, u64>,
+            }
+        };
+        let generated = from_item(TokenStream::new(), input).unwrap();
+        let expected = quote! {
+        #[derive(Clone, Default)]
+        pub struct Registry {
+            entries: PersistentIterableMap<Address, u64, registry_keys__::RegistryEntriesKey>,
+        }
+        impl soroban_sdk::Lazy for Registry {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("RegistryEntries")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("RegistryEntriesLen")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("RegistryEntriesIdx")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("RegistryEntriesPos"))];
+            fn get_lazy() -> Option<Self> {
+                Some(Registry::default())
+            }
+            fn set_lazy(self) {}
+        }
+
+            impl Registry {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    let _ = (threshold, extend_to);
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Registry {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
+        mod registry_keys__ {
+            use super::*;
+            #[derive(Clone)]
+            #[soroban_sdk::contracttype]
+            pub enum RegistryKey {
+                RegistryEntries(Address),
+                RegistryEntriesLen,
+                RegistryEntriesIdx(u32),
+                RegistryEntriesPos(Address),
+            }
+            #[derive(Clone)]
+            pub struct RegistryEntriesKey(Address);
+            impl From<Address> for RegistryEntriesKey {
+                fn from(key: Address) -> Self {
+                    Self(key)
+                }
+            }
+            impl soroban_sdk::LoamKey for RegistryEntriesKey {
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&RegistryKey::RegistryEntries(self.0.clone()), soroban_sdk::env())
+                }
+            }
+            impl soroban_sdk::LoamIndexKey for RegistryEntriesKey {
+                fn len_key() -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&RegistryKey::RegistryEntriesLen, soroban_sdk::env())
+                }
+                fn index_key(index: u32) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&RegistryKey::RegistryEntriesIdx(index), soroban_sdk::env())
+                }
+            }
+            impl soroban_sdk::LoamIterableKey for RegistryEntriesKey {
+                fn position_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&RegistryKey::RegistryEntriesPos(self.0.clone()), soroban_sdk::env())
+                }
+            }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Registry\",\"version\":null,\"fields\":[{\"name\":\"entries\",\"type\":\"PersistentIterableMap\",\"keys\":[\"RegistryEntries\",\"RegistryEntriesLen\",\"RegistryEntriesIdx\",\"RegistryEntriesPos\"]}]}");
+        }
+        };
+        equal_tokens(&expected, &generated);
+    }
+
+    #[test]
+    fn test_generate_storage_with_ttl() {
+        let input: Item = syn::parse_quote! {
+            struct Foo {
+                #[ttl(threshold = 100, extend_to = DAY * 30)]
+                bar: PersistentMap<String, u64>,
+                #[ttl(threshold = 10, extend_to = 20, write)]
+                baz: InstanceItem<u64>,
+            }
+        };
+        let generated = from_item(TokenStream::new(), input).unwrap();
+        let expected = quote! {
+        #[derive(Clone, Default)]
+        pub struct Foo {
+            bar: PersistentMap<String, u64, foo_keys__::FooBarKey>,
+            baz: InstanceItem<u64, foo_keys__::FooBazKey>,
+        }
+        impl soroban_sdk::Lazy for Foo {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("FooBar")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Instance, soroban_sdk::KeyName::Variant("FooBaz"))];
+            fn get_lazy() -> Option<Self> {
+                Some(Foo::default())
+            }
+            fn set_lazy(self) {}
+        }
+
+            impl Foo {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    let _ = (threshold, extend_to);
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Foo {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_all_instance(threshold, extend_to);
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
+        mod foo_keys__ {
+            use super::*;
+            #[derive(Clone)]
+            #[soroban_sdk::contracttype]
+            pub enum FooKey {
+                FooBar(String),
+                FooBaz,
+            }
+            #[derive(Clone)]
+            pub struct FooBarKey(String);
+            impl From<String> for FooBarKey {
+                fn from(key: String) -> Self {
+                    Self(key)
+                }
+            }
+            impl soroban_sdk::LoamKey for FooBarKey {
+                const TTL: Option<soroban_sdk::TtlPolicy> = {
+                    assert!(
+                        100 <= DAY * 30,
+                        "the `threshold` of `#[ttl]` must not be greater than its `extend_to`"
+                    );
+                    Some(soroban_sdk::TtlPolicy {
+                        threshold: 100,
+                        extend_to: DAY * 30,
+                        on_read: true,
+                        on_write: true,
+                    })
+                };
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&FooKey::FooBar(self.0.clone()), soroban_sdk::env())
+                }
+            }
+            #[derive(Clone, Default)]
+            pub struct FooBazKey;
+            impl soroban_sdk::LoamKey for FooBazKey {
+                const TTL: Option<soroban_sdk::TtlPolicy> = Some(soroban_sdk::TtlPolicy {
+                    threshold: 10,
+                    extend_to: 20,
+                    on_read: false,
+                    on_write: true,
+                });
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&FooKey::FooBaz, soroban_sdk::env())
+                }
+            }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Foo\",\"version\":null,\"fields\":[{\"name\":\"bar\",\"type\":\"PersistentMap\",\"keys\":[\"FooBar\"]},{\"name\":\"baz\",\"type\":\"InstanceItem\",\"keys\":[\"FooBaz\"]}]}");
+        }
+        };
+        equal_tokens(&expected, &generated);
+    }
+
+    #[test]
+    fn test_generate_vec_storage() {
+        let input: Item = syn::parse_quote! {
+            struct Log {
+                entries: TemporaryVec<String>,
+            }
+        };
+        let generated = from_item(TokenStream::new(), input).unwrap();
+        let expected = quote! {
+        #[derive(Clone, Default)]
+        pub struct Log {
+            entries: TemporaryVec<String, log_keys__::LogEntriesKey>,
+        }
+        impl soroban_sdk::Lazy for Log {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Temporary, soroban_sdk::KeyName::Variant("LogEntries")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Temporary, soroban_sdk::KeyName::Variant("LogEntriesLen"))];
+            fn get_lazy() -> Option<Self> {
+                Some(Log::default())
+            }
+            fn set_lazy(self) {}
+        }
+
+            impl Log {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    let _ = (threshold, extend_to);
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Log {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
+        mod log_keys__ {
+            use super::*;
+            #[derive(Clone)]
+            #[soroban_sdk::contracttype]
+            pub enum LogKey {
+                LogEntries(u32),
+                LogEntriesLen,
+            }
+            #[derive(Clone, Default)]
+            pub struct LogEntriesKey;
+            impl soroban_sdk::LoamKey for LogEntriesKey {
+                fn to_key(&self) -> soroban_sdk::Val {
+                    <Self as soroban_sdk::LoamIndexKey>::len_key()
+                }
+            }
+            impl soroban_sdk::LoamIndexKey for LogEntriesKey {
+                fn len_key() -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&LogKey::LogEntriesLen, soroban_sdk::env())
+                }
+                fn index_key(index: u32) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&LogKey::LogEntries(index), soroban_sdk::env())
+                }
+            }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Log\",\"version\":null,\"fields\":[{\"name\":\"entries\",\"type\":\"TemporaryVec\",\"keys\":[\"LogEntries\",\"LogEntriesLen\"]}]}");
+        }
+        };
+        equal_tokens(&expected, &generated);
+    }
+
+    #[test]
+    fn test_generate_versioned_storage() {
+        let input: Item = syn::parse_quote! {
+            struct Foo {
+                baz: TemporaryItem<u64>,
+            }
+        };
+        let generated = from_item(quote!(version = 2), input).unwrap();
+        let expected = quote! {
+        #[derive(Clone, Default)]
+        pub struct Foo {
+            baz: TemporaryItem<u64, foo_keys__::FooBazKey>,
+        }
+        impl soroban_sdk::Lazy for Foo {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Temporary, soroban_sdk::KeyName::Variant("FooBaz")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Instance, soroban_sdk::KeyName::Variant("FooSchemaVersion"))];
+            fn get_lazy() -> Option<Self> {
+                Some(Foo::default())
+            }
+            fn set_lazy(self) {}
+        }
+
+            impl Foo {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    if self.baz.has() {
+                        self.baz.extend_ttl(threshold, extend_to);
+                    }
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Foo {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_all_instance(threshold, extend_to);
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
+        mod foo_keys__ {
+            use super::*;
+            #[derive(Clone)]
+            #[soroban_sdk::contracttype]
+            pub enum FooKey {
+                FooBaz,
+                FooSchemaVersion,
+            }
+            #[derive(Clone, Default)]
+            pub struct FooBazKey;
+            impl soroban_sdk::LoamKey for FooBazKey {
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&FooKey::FooBaz, soroban_sdk::env())
+                }
+            }
+            impl soroban_sdk::Versioned for Foo {
+                const VERSION: u32 = 2u32;
+                fn stored_version() -> Option<u32> {
+                    soroban_sdk::store::instance().get(&FooKey::FooSchemaVersion)
+                }
+                fn set_stored_version(version: u32) {
+                    soroban_sdk::store::instance().set(&FooKey::FooSchemaVersion, &version);
+                }
+            }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Foo\",\"version\":2,\"fields\":[{\"name\":\"baz\",\"type\":\"TemporaryItem\",\"keys\":[\"FooBaz\"]}]}");
+        }
+        };
+        equal_tokens(&expected, &generated);
+    }
+
+    #[test]
+    fn test_generate_double_map_storage() {
+        let input: Item = syn::parse_quote! {
+            struct Token {
+                allowances: PersistentDoubleMap<Address, Address, i128>,
+            }
+        };
+        let generated = from_item(TokenStream::new(), input).unwrap();
+        let expected = quote! {
+        #[derive(Clone, Default)]
+        pub struct Token {
+            allowances: PersistentDoubleMap<Address, Address, i128, token_keys__::TokenAllowancesKey>,
+        }
+        impl soroban_sdk::Lazy for Token {
+            const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = &[soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("TokenAllowances")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("TokenAllowancesLen")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("TokenAllowancesIdx")), soroban_sdk::StorageKey::new(soroban_sdk::StorageTier::Persistent, soroban_sdk::KeyName::Variant("TokenAllowancesPos"))];
+            fn get_lazy() -> Option<Self> {
+                Some(Token::default())
+            }
+            fn set_lazy(self) {}
+        }
+
+            impl Token {
+                /// Extend the TTL of the contract instance, which holds the instance fields.
+                #[allow(clippy::unused_self)]
+                pub fn extend_all_instance(&self, threshold: u32, extend_to: u32) {
+                    soroban_sdk::store::instance().extend_ttl(threshold, extend_to);
+                }
+
+                /// Extend the TTL of the persistent and temporary items that have a value.
+                pub fn extend_item_fields(&self, threshold: u32, extend_to: u32) {
+                    let _ = (threshold, extend_to);
+                }
+            }
+
+            impl soroban_sdk::ExtendTtl for Token {
+                fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                    let storage = Self::default();
+                    storage.extend_item_fields(threshold, extend_to);
+                }
+            }
+        mod token_keys__ {
+            use super::*;
+            #[derive(Clone)]
+            #[soroban_sdk::contracttype]
+            pub enum TokenKey {
+                TokenAllowances(Address, Address),
+                TokenAllowancesLen(Address),
+                TokenAllowancesIdx(Address, u32),
+                TokenAllowancesPos(Address, Address),
+            }
+            #[derive(Clone)]
+            pub struct TokenAllowancesKey(Address, Address);
+            impl From<(Address, Address)> for TokenAllowancesKey {
+                fn from((first, second): (Address, Address)) -> Self {
+                    Self(first, second)
+                }
+            }
+            impl soroban_sdk::LoamKey for TokenAllowancesKey {
+                fn to_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&TokenKey::TokenAllowances(self.0.clone(), self.1.clone()), soroban_sdk::env())
+                }
+            }
+            impl soroban_sdk::LoamDoubleKey<Address> for TokenAllowancesKey {
+                fn len_key(first: &Address) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&TokenKey::TokenAllowancesLen(first.clone()), soroban_sdk::env())
+                }
+                fn index_key(first: &Address, index: u32) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&TokenKey::TokenAllowancesIdx(first.clone(), index), soroban_sdk::env())
+                }
+                fn position_key(&self) -> soroban_sdk::Val {
+                    soroban_sdk::IntoVal::into_val(&TokenKey::TokenAllowancesPos(self.0.clone(), self.1.clone()), soroban_sdk::env())
+                }
+            }
+            soroban_sdk::contractmeta!(key = "loamstorage", val = "{\"struct\":\"Token\",\"version\":null,\"fields\":[{\"name\":\"allowances\",\"type\":\"PersistentDoubleMap\",\"keys\":[\"TokenAllowances\",\"TokenAllowancesLen\",\"TokenAllowancesIdx\",\"TokenAllowancesPos\"]}]}");
+        }
+        };
+        equal_tokens(&expected, &generated);
+    }
+
+    #[test]
+    fn test_ttl_threshold_above_extend_to() {
+        let input: Item = syn::parse_quote! {
+            struct Foo {
+                #[ttl(threshold = 1000, extend_to = 100)]
+                bar: PersistentMap<String, u64>,
+            }
+        };
+        let error = from_item(TokenStream::new(), input).unwrap_err();
+        assert_eq!(error.to_string(), TTL_ORDER);
+    }
+
+    #[test]
+    fn test_storage_key_collision() {
+        let input: Item = syn::parse_quote! {
+            struct Foo {
+                bar: PersistentIterableMap<Address, u64>,
+                bar_len: InstanceItem<u32>,
+            }
+        };
+        let error = from_item(TokenStream::new(), input).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("storage key `FooBarLen` is generated for two fields"));
+    }
+
+    #[test]
+    fn test_storage_key_too_long() {
+        let input: Item = syn::parse_quote! {
+            struct MyFungibleToken {
+                allowances_by_spender: PersistentMap<Address, i128>,
+            }
+        };
+        let error = from_item(TokenStream::new(), input).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("storage key `MyFungibleTokenAllowancesBySpender` is longer than 32"));
+    }
 }
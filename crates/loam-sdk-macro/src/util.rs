@@ -1,162 +1,214 @@
-use std::{collections::BTreeMap, path::Path};
+use std::collections::BTreeMap;
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
-use stellar_xdr::curr as xdr;
-use syn::{File, ItemTrait, TraitItemFn};
+use syn::{fold::Fold, ItemTrait, Type};
 
-/// Read a crate starting from a single file then parse into a file
-pub fn parse_crate_as_file(path: &Path) -> Option<File> {
-    syn_file_expand::read_crate(path)
-        .map_err(|e| {
-            println!("{e}");
-            e
+use crate::subcontract::{auth::Auth, is_constructor, is_loam_attr, Mount};
+
+pub type Traits = BTreeMap<String, ItemTrait>;
+
+/// Contract methods exporting the methods of a subcontract trait as mounted by `mount` on
+/// `contract`. Constructor hooks are left to [`generate_constructor`].
+pub fn generate_methods(
+    item: &ItemTrait,
+    mount: &Mount,
+    contract: &syn::ItemStruct,
+) -> Vec<TokenStream> {
+    let mut concrete = Concrete::new(item, mount);
+    item.items
+        .iter()
+        .filter_map(|item| {
+            let syn::TraitItem::Fn(method) = item else {
+                return None;
+            };
+            if is_constructor(method) {
+                return None;
+            }
+            let export_name = mount.exported_name(&method.sig.ident)?;
+            Some(generate_method(
+                &concrete.fold_signature(method.sig.clone()),
+                &spec_attrs(method),
+                mount,
+                contract,
+                &export_name,
+            ))
         })
-        .ok()
-        .map(|file| file.to_token_stream().to_string())
-        .and_then(|file| syn::parse_file(&file).ok())
+        .collect::<Vec<_>>()
 }
 
-pub fn has_macro(attrs: &[syn::Attribute], macro_name: &str) -> bool {
-    for attr in attrs {
-        if format!("{:#?}", attr.path()).contains(macro_name) {
-            return true;
+/// The contract's `__constructor`, calling the constructor hooks of the mounted subcontracts in
+/// the order they are mounted, along with its arguments. Hooks share arguments of the same name;
+/// the arguments of hooks mounted under a prefix get the prefix, e.g. `ops_admin`.
+pub fn generate_constructor(
+    mounts: &[(&Mount, &ItemTrait)],
+    contract: &syn::ItemStruct,
+) -> Option<(TokenStream, Vec<(Ident, Type)>)> {
+    let mut inputs = Vec::<(Ident, Type)>::new();
+    let mut hooks = Vec::new();
+    for (mount, item) in mounts {
+        let mut concrete = Concrete::new(item, mount);
+        for method in item.items.iter().filter_map(|item| match item {
+            syn::TraitItem::Fn(method) if is_constructor(method) => Some(method),
+            _ => None,
+        }) {
+            if mount.exclude.contains(&method.sig.ident) {
+                continue;
+            }
+            let sig = concrete.fold_signature(method.sig.clone());
+            let mut args = Vec::new();
+            for arg in &sig.inputs {
+                let syn::FnArg::Typed(syn::PatType { pat, ty, .. }) = arg else {
+                    continue;
+                };
+                let syn::Pat::Ident(syn::PatIdent { ident, .. }) = &**pat else {
+                    continue;
+                };
+                // Resolved at the call site, as hooks of different subcontracts can share it.
+                let name = match &mount.prefix {
+                    Some(prefix) => format!("{prefix}_{ident}"),
+                    None => ident.to_string(),
+                };
+                let name = Ident::new(&name, Span::call_site());
+                if !inputs.iter().any(|(input, _)| *input == name) {
+                    inputs.push((name.clone(), (**ty).clone()));
+                }
+                args.push(name);
+            }
+            hooks.push((*mount, sig.ident, args));
         }
     }
-    false
+    if hooks.is_empty() {
+        return None;
+    }
+    // Arguments are moved into their last hook and cloned for the ones before.
+    let mut uses = BTreeMap::<Ident, usize>::new();
+    for arg in hooks.iter().flat_map(|(_, _, args)| args) {
+        *uses.entry(arg.clone()).or_default() += 1;
+    }
+    let calls = hooks.iter().map(|(mount, name, args)| {
+        let args = args
+            .iter()
+            .map(|arg| {
+                let left = uses.get_mut(arg).expect("every argument is counted");
+                *left -= 1;
+                if *left == 0 {
+                    quote!(#arg)
+                } else {
+                    quote!(::core::clone::Clone::clone(&#arg))
+                }
+            })
+            .collect::<Vec<_>>();
+        mount_call(mount, contract, name, &args)
+    });
+    let (names, types): (Vec<_>, Vec<_>) = inputs.iter().cloned().unzip();
+    let constructor = quote! {
+        pub fn __constructor(env: loam_sdk::soroban_sdk::Env, #(#names: #types),*) {
+            let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+            #(#calls;)*
+        }
+    };
+    Some((constructor, inputs))
 }
 
-use syn::visit::Visit;
-
-pub type Traits = BTreeMap<String, Vec<TokenStream>>;
-
-#[derive(Default)]
-pub struct TraitVisitor {
-    pub traits: Traits,
+/// Attributes of an exported method: those of the subcontract method, with a line of docs for
+/// each authorization it requires in place of the authorization attributes.
+fn spec_attrs(method: &syn::TraitItemFn) -> Vec<syn::Attribute> {
+    // `#[subcontract]` has already reported invalid authorization attributes.
+    let auths = Auth::from_method(method).unwrap_or_default();
+    let mut attrs = method
+        .attrs
+        .iter()
+        .filter(|attr| !is_loam_attr(attr))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !auths.is_empty() && attrs.iter().any(|attr| attr.path().is_ident("doc")) {
+        attrs.push(syn::parse_quote!(#[doc = ""]));
+    }
+    attrs.extend(auths.iter().map(|auth| {
+        let doc = format!(" {}", auth.doc());
+        syn::parse_quote!(#[doc = #doc])
+    }));
+    attrs
 }
 
-impl TraitVisitor {
-    pub fn find_traits_in_file(ast: &syn::File) -> Traits {
-        let mut visitor = TraitVisitor::default();
-        syn::visit::visit_file(&mut visitor, ast);
-        visitor.traits
+/// Replaces the type parameters of a generic subcontract trait with the types it is mounted with.
+struct Concrete(BTreeMap<Ident, Type>);
+
+impl Concrete {
+    fn new(item: &ItemTrait, mount: &Mount) -> Self {
+        Concrete(
+            item.generics
+                .type_params()
+                .map(|param| param.ident.clone())
+                .zip(mount.trait_args.iter().flat_map(|args| {
+                    args.args.iter().filter_map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => Some(ty.clone()),
+                        _ => None,
+                    })
+                }))
+                .collect(),
+        )
     }
 }
 
-impl<'ast> Visit<'ast> for TraitVisitor {
-    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
-        if has_macro(&item.attrs, "subcontract") {
-            self.traits
-                .insert(item.ident.to_string(), generate_methods(item));
+impl Fold for Concrete {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        if let Type::Path(syn::TypePath { qself: None, path }) = &ty {
+            if let Some(concrete) = path.get_ident().and_then(|ident| self.0.get(ident)) {
+                return concrete.clone();
+            }
         }
+        syn::fold::fold_type(self, ty)
     }
 }
 
-fn generate_methods(item: &ItemTrait) -> Vec<TokenStream> {
-    item.items
-        .iter()
-        .filter_map(|item| {
-            if let syn::TraitItem::Fn(TraitItemFn { sig, attrs, .. }) = item {
-                let name = &sig.ident;
-                Some(generate_method(sig, attrs, name))
-            } else {
-                None
+/// Call of the subcontract method `name` as mounted by `mount` on `contract`.
+fn mount_call(
+    mount: &Mount,
+    contract: &syn::ItemStruct,
+    name: &Ident,
+    args: &[impl ToTokens],
+) -> TokenStream {
+    let implementor = mount.implementor(&contract.ident);
+    let trait_path = mount.trait_path();
+    match &mount.prefix {
+        None if mount.trait_args.is_none() && contract.generics.params.is_empty() => {
+            quote! { #implementor::#name(#(#args),*) }
+        }
+        None => quote! { <#implementor as #trait_path>::#name(#(#args),*) },
+        Some(prefix) => {
+            let prefix_str = prefix.to_string();
+            quote! {
+                loam_sdk::soroban_sdk::store::with_prefix(#prefix_str, || {
+                    <#implementor as #trait_path>::#name(#(#args),*)
+                })
             }
-        })
-        .collect::<Vec<_>>()
+        }
+    }
 }
 
 fn generate_method(
     sig: &syn::Signature,
     attrs: &[syn::Attribute],
-    name: &syn::Ident,
+    mount: &Mount,
+    contract: &syn::ItemStruct,
+    export_name: &Ident,
 ) -> TokenStream {
+    let name = &sig.ident;
     let output = &sig.output;
-    let inputs = sig.inputs.iter().skip(1);
+    let inputs = sig
+        .inputs
+        .iter()
+        .filter(|arg| matches!(arg, syn::FnArg::Typed(_)));
     let args_without_self = crate::subcontract::get_args_without_self(&sig.inputs);
+    let call = mount_call(mount, contract, name, &args_without_self);
     quote! {
         #(#attrs)*
-        pub fn #name(env: loam_sdk::soroban_sdk::Env, #(#inputs),*) #output {
-            loam_sdk::soroban_sdk::set_env(env);
-            Contract::#name(#(#args_without_self),*)
-        }
-    }
-}
-
-pub const LOCAL: &str = "Standalone Network ; February 2017";
-pub const TESTNET: &str = "Test SDF Network ; September 2015";
-pub const FUTURENET: &str = "Test SDF Future Network ; October 2022";
-pub const MAINNET: &str = "Public Global Stellar Network ; September 2015";
-
-///
-/// Match network names to passphrases
-///
-///
-pub fn network_passphrase(s: &str) -> &'static str {
-    match s.to_lowercase().as_str() {
-        "local" => LOCAL,
-        "testnet" => TESTNET,
-        "future" => FUTURENET,
-        "main" => MAINNET,
-        _ => "",
-    }
-}
-
-pub fn generate_asset_id(
-    asset: &str,
-    network: &str,
-) -> Result<stellar_strkey::Contract, xdr::Error> {
-    use sha2::{Digest, Sha256};
-    use xdr::WriteXdr;
-    let asset = parse_asset(asset).unwrap();
-    let network_passphrase = network_passphrase(network);
-    let network_id = xdr::Hash(Sha256::digest(network_passphrase.as_bytes()).into());
-    let preimage = xdr::HashIdPreimage::ContractId(xdr::HashIdPreimageContractId {
-        network_id,
-        contract_id_preimage: xdr::ContractIdPreimage::Asset(asset.clone()),
-    });
-    let preimage_xdr = preimage.to_xdr(xdr::Limits::none())?;
-    Ok(stellar_strkey::Contract(
-        Sha256::digest(preimage_xdr).into(),
-    ))
-}
-
-pub fn parse_asset(str: &str) -> Result<xdr::Asset, xdr::Error> {
-    if str == "native" {
-        return Ok(xdr::Asset::Native);
-    }
-    let split: Vec<&str> = str.splitn(2, ':').collect();
-    assert!(split.len() == 2, "invalid asset \"{str}\"");
-    let code = split[0];
-    let issuer: xdr::AccountId = split[1].parse()?;
-    let re = regex::Regex::new("^[[:alnum:]]{1,12}$").expect("regex failed");
-    assert!(re.is_match(code), "invalid asset \"{str}\"");
-    let asset_code: xdr::AssetCode = code.parse()?;
-    Ok(match asset_code {
-        xdr::AssetCode::CreditAlphanum4(asset_code) => {
-            xdr::Asset::CreditAlphanum4(xdr::AlphaNum4 { asset_code, issuer })
-        }
-        xdr::AssetCode::CreditAlphanum12(asset_code) => {
-            xdr::Asset::CreditAlphanum12(xdr::AlphaNum12 { asset_code, issuer })
+        pub fn #export_name(env: loam_sdk::soroban_sdk::Env, #(#inputs),*) #output {
+            let _env = loam_sdk::soroban_sdk::EnvGuard::new(env);
+            #call
         }
-    })
-}
-
-// Generate the code to read the STELLAR_NETWORK environment variable
-// and call the generate_asset_id function
-pub fn parse_asset_literal(lit_str: &syn::LitStr, network: &str) -> TokenStream {
-    let asset_code = lit_str.value();
-    let asset_id = generate_asset_id(&asset_code, network).unwrap();
-    let asset_id = stellar_strkey::Contract(asset_id.0).to_string();
-    quote! {
-
-            loam_sdk::soroban_sdk::token::Client::new(
-                loam_sdk::soroban_sdk::env(),
-                &loam_sdk::soroban_sdk::Address::from_string(
-                        &loam_sdk::soroban_sdk::String::from_str(loam_sdk::soroban_sdk::env(), #asset_id,)
-                )
-            )
-
     }
 }
 
@@ -197,34 +249,3 @@ pub(crate) fn format_snippet(snippet: &str) -> String {
     println!("\n\n\n{buf}\n\n\n");
     buf
 }
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test_generate_asset_id() {
-        let asset_id = generate_asset_id("native", "local").unwrap();
-        assert_eq!(
-            asset_id.to_string(),
-            "CDMLFMKMMD7MWZP3FKUBZPVHTUEDLSX4BYGYKH4GCESXYHS3IHQ4EIG4"
-        );
-    }
-
-    #[test]
-    fn test_generate_asset_id_code() {
-        let asset_id = parse_asset_literal(
-            &syn::LitStr::new("native", proc_macro2::Span::call_site()),
-            "local",
-        );
-        equal_tokens(
-            &asset_id,
-            &quote! {
-                loam_sdk::soroban_sdk::token::Client::new(loam_sdk::soroban_sdk::env(),
-                    &loam_sdk::soroban_sdk::Address::from_string(
-                    &loam_sdk::soroban_sdk::String::from_str( loam_sdk::soroban_sdk::env(), "CDMLFMKMMD7MWZP3FKUBZPVHTUEDLSX4BYGYKH4GCESXYHS3IHQ4EIG4"))
-                )
-            },
-        );
-    }
-}
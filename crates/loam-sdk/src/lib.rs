@@ -4,6 +4,9 @@ pub use loam_sdk_macro::*;
 #[cfg(feature = "loam-soroban-sdk")]
 pub mod soroban_sdk;
 
+#[cfg(all(feature = "soroban-sdk-testutils", not(target_family = "wasm")))]
+pub mod testutils;
+
 #[cfg(feature = "loam-soroban-sdk")]
 #[macro_export]
 macro_rules! vec {
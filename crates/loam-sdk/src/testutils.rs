@@ -0,0 +1,93 @@
+//! Helpers for native tests of loam contracts, to use with the client `derive_contract`
+//! generates:
+//!
+//! ```ignore
+//! use loam_sdk::testutils;
+//!
+//! let env = testutils::env_with_mocked_auths();
+//! let [admin, user] = testutils::addresses(&env);
+//! let client = Contract::register_test(&env, admin.clone());
+//! client.transfer(&admin, &user, &10);
+//! testutils::advance_ledgers(&env, 100);
+//! ```
+use crate::soroban_sdk::{
+    testutils::{Address as _, Ledger as _, MockAuth, MockAuthInvoke},
+    Address, Env, EnvGuard, IntoVal, Val, Vec,
+};
+
+/// Average time between two ledgers of the network, by which [`advance_ledgers`] moves the
+/// ledger's timestamp for each ledger.
+pub const LEDGER_SECONDS: u64 = 5;
+
+/// A test environment in which every `require_auth` succeeds, as after `Env::mock_all_auths`.
+#[must_use]
+pub fn env_with_mocked_auths() -> Env {
+    let env = Env::default();
+    env.mock_all_auths();
+    env
+}
+
+/// Authorize only `address` calling `fn_name` of `contract` with `args`, for the next call to a
+/// contract. Other `require_auth` calls fail.
+pub fn mock_auth(
+    env: &Env,
+    address: &Address,
+    contract: &Address,
+    fn_name: &str,
+    args: impl IntoVal<Env, Vec<Val>>,
+) {
+    env.mock_auths(&[MockAuth {
+        address,
+        invoke: &MockAuthInvoke {
+            contract,
+            fn_name,
+            args: args.into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+}
+
+/// A new address, of a contract that does not exist.
+#[must_use]
+pub fn address(env: &Env) -> Address {
+    Address::generate(env)
+}
+
+/// `N` new addresses, e.g. `let [admin, user] = addresses(&env);`.
+#[must_use]
+pub fn addresses<const N: usize>(env: &Env) -> [Address; N] {
+    core::array::from_fn(|_| Address::generate(env))
+}
+
+/// Close `ledgers` ledgers, moving the ledger's timestamp [`LEDGER_SECONDS`] forward for each.
+/// Entries whose TTL runs out are archived.
+pub fn advance_ledgers(env: &Env, ledgers: u32) {
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += ledgers;
+        ledger.timestamp += u64::from(ledgers) * LEDGER_SECONDS;
+    });
+}
+
+/// Move the ledger's timestamp `seconds` forward, leaving its sequence number.
+pub fn advance_time(env: &Env, seconds: u64) {
+    env.ledger().with_mut(|ledger| ledger.timestamp += seconds);
+}
+
+/// Set the sequence number of the ledger.
+pub fn set_ledger_sequence(env: &Env, sequence: u32) {
+    env.ledger().set_sequence_number(sequence);
+}
+
+/// Set the timestamp of the ledger, in seconds since the Unix epoch.
+pub fn set_timestamp(env: &Env, timestamp: u64) {
+    env.ledger().set_timestamp(timestamp);
+}
+
+/// Run `f` as the contract `id`, with `env` current, so that it can use `env()` and the loam
+/// storage types, e.g. to check the contract's state. Writes are flushed when `f` returns.
+pub fn as_contract<R>(env: &Env, id: &Address, f: impl FnOnce() -> R) -> R {
+    env.as_contract(id, || {
+        let _guard = EnvGuard::new(env.clone());
+        f()
+    })
+}
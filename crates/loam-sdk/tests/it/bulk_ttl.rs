@@ -0,0 +1,111 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{
+        self, env, flush_cache,
+        testutils::storage::{Instance as _, Persistent as _, Temporary as _},
+        ExtendTtl, InstanceItem, PersistentDoubleMap, PersistentItem, PersistentIterableMap,
+        PersistentMap, TemporaryItem,
+    },
+};
+
+use crate::util::in_contract;
+
+const THRESHOLD: u32 = 5_000;
+const EXTEND_TO: u32 = 10_000;
+
+#[loamstorage]
+struct Archive {
+    owner: PersistentItem<u32>,
+    nonce: TemporaryItem<u32>,
+    unset: PersistentItem<u32>,
+    config: InstanceItem<u32>,
+    balances: PersistentMap<u32, u32>,
+    holders: PersistentIterableMap<u32, u32>,
+    allowances: PersistentDoubleMap<u32, u32, u32>,
+}
+
+use archive_keys__::ArchiveKey;
+
+fn persistent_ttl(key: &ArchiveKey) -> u32 {
+    flush_cache();
+    env().storage().persistent().get_ttl(key)
+}
+
+#[test]
+fn extend_ttl_all_reaches_items_and_instance() {
+    in_contract(|| {
+        let mut archive = Archive::default();
+        archive.owner.set(&1);
+        archive.nonce.set(&2);
+        archive.config.set(&3);
+        archive.balances.set(1, &1);
+
+        Archive::extend_ttl_all(THRESHOLD, EXTEND_TO);
+
+        assert_eq!(persistent_ttl(&ArchiveKey::ArchiveOwner), EXTEND_TO);
+        assert_eq!(
+            env()
+                .storage()
+                .temporary()
+                .get_ttl(&ArchiveKey::ArchiveNonce),
+            EXTEND_TO
+        );
+        assert_eq!(env().storage().instance().get_ttl(), EXTEND_TO);
+        // Unset items are skipped, and map entries are not reachable without their keys.
+        assert!(!archive.unset.has());
+        assert!(persistent_ttl(&ArchiveKey::ArchiveBalances(1)) < THRESHOLD);
+    });
+}
+
+#[test]
+fn extend_ttl_keys_touches_every_map_entry() {
+    in_contract(|| {
+        let mut archive = Archive::default();
+        for key in 1..=3 {
+            archive.balances.set(key, &key);
+        }
+        archive
+            .balances
+            .extend_ttl_keys([1, 2, 3, 4], THRESHOLD, EXTEND_TO);
+        for key in 1..=3 {
+            assert_eq!(persistent_ttl(&ArchiveKey::ArchiveBalances(key)), EXTEND_TO);
+        }
+        assert!(!archive.balances.has(4));
+    });
+}
+
+#[test]
+fn extend_ttl_keys_touches_every_index_entry() {
+    in_contract(|| {
+        let mut archive = Archive::default();
+        for key in 1..=3 {
+            archive.holders.set(key, &key);
+            archive.allowances.set(7, key, &key);
+        }
+        archive
+            .holders
+            .extend_ttl_keys([1, 2, 3, 4], THRESHOLD, EXTEND_TO);
+        archive
+            .allowances
+            .extend_ttl_keys([(7, 1), (7, 2), (7, 3), (8, 1)], THRESHOLD, EXTEND_TO);
+
+        assert_eq!(persistent_ttl(&ArchiveKey::ArchiveHoldersLen), EXTEND_TO);
+        assert_eq!(
+            persistent_ttl(&ArchiveKey::ArchiveAllowancesLen(7)),
+            EXTEND_TO
+        );
+        for (index, key) in (1..=3).enumerate() {
+            let index = u32::try_from(index).unwrap();
+            for key in [
+                ArchiveKey::ArchiveHolders(key),
+                ArchiveKey::ArchiveHoldersPos(key),
+                ArchiveKey::ArchiveHoldersIdx(index),
+                ArchiveKey::ArchiveAllowances(7, key),
+                ArchiveKey::ArchiveAllowancesPos(7, key),
+                ArchiveKey::ArchiveAllowancesIdx(7, index),
+            ] {
+                assert_eq!(persistent_ttl(&key), EXTEND_TO);
+            }
+        }
+    });
+}
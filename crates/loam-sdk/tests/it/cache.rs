@@ -0,0 +1,97 @@
+use loam_sdk::soroban_sdk::{self, env, flush_cache, store, symbol_short, Symbol};
+
+use crate::util::{contract, in_contract, run};
+
+fn ledger(key: u32) -> Option<u32> {
+    env().storage().persistent().get(&key)
+}
+
+#[test]
+fn writes_reach_the_ledger_when_flushed() {
+    in_contract(|| {
+        store::persistent().set(&1_u32, &10_u32);
+        assert_eq!(store::persistent().get(&1_u32), Some(10_u32));
+        assert_eq!(ledger(1), None);
+        flush_cache();
+        assert_eq!(ledger(1), Some(10));
+    });
+}
+
+#[test]
+fn guard_flushes_when_dropped() {
+    let (env, id) = contract();
+    run(&env, &id, || store::persistent().set(&1_u32, &10_u32));
+    run(&env, &id, || assert_eq!(ledger(1), Some(10)));
+}
+
+#[test]
+fn entries_set_back_are_not_written() {
+    in_contract(|| {
+        env().storage().persistent().set(&1_u32, &10_u32);
+        assert_eq!(store::persistent().get(&1_u32), Some(10_u32));
+        // A write the cache does not know about, which only a write back would undo.
+        env().storage().persistent().set(&1_u32, &20_u32);
+        store::persistent().set(&1_u32, &11_u32);
+        store::persistent().set(&1_u32, &10_u32);
+        flush_cache();
+        assert_eq!(ledger(1), Some(20));
+    });
+}
+
+#[test]
+fn removed_entries_read_as_missing() {
+    in_contract(|| {
+        env().storage().persistent().set(&1_u32, &10_u32);
+        store::persistent().remove(&1_u32);
+        assert!(!store::persistent().has(&1_u32));
+        assert_eq!(store::persistent().get::<_, u32>(&1_u32), None);
+        store::persistent().set(&2_u32, &20_u32);
+        store::persistent().remove(&2_u32);
+        flush_cache();
+        assert_eq!(ledger(1), None);
+        assert_eq!(ledger(2), None);
+    });
+}
+
+#[test]
+fn prefixes_and_tiers_are_kept_apart() {
+    in_contract(|| {
+        let key = symbol_short!("key");
+        store::persistent().set(&key, &1_u32);
+        store::temporary().set(&key, &2_u32);
+        store::with_prefix("lp", || store::persistent().set(&key, &3_u32));
+        assert_eq!(store::persistent().get(&key), Some(1_u32));
+        assert_eq!(store::temporary().get(&key), Some(2_u32));
+        assert_eq!(
+            store::with_prefix("lp", || store::persistent().get(&key)),
+            Some(3_u32)
+        );
+        flush_cache();
+        let prefixed = (Symbol::new(env(), "lp"), key);
+        assert_eq!(env().storage().persistent().get(&prefixed), Some(3_u32));
+    });
+}
+
+#[test]
+fn keys_past_the_slots_go_to_the_ledger() {
+    in_contract(|| {
+        for key in 0..40_u32 {
+            store::persistent().set(&key, &(key * 10));
+        }
+        for key in 0..40_u32 {
+            assert_eq!(store::persistent().get(&key), Some(key * 10));
+        }
+        assert_eq!(ledger(39), Some(390));
+        flush_cache();
+        assert_eq!(ledger(0), Some(0));
+    });
+}
+
+#[test]
+fn extending_a_cached_entry_writes_it_first() {
+    in_contract(|| {
+        store::persistent().set(&1_u32, &10_u32);
+        store::persistent().extend_ttl(&1_u32, 100, 1_000);
+        assert_eq!(ledger(1), Some(10));
+    });
+}
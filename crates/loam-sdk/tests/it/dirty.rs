@@ -0,0 +1,50 @@
+use loam_sdk::{
+    derive_contract,
+    soroban_sdk::{self, contracttype, Env, Lazy, Symbol},
+    subcontract,
+};
+
+#[contracttype]
+#[derive(Default, Lazy)]
+#[lazy(key = "COUNT")]
+pub struct Counter(i128);
+
+#[subcontract]
+pub trait IsCounting {
+    fn add(&mut self, amount: i128);
+    fn count(&self) -> i128;
+}
+
+impl IsCounting for Counter {
+    fn add(&mut self, amount: i128) {
+        self.0 += amount;
+    }
+
+    fn count(&self) -> i128 {
+        self.0
+    }
+}
+
+#[derive_contract(Counting(Counter))]
+pub struct Contract;
+
+#[test]
+fn unchanged_state_is_not_written() {
+    let env = Env::default();
+    let client = Contract::register_test(&env);
+    let stored = || {
+        env.as_contract(&client.address, || {
+            env.storage()
+                .persistent()
+                .get::<_, Counter>(&Symbol::new(&env, "COUNT"))
+                .map(|counter| counter.0)
+        })
+    };
+    client.add(&0);
+    assert_eq!(stored(), None);
+    client.add(&2);
+    assert_eq!(stored(), Some(2));
+    client.add(&0);
+    assert_eq!(stored(), Some(2));
+    assert_eq!(client.count(), 2);
+}
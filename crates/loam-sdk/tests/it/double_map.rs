@@ -0,0 +1,143 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{
+        self, env, store, vec, InstanceDoubleMap, PersistentDoubleMap, TemporaryDoubleMap, Vec,
+    },
+};
+
+use crate::util::in_contract;
+
+#[loamstorage]
+struct Grants {
+    persistent: PersistentDoubleMap<u32, u32, u64>,
+    instance: InstanceDoubleMap<u32, u32, u64>,
+    temporary: TemporaryDoubleMap<u32, u32, u64>,
+}
+
+use grants_keys__::GrantsKey;
+
+fn has(key: &GrantsKey) -> bool {
+    store::persistent().has(key)
+}
+
+/// Check the index under `first` against the positions, and return its keys in order.
+fn indexed(grants: &Grants, first: u32) -> Vec<u32> {
+    let keys = grants.persistent.keys(first, 0, u32::MAX);
+    for (index, second) in keys.iter().enumerate() {
+        let position: Option<u32> =
+            store::persistent().get(&GrantsKey::GrantsPersistentPos(first, second));
+        assert_eq!(position, Some(u32::try_from(index).unwrap()));
+        assert!(grants.persistent.has(first, second));
+    }
+    assert_eq!(keys.len(), grants.persistent.len(first));
+    keys
+}
+
+fn filled() -> Grants {
+    let mut grants = Grants::default();
+    for second in 1..=4 {
+        grants.persistent.set(1, second, &u64::from(second));
+        grants.instance.set(1, second, &u64::from(second));
+        grants.temporary.set(1, second, &u64::from(second));
+    }
+    grants.persistent.set(2, 1, &10);
+    grants
+}
+
+#[test]
+fn first_keys_are_kept_apart() {
+    in_contract(|| {
+        let grants = filled();
+        assert_eq!(indexed(&grants, 1), vec![env(), 1, 2, 3, 4]);
+        assert_eq!(indexed(&grants, 2), vec![env(), 1]);
+        assert_eq!(grants.persistent.get(2, 1), Some(10));
+        assert_eq!(grants.persistent.get(1, 1), Some(1));
+        assert_eq!(grants.persistent.get(3, 1), None);
+        assert!(grants.persistent.is_empty(3));
+        assert_eq!(grants.persistent.keys(1, 3, 5), vec![env(), 4]);
+        assert_eq!(grants.persistent.keys(1, 9, 5), vec![env()]);
+    });
+}
+
+#[test]
+fn remove_keeps_the_index_consistent() {
+    in_contract(|| {
+        let grants = filled();
+        grants.persistent.remove(1, 2);
+        assert_eq!(indexed(&grants, 1), vec![env(), 1, 4, 3]);
+        grants.persistent.remove(1, 3);
+        assert_eq!(indexed(&grants, 1), vec![env(), 1, 4]);
+        grants.persistent.remove(1, 3);
+        assert_eq!(indexed(&grants, 1), vec![env(), 1, 4]);
+        grants.persistent.remove(2, 1);
+        assert!(!has(&GrantsKey::GrantsPersistentLen(2)));
+        assert_eq!(indexed(&grants, 1), vec![env(), 1, 4]);
+    });
+}
+
+#[test]
+fn clear_is_bounded() {
+    in_contract(|| {
+        let grants = filled();
+        assert_eq!(grants.persistent.clear(1, 3), 1);
+        assert_eq!(indexed(&grants, 1), vec![env(), 1]);
+        for second in 2..=4 {
+            assert!(!has(&GrantsKey::GrantsPersistent(1, second)));
+            assert!(!has(&GrantsKey::GrantsPersistentPos(1, second)));
+        }
+        assert_eq!(grants.persistent.clear(1, 3), 0);
+        assert!(grants.persistent.is_empty(1));
+        assert!(!has(&GrantsKey::GrantsPersistentLen(1)));
+        assert!(!has(&GrantsKey::GrantsPersistentIdx(1, 0)));
+        assert_eq!(grants.persistent.clear(1, 3), 0);
+        assert_eq!(indexed(&grants, 2), vec![env(), 1]);
+
+        assert_eq!(grants.instance.clear(1, 2), 2);
+        assert_eq!(grants.instance.keys(1, 0, 10), vec![env(), 1, 2]);
+        assert_eq!(grants.temporary.clear(1, u32::MAX), 0);
+        assert_eq!(grants.temporary.get(1, 1), None);
+    });
+}
+
+#[test]
+fn clear_skips_missing_index_entries() {
+    in_contract(|| {
+        let grants = filled();
+        store::persistent().remove(&GrantsKey::GrantsPersistentIdx(1, 3));
+        assert_eq!(grants.persistent.clear(1, 10), 0);
+        assert!(grants.persistent.is_empty(1));
+        assert_eq!(grants.persistent.get(1, 1), None);
+    });
+}
+
+#[test]
+fn keys_and_remove_skip_missing_index_entries() {
+    in_contract(|| {
+        let grants = filled();
+        store::persistent().remove(&GrantsKey::GrantsPersistentIdx(1, 1));
+        assert_eq!(grants.persistent.keys(1, 0, 10), vec![env(), 1, 3, 4]);
+
+        // Without the last index entry, the removed key's place is left empty.
+        store::persistent().remove(&GrantsKey::GrantsPersistentIdx(1, 3));
+        grants.persistent.remove(1, 1);
+        assert_eq!(grants.persistent.len(1), 3);
+        assert_eq!(grants.persistent.get(1, 1), None);
+        assert_eq!(grants.persistent.keys(1, 0, 10), vec![env(), 3]);
+        assert_eq!(grants.persistent.clear(1, u32::MAX), 0);
+        assert!(grants.persistent.is_empty(1));
+    });
+}
+
+#[test]
+fn update_writes_through_the_index() {
+    in_contract(|| {
+        let mut grants = Grants::default();
+        assert_eq!(grants.persistent.update(5, 6, |v| v.unwrap_or(1) * 2), 2);
+        assert_eq!(grants.persistent.update(5, 6, |v| v.unwrap_or(1) * 2), 4);
+        assert_eq!(
+            grants.persistent.try_update(5, 7, |_| Err::<u64, _>("no")),
+            Err("no")
+        );
+        assert_eq!(indexed(&grants, 5), vec![env(), 6]);
+    });
+}
@@ -0,0 +1,80 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{self, PersistentDoubleMap, PersistentIterableMap, PersistentMap},
+};
+
+use crate::util::{contract, in_contract, run};
+
+#[loamstorage]
+struct Ledger {
+    balances: PersistentMap<u32, i128>,
+    holders: PersistentIterableMap<u32, i128>,
+    allowances: PersistentDoubleMap<u32, u32, i128>,
+}
+
+#[test]
+fn or_insert_persists_the_default() {
+    let (env, id) = contract();
+    run(&env, &id, || {
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.balances.entry(1).or_insert(10), 10);
+        assert_eq!(ledger.balances.entry(1).or_insert(20), 10);
+        assert_eq!(ledger.balances.entry(2).or_default(), 0);
+    });
+    run(&env, &id, || {
+        let ledger = Ledger::default();
+        assert_eq!(ledger.balances.get(1), Some(10));
+        assert!(ledger.balances.has(2));
+    });
+}
+
+#[test]
+fn and_modify_writes_back_existing_values() {
+    in_contract(|| {
+        let mut ledger = Ledger::default();
+        let balance = ledger
+            .balances
+            .entry(1)
+            .and_modify(|balance| *balance += 5)
+            .or_insert(10);
+        assert_eq!(balance, 10);
+        let balance = ledger
+            .balances
+            .entry(1)
+            .and_modify(|balance| *balance += 5)
+            .or_insert(10);
+        assert_eq!(balance, 15);
+        assert_eq!(ledger.balances.get(1), Some(15));
+    });
+}
+
+#[test]
+fn remove_entry_only_removes_stored_values() {
+    in_contract(|| {
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.balances.entry(1).remove_entry(), None);
+        ledger.balances.set(1, &7);
+        assert_eq!(ledger.balances.entry(1).remove_entry(), Some((1, 7)));
+        assert!(!ledger.balances.has(1));
+    });
+}
+
+#[test]
+fn entries_keep_indexes_up_to_date() {
+    in_contract(|| {
+        let mut ledger = Ledger::default();
+        ledger.holders.entry(1).or_insert(1);
+        ledger.holders.entry(2).or_insert(2);
+        assert_eq!(ledger.holders.len(), 2);
+        ledger.holders.entry(1).remove_entry();
+        assert_eq!(ledger.holders.keys(0, 10).len(), 1);
+        assert_eq!(ledger.holders.get(2), Some(2));
+
+        ledger.allowances.entry(1, 2).or_insert(3);
+        assert_eq!(ledger.allowances.get(1, 2), Some(3));
+        assert_eq!(ledger.allowances.len(1), 1);
+        assert_eq!(*ledger.allowances.entry(1, 2).key(), (1, 2));
+        ledger.allowances.entry(1, 2).remove_entry();
+        assert!(ledger.allowances.is_empty(1));
+    });
+}
@@ -0,0 +1,128 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{self, env, store, vec, PersistentIterableMap, Vec},
+};
+
+use crate::util::in_contract;
+
+#[loamstorage]
+struct Registry {
+    entries: PersistentIterableMap<u32, u64>,
+}
+
+use registry_keys__::RegistryKey;
+
+fn position(key: u32) -> Option<u32> {
+    store::persistent().get(&RegistryKey::RegistryEntriesPos(key))
+}
+
+/// Check the index against the positions, and return its keys in order.
+fn indexed(registry: &Registry) -> Vec<u32> {
+    let keys = registry.entries.keys(0, u32::MAX);
+    for (index, key) in keys.iter().enumerate() {
+        assert_eq!(position(key), Some(u32::try_from(index).unwrap()));
+        assert!(registry.entries.has(key));
+    }
+    assert_eq!(keys.len(), registry.entries.len());
+    keys
+}
+
+fn filled() -> Registry {
+    let mut registry = Registry::default();
+    for key in 1..=4 {
+        registry.entries.set(key, &u64::from(key * 10));
+    }
+    registry
+}
+
+#[test]
+fn remove_from_middle_moves_last_key() {
+    in_contract(|| {
+        let registry = filled();
+        registry.entries.remove(2);
+        assert_eq!(indexed(&registry), vec![env(), 1, 4, 3]);
+        assert_eq!(position(2), None);
+        assert_eq!(registry.entries.get(2), None);
+        assert_eq!(registry.entries.get(4), Some(40));
+    });
+}
+
+#[test]
+fn remove_from_end() {
+    in_contract(|| {
+        let registry = filled();
+        registry.entries.remove(4);
+        assert_eq!(indexed(&registry), vec![env(), 1, 2, 3]);
+        // Removing a missing key changes nothing.
+        registry.entries.remove(4);
+        assert_eq!(indexed(&registry), vec![env(), 1, 2, 3]);
+    });
+}
+
+#[test]
+fn removing_every_key_removes_the_len() {
+    in_contract(|| {
+        let registry = filled();
+        for key in [3, 1, 4, 2] {
+            registry.entries.remove(key);
+            indexed(&registry);
+        }
+        assert!(registry.entries.is_empty());
+        assert!(!store::persistent().has(&RegistryKey::RegistryEntriesLen));
+        assert!(!store::persistent().has(&RegistryKey::RegistryEntriesIdx(0)));
+    });
+}
+
+#[test]
+fn removed_key_is_reinserted_at_the_end() {
+    in_contract(|| {
+        let mut registry = filled();
+        registry.entries.remove(1);
+        registry.entries.set(1, &11);
+        assert_eq!(indexed(&registry), vec![env(), 4, 2, 3, 1]);
+        assert_eq!(registry.entries.get(1), Some(11));
+        // Setting an existing key keeps its place.
+        registry.entries.set(2, &22);
+        assert_eq!(indexed(&registry), vec![env(), 4, 2, 3, 1]);
+    });
+}
+
+#[test]
+fn pagination_stays_in_bounds() {
+    in_contract(|| {
+        let registry = filled();
+        assert_eq!(registry.entries.keys(1, 2), vec![env(), 2, 3]);
+        assert_eq!(registry.entries.keys(3, 10), vec![env(), 4]);
+        assert_eq!(registry.entries.keys(4, 1), vec![env()]);
+        assert_eq!(registry.entries.keys(10, 1), vec![env()]);
+        assert_eq!(registry.entries.keys(2, u32::MAX), vec![env(), 3, 4]);
+        assert_eq!(registry.entries.keys(0, 0), vec![env()]);
+        assert_eq!(registry.entries.values(1, 2), vec![env(), 20, 30]);
+        assert_eq!(registry.entries.values(5, 2), vec![env()]);
+        let page: std::vec::Vec<_> = registry.entries.iter().skip(3).collect();
+        assert_eq!(page, [(4, 40)]);
+        assert_eq!(registry.entries.iter().nth(9), None);
+        assert_eq!(registry.entries.iter().size_hint(), (0, Some(4)));
+    });
+}
+
+#[test]
+fn missing_index_entries_are_skipped() {
+    in_contract(|| {
+        let registry = filled();
+        store::persistent().remove(&RegistryKey::RegistryEntriesIdx(1));
+        assert_eq!(registry.entries.keys(0, 10), vec![env(), 1, 3, 4]);
+        assert_eq!(registry.entries.values(0, 10), vec![env(), 10, 30, 40]);
+        let entries: std::vec::Vec<_> = registry.entries.iter().collect();
+        assert_eq!(entries, [(1, 10), (3, 30), (4, 40)]);
+
+        // Without the last index entry, the removed key's place is left empty.
+        store::persistent().remove(&RegistryKey::RegistryEntriesIdx(3));
+        registry.entries.remove(1);
+        assert_eq!(registry.entries.len(), 3);
+        assert_eq!(registry.entries.get(1), None);
+        assert_eq!(registry.entries.keys(0, 10), vec![env(), 3]);
+        let entries: std::vec::Vec<_> = registry.entries.iter().collect();
+        assert_eq!(entries, [(3, 30)]);
+    });
+}
@@ -0,0 +1,12 @@
+mod bulk_ttl;
+#[cfg(feature = "cache")]
+mod cache;
+mod dirty;
+mod double_map;
+mod entry;
+mod iterable_map;
+mod migrate;
+mod pause;
+mod ttl;
+mod util;
+mod vec;
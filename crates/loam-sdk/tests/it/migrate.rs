@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+
+use loam_sdk::{
+    derive_contract, loamstorage,
+    soroban_sdk::{self, Env, Lazy, Migrate, MigrateAll, PersistentItem, Versioned},
+    subcontract,
+};
+
+use crate::util::in_contract;
+
+std::thread_local! {
+    /// The `from` version of each migration run on this thread.
+    static RAN: RefCell<std::vec::Vec<u32>> = const { RefCell::new(std::vec::Vec::new()) };
+}
+
+fn ran() -> std::vec::Vec<u32> {
+    RAN.with_borrow(Clone::clone)
+}
+
+mod v1 {
+    use loam_sdk::{
+        loamstorage,
+        soroban_sdk::{self, PersistentItem},
+    };
+
+    #[loamstorage]
+    pub struct Store {
+        pub count: PersistentItem<u32>,
+    }
+}
+
+mod v2 {
+    use loam_sdk::{
+        loamstorage,
+        soroban_sdk::{self, PersistentItem},
+    };
+
+    #[loamstorage(version = 2)]
+    pub struct Store {
+        pub count: PersistentItem<u64>,
+    }
+}
+
+/// Version 3 keeps the count under a new name.
+#[loamstorage(version = 3)]
+struct Store {
+    total: PersistentItem<u64>,
+}
+
+impl Migrate for Store {
+    fn migrate(from: u32) {
+        RAN.with_borrow_mut(|ran| ran.push(from));
+        match from {
+            1 => {
+                let old = v1::Store::default();
+                if let Some(count) = old.count.get() {
+                    v2::Store::default().count.set(&count.into());
+                }
+            }
+            2 => {
+                let old = v2::Store::default();
+                if let Some(count) = old.count.get() {
+                    Store::default().total.set(&count);
+                    old.count.remove();
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn unversioned_state_migrates_through_every_version() {
+    in_contract(|| {
+        v1::Store::default().count.set(&5);
+        assert_eq!(Store::stored_version(), None);
+
+        Store::migrate_all();
+        assert_eq!(ran(), [1, 2]);
+        assert_eq!(Store::stored_version(), Some(3));
+        assert_eq!(Store::default().total.get(), Some(5));
+        assert!(!v2::Store::default().count.has());
+
+        Store::run_migrations();
+        assert_eq!(ran(), [1, 2]);
+    });
+}
+
+#[test]
+fn version_two_state_runs_later_migrations_only() {
+    in_contract(|| {
+        v2::Store::default().count.set(&7);
+        v2::Store::record_version();
+        assert_eq!(Store::stored_version(), Some(2));
+
+        <(Store,)>::migrate_all();
+        assert_eq!(ran(), [2]);
+        assert_eq!(Store::default().total.get(), Some(7));
+    });
+}
+
+#[test]
+fn loading_does_not_migrate() {
+    in_contract(|| {
+        v1::Store::default().count.set(&5);
+        let store = Store::get_lazy().unwrap();
+        assert_eq!(store.total.get(), None);
+        assert_eq!(Store::default().total.get(), None);
+        assert!(ran().is_empty());
+        assert_eq!(Store::stored_version(), None);
+    });
+}
+
+#[test]
+#[should_panic(expected = "storage was written by a newer version of this contract")]
+fn newer_state_is_refused() {
+    in_contract(|| {
+        Store::set_stored_version(4);
+        Store::run_migrations();
+    });
+}
+
+#[subcontract]
+pub trait IsTotaling {
+    fn add_to_total(&mut self, amount: u64);
+}
+
+impl IsTotaling for Store {
+    fn add_to_total(&mut self, amount: u64) {
+        self.total
+            .update(|total| total.unwrap_or_default() + amount);
+    }
+}
+
+#[derive_contract(Totaling(Store))]
+pub struct Contract;
+
+#[test]
+fn writes_do_not_record_a_version() {
+    let env = Env::default();
+    let client = Contract::register_test(&env);
+    env.as_contract(&client.address, || {
+        let _guard = soroban_sdk::EnvGuard::new(env.clone());
+        v1::Store::default().count.set(&5);
+    });
+    client.add_to_total(&3);
+    env.as_contract(&client.address, || {
+        let _guard = soroban_sdk::EnvGuard::new(env.clone());
+        assert_eq!(Store::stored_version(), None);
+        Store::run_migrations();
+        assert_eq!(ran(), [1, 2]);
+        assert_eq!(Store::stored_version(), Some(3));
+    });
+}
+
+mod deployed {
+    use loam_sdk::{
+        derive_contract,
+        soroban_sdk::{Lazy, MigrateAll},
+        subcontract,
+    };
+
+    use super::{Store, Totaling};
+
+    #[subcontract]
+    pub trait IsVersioning {
+        #[constructor]
+        fn versioning_init(&mut self);
+    }
+
+    /// Records the versions on deploy, as the `Migrations` subcontract does.
+    #[derive(Default, Lazy)]
+    pub struct Versioner;
+
+    impl IsVersioning for Versioner {
+        fn versioning_init(&mut self) {
+            Store::record_versions();
+        }
+    }
+
+    #[derive_contract(Totaling(Store), Versioning(Versioner))]
+    pub struct Contract;
+}
+
+#[test]
+fn constructor_records_the_current_version() {
+    let env = Env::default();
+    let client = deployed::Contract::register_test(&env);
+    client.add_to_total(&3);
+    env.as_contract(&client.address, || {
+        let _guard = soroban_sdk::EnvGuard::new(env.clone());
+        assert_eq!(Store::stored_version(), Some(3));
+        Store::run_migrations();
+        assert!(ran().is_empty());
+        assert_eq!(Store::default().total.get(), Some(3));
+    });
+}
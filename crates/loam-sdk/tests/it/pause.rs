@@ -0,0 +1,82 @@
+use loam_sdk::{
+    derive_contract,
+    soroban_sdk::{self, contracttype, Env, Lazy},
+    subcontract,
+};
+
+#[contracttype]
+#[derive(Default, Lazy)]
+#[lazy(key = "COUNT")]
+pub struct Counter(u32);
+
+#[subcontract]
+pub trait IsBumping {
+    #[when_not_paused]
+    fn bump(&mut self);
+
+    fn count(&self) -> u32;
+}
+
+impl IsBumping for Counter {
+    fn bump(&mut self) {
+        self.0 += 1;
+    }
+
+    fn count(&self) -> u32 {
+        self.0
+    }
+}
+
+#[contracttype]
+#[derive(Default, Lazy)]
+#[lazy(instance, key = "HALTED")]
+pub struct Halt(bool);
+
+#[subcontract]
+pub trait IsHalting {
+    fn halt(&mut self);
+
+    #[pause_state]
+    fn halted(&self) -> bool;
+}
+
+impl IsHalting for Halt {
+    fn halt(&mut self) {
+        self.0 = true;
+    }
+
+    fn halted(&self) -> bool {
+        self.0
+    }
+}
+
+#[derive_contract(Bumping(Counter), Halting(Halt))]
+pub struct Contract;
+
+mod unpausable {
+    use loam_sdk::derive_contract;
+
+    use super::{Bumping, Counter};
+
+    #[derive_contract(Bumping(Counter))]
+    pub struct Contract;
+}
+
+#[test]
+fn guarded_methods_fail_while_paused() {
+    let env = Env::default();
+    let client = Contract::register_test(&env);
+    client.bump();
+    client.halt();
+    assert!(client.try_bump().is_err());
+    assert_eq!(client.count(), 1);
+}
+
+#[test]
+fn guarded_methods_run_without_pause_state() {
+    let env = Env::default();
+    let client = unpausable::Contract::register_test(&env);
+    client.bump();
+    client.bump();
+    assert_eq!(client.count(), 2);
+}
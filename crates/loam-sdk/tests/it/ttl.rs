@@ -0,0 +1,96 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{
+        self, env, flush_cache,
+        testutils::{storage::Persistent as _, Ledger as _},
+        IntoVal, PersistentItem, PersistentMap, Val,
+    },
+};
+
+use crate::util::in_contract;
+
+const THRESHOLD: u32 = 5_000;
+const EXTEND_TO: u32 = 10_000;
+
+#[loamstorage]
+struct Timed {
+    #[ttl(threshold = 5_000, extend_to = 10_000, read)]
+    on_read: PersistentMap<u32, u32>,
+    #[ttl(threshold = 5_000, extend_to = 10_000, write)]
+    on_write: PersistentMap<u32, u32>,
+    #[ttl(threshold = THRESHOLD, extend_to = EXTEND_TO, read)]
+    item: PersistentItem<u32>,
+}
+
+use timed_keys__::TimedKey;
+
+fn ttl(key: TimedKey) -> u32 {
+    let key: Val = key.into_val(env());
+    flush_cache();
+    env().storage().persistent().get_ttl(&key)
+}
+
+fn advance_ledgers(count: u32) {
+    env()
+        .ledger()
+        .with_mut(|ledger| ledger.sequence_number += count);
+}
+
+#[test]
+fn read_policy_extends_on_read_and_update() {
+    in_contract(|| {
+        let mut timed = Timed::default();
+        timed.on_read.set(1, &1);
+        let initial = ttl(TimedKey::TimedOnRead(1));
+        assert!(initial < THRESHOLD);
+
+        timed
+            .on_read
+            .update(1, |value| value.unwrap_or_default() + 1);
+        assert_eq!(ttl(TimedKey::TimedOnRead(1)), EXTEND_TO);
+
+        timed.on_read.set(2, &2);
+        assert_eq!(ttl(TimedKey::TimedOnRead(2)), initial);
+        assert_eq!(timed.on_read.get(2), Some(2));
+        assert_eq!(ttl(TimedKey::TimedOnRead(2)), EXTEND_TO);
+
+        timed.on_read.set(3, &3);
+        timed
+            .on_read
+            .try_update(3, |value| Ok::<_, ()>(value.unwrap_or_default() + 1))
+            .unwrap();
+        assert_eq!(ttl(TimedKey::TimedOnRead(3)), EXTEND_TO);
+    });
+}
+
+#[test]
+fn write_policy_extends_on_write_only() {
+    in_contract(|| {
+        let mut timed = Timed::default();
+        timed.on_write.set(1, &1);
+        assert_eq!(ttl(TimedKey::TimedOnWrite(1)), EXTEND_TO);
+
+        advance_ledgers(EXTEND_TO - THRESHOLD + 1);
+        assert_eq!(timed.on_write.get(1), Some(1));
+        assert_eq!(ttl(TimedKey::TimedOnWrite(1)), THRESHOLD - 1);
+
+        timed
+            .on_write
+            .update(1, |value| value.unwrap_or_default() + 1);
+        assert_eq!(ttl(TimedKey::TimedOnWrite(1)), EXTEND_TO);
+    });
+}
+
+#[test]
+fn reads_keep_an_entry_alive() {
+    in_contract(|| {
+        let mut timed = Timed::default();
+        timed.item.set(&7);
+        for _ in 0..3 {
+            assert_eq!(timed.item.get(), Some(7));
+            advance_ledgers(EXTEND_TO - 1);
+        }
+        timed.item.update(|value| value.unwrap_or_default() + 1);
+        assert_eq!(ttl(TimedKey::TimedItem), EXTEND_TO);
+    });
+}
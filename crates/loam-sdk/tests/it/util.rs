@@ -0,0 +1,29 @@
+use loam_sdk::soroban_sdk::{self, contract, contractimpl, Address, Env, EnvGuard};
+
+/// A contract to run storage code in, as storage can only be used from a contract.
+#[contract]
+pub struct TestContract;
+
+#[contractimpl]
+impl TestContract {}
+
+/// A fresh environment with [`TestContract`] registered in it.
+pub fn contract() -> (Env, Address) {
+    let env = Env::default();
+    let id = env.register(TestContract, ());
+    (env, id)
+}
+
+/// Run `f` as the contract `id`, with `env` current so the loam storage types can reach it.
+pub fn run<R>(env: &Env, id: &Address, f: impl FnOnce() -> R) -> R {
+    env.as_contract(id, || {
+        let _guard = EnvGuard::new(env.clone());
+        f()
+    })
+}
+
+/// Run `f` in a contract of its own.
+pub fn in_contract<R>(f: impl FnOnce() -> R) -> R {
+    let (env, id) = contract();
+    run(&env, &id, f)
+}
@@ -0,0 +1,120 @@
+use loam_sdk::{
+    loamstorage,
+    soroban_sdk::{self, store, InstanceVec, PersistentVec, TemporaryVec},
+};
+
+use crate::util::in_contract;
+use queues_keys__::QueuesKey;
+
+#[loamstorage]
+struct Queues {
+    persistent: PersistentVec<u32>,
+    instance: InstanceVec<u32>,
+    temporary: TemporaryVec<u32>,
+}
+
+/// Run the same checks against each kind of storage.
+macro_rules! for_each_vec {
+    ($queues:ident, |$vec:ident| $body:block) => {{
+        let $vec = &mut $queues.persistent;
+        $body
+        let $vec = &mut $queues.instance;
+        $body
+        let $vec = &mut $queues.temporary;
+        $body
+    }};
+}
+
+#[test]
+fn empty_vec_has_nothing_to_remove() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        for_each_vec!(queues, |vec| {
+            assert_eq!(vec.pop(), None);
+            assert_eq!(vec.swap_remove(0), None);
+            assert!(vec.is_empty());
+            assert_eq!(vec.get(0), None);
+            assert_eq!(vec.iter().count(), 0);
+        });
+    });
+}
+
+#[test]
+fn push_and_pop() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        for_each_vec!(queues, |vec| {
+            for value in 1..=3 {
+                vec.push(&value);
+            }
+            assert_eq!(vec.len(), 3);
+            assert_eq!(vec.pop(), Some(3));
+            assert_eq!(vec.pop(), Some(2));
+            assert_eq!(vec.pop(), Some(1));
+            assert_eq!(vec.pop(), None);
+            assert!(vec.is_empty());
+        });
+    });
+}
+
+#[test]
+fn emptying_removes_the_len() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        for_each_vec!(queues, |vec| {
+            vec.push(&1);
+            vec.push(&2);
+            vec.swap_remove(0);
+            vec.pop();
+            assert!(vec.is_empty());
+        });
+        assert!(!store::persistent().has(&QueuesKey::QueuesPersistentLen));
+        assert!(!store::instance().has(&QueuesKey::QueuesInstanceLen));
+        assert!(!store::temporary().has(&QueuesKey::QueuesTemporaryLen));
+    });
+}
+
+#[test]
+fn swap_remove_moves_the_last_element() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        for_each_vec!(queues, |vec| {
+            for value in 1..=4 {
+                vec.push(&value);
+            }
+            assert_eq!(vec.swap_remove(1), Some(2));
+            assert_eq!(vec.iter().collect::<Vec<_>>(), [1, 4, 3]);
+            assert_eq!(vec.swap_remove(2), Some(3));
+            assert_eq!(vec.iter().collect::<Vec<_>>(), [1, 4]);
+            assert_eq!(vec.swap_remove(2), None);
+            assert_eq!(vec.len(), 2);
+            assert_eq!(vec.get(2), None);
+        });
+    });
+}
+
+#[test]
+fn set_and_range() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        for_each_vec!(queues, |vec| {
+            for value in 1..=4 {
+                vec.push(&value);
+            }
+            vec.set(0, &10);
+            assert_eq!(vec.get(0), Some(10));
+            assert_eq!(vec.range(1..3).collect::<Vec<_>>(), [2, 3]);
+            assert_eq!(vec.range(2..10).collect::<Vec<_>>(), [3, 4]);
+            assert_eq!(vec.range(5..10).count(), 0);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds")]
+fn set_past_the_end_panics() {
+    in_contract(|| {
+        let mut queues = Queues::default();
+        queues.persistent.set(0, &1);
+    });
+}
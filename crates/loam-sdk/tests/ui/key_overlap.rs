@@ -0,0 +1,43 @@
+#![allow(unexpected_cfgs)]
+use loam_sdk::{
+    derive_contract,
+    soroban_sdk::{self, contracttype, Lazy},
+    subcontract,
+};
+
+#[contracttype]
+#[derive(Default, Lazy)]
+#[lazy(key = "COUNT")]
+pub struct Counter(i128);
+
+#[contracttype]
+#[derive(Default, Lazy)]
+#[lazy(key = "COUNT")]
+pub struct Total(i128);
+
+#[subcontract]
+pub trait IsCounting {
+    fn count(&self) -> i128;
+}
+
+#[subcontract]
+pub trait IsTotals {
+    fn total(&self) -> i128;
+}
+
+impl IsCounting for Counter {
+    fn count(&self) -> i128 {
+        self.0
+    }
+}
+
+impl IsTotals for Total {
+    fn total(&self) -> i128 {
+        self.0
+    }
+}
+
+#[derive_contract(Counting(Counter), Totals(Total))]
+pub struct Contract;
+
+fn main() {}
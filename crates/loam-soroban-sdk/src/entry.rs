@@ -0,0 +1,252 @@
+//! An entry API for the loam storage maps, in the spirit of `std`'s `HashMap::entry`.
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+
+use crate::loam_storage::{
+    InstanceDoubleMap, InstanceMap, LoamDoubleKey, LoamIterableKey, LoamKey, PersistentDoubleMap,
+    PersistentIterableMap, PersistentMap, TemporaryDoubleMap, TemporaryMap,
+};
+
+/// A storage map whose entries can be worked with through an [`Entry`].
+///
+/// Double maps are keyed by the pair of their keys.
+pub trait StorageMap {
+    type Key: Clone;
+    type Value;
+
+    fn get(&self, key: Self::Key) -> Option<Self::Value>;
+
+    fn set(&mut self, key: Self::Key, value: &Self::Value);
+
+    fn remove(&self, key: Self::Key);
+}
+
+/// A key of a storage map along with the value it had when the entry was made, returned by
+/// the maps' `entry` methods.
+///
+/// The value is read once, when the entry is made, and written back by the methods that change
+/// it.
+///
+/// ```ignore
+/// let total = self.balances.entry(owner).and_modify(|b| *b += amount).or_insert(amount);
+/// ```
+pub struct Entry<'a, M: StorageMap> {
+    map: &'a mut M,
+    key: M::Key,
+    value: Option<M::Value>,
+}
+
+impl<'a, M: StorageMap> Entry<'a, M> {
+    pub(crate) fn new(map: &'a mut M, key: M::Key) -> Self {
+        let value = map.get(key.clone());
+        Self { map, key, value }
+    }
+
+    pub fn key(&self) -> &M::Key {
+        &self.key
+    }
+
+    /// The value stored under the key, if any.
+    pub fn get(&self) -> Option<&M::Value> {
+        self.value.as_ref()
+    }
+
+    /// The stored value, or `default` after storing it.
+    pub fn or_insert(self, default: M::Value) -> M::Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// The stored value, or the result of `f` after storing it.
+    pub fn or_insert_with(self, f: impl FnOnce() -> M::Value) -> M::Value {
+        if let Some(value) = self.value {
+            return value;
+        }
+        let value = f();
+        self.map.set(self.key, &value);
+        value
+    }
+
+    /// The stored value, or `M::Value::default()` after storing it.
+    pub fn or_default(self) -> M::Value
+    where
+        M::Value: Default,
+    {
+        self.or_insert_with(Default::default)
+    }
+
+    /// Change the stored value with `f` and write it back. Does nothing if there is no value.
+    #[must_use]
+    pub fn and_modify(mut self, f: impl FnOnce(&mut M::Value)) -> Self {
+        if let Some(value) = &mut self.value {
+            f(value);
+            self.map.set(self.key.clone(), value);
+        }
+        self
+    }
+
+    /// Remove the entry, returning its key and value if it had one.
+    pub fn remove_entry(self) -> Option<(M::Key, M::Value)> {
+        let value = self.value?;
+        self.map.remove(self.key.clone());
+        Some((self.key, value))
+    }
+}
+
+impl<K, V, W> StorageMap for PersistentMap<K, V, W>
+where
+    K: Into<W> + Clone,
+    W: LoamKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: K) -> Option<V> {
+        Self::get(self, key)
+    }
+
+    fn set(&mut self, key: K, value: &V) {
+        Self::set(self, key, value);
+    }
+
+    fn remove(&self, key: K) {
+        Self::remove(self, key);
+    }
+}
+
+impl<K, V, W> StorageMap for InstanceMap<K, V, W>
+where
+    K: Into<W> + Clone,
+    W: LoamKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: K) -> Option<V> {
+        Self::get(self, key)
+    }
+
+    fn set(&mut self, key: K, value: &V) {
+        Self::set(self, key, value);
+    }
+
+    fn remove(&self, key: K) {
+        Self::remove(self, key);
+    }
+}
+
+impl<K, V, W> StorageMap for TemporaryMap<K, V, W>
+where
+    K: Into<W> + Clone,
+    W: LoamKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: K) -> Option<V> {
+        Self::get(self, key)
+    }
+
+    fn set(&mut self, key: K, value: &V) {
+        Self::set(self, key, value);
+    }
+
+    fn remove(&self, key: K) {
+        Self::remove(self, key);
+    }
+}
+
+impl<K, V, W> StorageMap for PersistentIterableMap<K, V, W>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: K) -> Option<V> {
+        Self::get(self, key)
+    }
+
+    fn set(&mut self, key: K, value: &V) {
+        Self::set(self, key, value);
+    }
+
+    fn remove(&self, key: K) {
+        Self::remove(self, key);
+    }
+}
+
+impl<K1, K2, V, W> StorageMap for PersistentDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = (K1, K2);
+    type Value = V;
+
+    fn get(&self, (first, second): (K1, K2)) -> Option<V> {
+        Self::get(self, first, second)
+    }
+
+    fn set(&mut self, (first, second): (K1, K2), value: &V) {
+        Self::set(self, first, second, value);
+    }
+
+    fn remove(&self, (first, second): (K1, K2)) {
+        Self::remove(self, first, second);
+    }
+}
+
+impl<K1, K2, V, W> StorageMap for InstanceDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = (K1, K2);
+    type Value = V;
+
+    fn get(&self, (first, second): (K1, K2)) -> Option<V> {
+        Self::get(self, first, second)
+    }
+
+    fn set(&mut self, (first, second): (K1, K2), value: &V) {
+        Self::set(self, first, second, value);
+    }
+
+    fn remove(&self, (first, second): (K1, K2)) {
+        Self::remove(self, first, second);
+    }
+}
+
+impl<K1, K2, V, W> StorageMap for TemporaryDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Key = (K1, K2);
+    type Value = V;
+
+    fn get(&self, (first, second): (K1, K2)) -> Option<V> {
+        Self::get(self, first, second)
+    }
+
+    fn set(&mut self, (first, second): (K1, K2), value: &V) {
+        Self::set(self, first, second, value);
+    }
+
+    fn remove(&self, (first, second): (K1, K2)) {
+        Self::remove(self, first, second);
+    }
+}
@@ -0,0 +1,104 @@
+//! State shared by the whole contract: the current [`Env`](crate::Env) and the bookkeeping of
+//! [`store`](crate::store).
+//!
+//! A contract runs on a single thread, so on wasm these are plain statics. On other targets they
+//! are thread locals, so tests running in parallel each see only their own environment.
+
+/// A value that starts out unset, declared with [`global!`].
+pub(crate) struct Global<T>(Cell<T>);
+
+#[cfg(target_family = "wasm")]
+type Cell<T> = core::cell::UnsafeCell<Option<T>>;
+
+#[cfg(not(target_family = "wasm"))]
+type Cell<T> = core::cell::RefCell<Option<T>>;
+
+impl<T> Global<T> {
+    pub const fn new() -> Self {
+        Self(Cell::new(None))
+    }
+}
+
+// SAFETY: wasm contracts are single threaded.
+#[cfg(target_family = "wasm")]
+unsafe impl<T> Sync for Global<T> {}
+
+/// Access to a [`Global`], whether it is a static or a thread local.
+pub(crate) trait Access<T> {
+    /// A copy of the current value.
+    fn get(&'static self) -> Option<T>;
+
+    /// The current value, borrowed for as long as the contract or thread runs. Replacing the value
+    /// leaves such a borrow looking at the new one, so only use this for values that are replaced
+    /// by another of their kind and never cleared.
+    fn get_ref(&'static self) -> Option<&'static T>;
+
+    /// Set the value, returning the previous one.
+    fn replace(&'static self, value: Option<T>) -> Option<T>;
+
+    /// Run `f` on the value in place, without copying it.
+    #[cfg(feature = "cache")]
+    fn with_mut<R>(&'static self, f: impl FnOnce(&mut Option<T>) -> R) -> R;
+}
+
+#[cfg(target_family = "wasm")]
+impl<T: Clone> Access<T> for Global<T> {
+    fn get(&'static self) -> Option<T> {
+        self.get_ref().cloned()
+    }
+
+    fn get_ref(&'static self) -> Option<&'static T> {
+        unsafe { (*self.0.get()).as_ref() }
+    }
+
+    fn replace(&'static self, value: Option<T>) -> Option<T> {
+        unsafe { core::mem::replace(&mut *self.0.get(), value) }
+    }
+
+    #[cfg(feature = "cache")]
+    fn with_mut<R>(&'static self, f: impl FnOnce(&mut Option<T>) -> R) -> R {
+        f(unsafe { &mut *self.0.get() })
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<T: Clone> Access<T> for std::thread::LocalKey<Global<T>> {
+    fn get(&'static self) -> Option<T> {
+        self.with(|global| global.0.borrow().clone())
+    }
+
+    fn get_ref(&'static self) -> Option<&'static T> {
+        // SAFETY: a thread local lives until its thread exits, and the value is only borrowed on
+        // this thread: the types kept here are not `Sync`.
+        self.with(|global| unsafe {
+            let value = global.0.try_borrow_unguarded().ok()?.as_ref()?;
+            Some(&*core::ptr::from_ref(value))
+        })
+    }
+
+    fn replace(&'static self, value: Option<T>) -> Option<T> {
+        self.with(|global| global.0.replace(value))
+    }
+
+    #[cfg(feature = "cache")]
+    fn with_mut<R>(&'static self, f: impl FnOnce(&mut Option<T>) -> R) -> R {
+        self.with(|global| f(&mut global.0.borrow_mut()))
+    }
+}
+
+/// Declare a [`Global`]: a static on wasm and a thread local elsewhere.
+macro_rules! global {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty;) => {
+        $(#[$attr])*
+        #[cfg(target_family = "wasm")]
+        $vis static $name: $crate::global::Global<$ty> = $crate::global::Global::new();
+
+        #[cfg(not(target_family = "wasm"))]
+        ::std::thread_local! {
+            $(#[$attr])*
+            $vis static $name: $crate::global::Global<$ty> = const { $crate::global::Global::new() };
+        }
+    };
+}
+
+pub(crate) use global;
@@ -2,5 +2,7 @@ use soroban_sdk::{Env, IntoVal, Val};
 
 pub trait IntoKey {
     type Key: IntoVal<Env, Val>;
+    /// Storage entries behind the key, see [`crate::Lazy::STORAGE_KEYS`].
+    const STORAGE_KEYS: &'static [crate::StorageKey];
     fn into_key() -> <Self as IntoKey>::Key;
 }
@@ -1,25 +1,157 @@
 #![no_std]
 
-use soroban_sdk::unwrap::UnwrapOptimized;
+#[cfg(not(target_family = "wasm"))]
+extern crate std;
+
 pub use soroban_sdk::*;
 
+use global::{global, Access};
+
+pub mod entry;
+mod global;
 pub mod into_key;
 pub mod loam_storage;
+pub mod migrate;
+pub mod storage_key;
+pub mod store;
 
+pub use entry::{Entry, StorageMap};
 pub use into_key::IntoKey;
 pub use loam_storage::*;
+pub use migrate::{Migrate, MigrateAll, Versioned};
+pub use storage_key::{keys_overlap, KeyName, StorageKey, StorageTier};
+pub use store::flush_cache;
 
 /// Trait for loading and setting a singleton type
 pub trait Lazy: Sized {
+    /// Storage entries the type reads and writes. `#[derive_contract]` refuses to combine
+    /// subcontracts mounted under the same prefix whose entries overlap.
+    ///
+    /// The derives and `#[loamstorage]` fill this in. Hand-written implementations must list every
+    /// entry they use, or leave it empty if they store nothing, for the check to see them.
+    const STORAGE_KEYS: &'static [StorageKey];
+
     fn get_lazy() -> Option<Self>;
 
     fn set_lazy(self);
+
+    /// The value `set_lazy` would write, taken before a `&mut self` subcontract method runs so
+    /// that [`Lazy::set_lazy_if_changed`] can tell whether the method changed it. Types that can't
+    /// tell, or write nothing, return `None`.
+    fn snapshot(&self) -> Option<Val> {
+        None
+    }
+
+    /// Write back `self` after a `&mut self` subcontract method, unless it still holds the value
+    /// of the `snapshot` taken before the method ran.
+    fn set_lazy_if_changed(self, snapshot: Option<Val>) {
+        if let (Some(before), Some(after)) = (snapshot, self.snapshot()) {
+            if store::same(before, after) {
+                return;
+            }
+        }
+        self.set_lazy();
+    }
+}
+
+/// A typed contract event, implemented with `#[loam_event]`.
+pub trait Event {
+    /// Name of the event, its first topic.
+    const NAME: &'static str;
+
+    /// Contract spec entry describing the event type.
+    #[doc(hidden)]
+    fn spec_xdr() -> &'static [u8];
+
+    /// Publish the event from the current contract.
+    fn publish(self);
 }
 
-static mut ENV: Option<Env> = None;
+/// The admin of a contract, whose authorization `#[only_admin]` subcontract methods require.
+/// `derive_contract` implements it for the contract, and for each prefix a `Core` subcontract is
+/// mounted under, from that subcontract's `admin_get`.
+#[diagnostic::on_unimplemented(
+    message = "`#[only_admin]` methods need a `Core` subcontract mounted under the same prefix"
+)]
+pub trait HasAdmin {
+    fn admin() -> Option<Address>;
+
+    /// Require the authorization of the admin.
+    ///
+    /// # Panics
+    ///
+    /// If the contract has no admin.
+    fn require_admin() {
+        Self::admin().expect("admin is not set").require_auth();
+    }
+}
+
+/// Whether a contract is paused, which `#[when_not_paused]` subcontract methods check.
+/// `derive_contract` implements it for the contract, and for each prefix such a method is mounted
+/// under, from the `#[pause_state]` method of the subcontract, such as `Pausable`, mounted under the
+/// same prefix or else at the root. A contract without one is never paused.
+pub trait HasPause {
+    fn paused() -> bool;
+
+    /// Reject the call while the contract is paused.
+    ///
+    /// # Panics
+    ///
+    /// If the contract is paused.
+    fn require_not_paused() {
+        assert!(!Self::paused(), "contract is paused");
+    }
+}
 
+global! {
+    /// The last environment made current, and whether it still is. Once set it is only replaced
+    /// by another one, never cleared, so the references [`env`] hands out always point to a live
+    /// `Env`.
+    static ENV: (Env, bool);
+}
+
+/// Make `env` the current environment, or stop the current one being current if it is `None`.
+/// Returns the environment that was current.
+fn replace_env(env: Option<Env>) -> Option<Env> {
+    let kept = match env {
+        Some(env) => (env, true),
+        None => (parked()?, false),
+    };
+    let (previous, current) = ENV.replace(Some(kept))?;
+    current.then_some(previous)
+}
+
+/// What [`ENV`] holds once no environment is current, if one ever was: the last one on wasm.
+#[cfg(target_family = "wasm")]
+fn parked() -> Option<Env> {
+    ENV.get().map(|(env, _)| env)
+}
+
+/// What [`ENV`] holds once no environment is current, if one ever was. Elsewhere than on wasm it
+/// is a placeholder, so that the last environment is dropped, writing its test snapshot, when the
+/// test is done with it rather than when the thread exits.
+#[cfg(not(target_family = "wasm"))]
+fn parked() -> Option<Env> {
+    std::thread_local! {
+        static PARKED: Env = Env::new_with_config(testutils::EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+    }
+    ENV.get_ref()?;
+    Some(PARKED.with(Env::clone))
+}
+
+/// Make `env` the current environment, until another one is set or the [`EnvGuard`] this is
+/// called under is dropped.
+///
+/// On wasm the environment is a plain static. On other targets it is kept per thread, so tests
+/// running in parallel do not see each other's environment. Contract methods generated by
+/// `derive_contract` hold an [`EnvGuard`] instead, so the caller's environment is back when they
+/// return. With the `cache` feature, writes cached for the previous environment are flushed first.
 pub fn set_env(env: Env) {
-    unsafe { ENV = Some(env) };
+    flush_cache();
+    let _previous = store::take_state();
+    replace_env(Some(env));
 }
 
 /// Utility to cast a `&str` to a `String`.
@@ -30,29 +162,189 @@ pub fn to_string(s: &str) -> String {
 
 /// Returns a reference to the current environment.
 ///
+/// On targets other than wasm the reference points into this thread's state, so a reference kept
+/// while another environment is made current sees the new one.
+///
 /// # Panics
 ///
-/// This function will panic if the environment has not been initialized.
-/// It is expected that the environment is always initialized before this
-/// function is called in normal operation.
+/// On targets other than wasm, if no environment has been set on this thread with [`set_env`]
+/// or an [`EnvGuard`]. On wasm the environment is always set before contract code runs.
 #[must_use]
-#[allow(static_mut_refs)]
 #[inline]
 pub fn env() -> &'static Env {
-    unsafe { ENV.as_ref().unwrap_optimized() }
+    #[cfg(target_family = "wasm")]
+    let env = {
+        use soroban_sdk::unwrap::UnwrapOptimized;
+        &ENV.get_ref().unwrap_optimized().0
+    };
+    #[cfg(not(target_family = "wasm"))]
+    let Some((env, true)) = ENV.get_ref() else {
+        panic!(
+            "no `Env` is set on this thread; call `set_env(env)` or hold an `EnvGuard` before \
+             using loam storage or `env()` outside a contract method"
+        );
+    };
+    env
+}
+
+/// Makes an environment current until the guard is dropped, then restores the previous one.
+///
+/// Contract methods generated by `derive_contract` hold one while they run. Tests can use one to
+/// call `env()` or the loam storage types outside a contract method:
+///
+/// ```ignore
+/// let env = Env::default();
+/// let _guard = EnvGuard::new(env.clone());
+/// Counter::default().count.set(&1);
+/// ```
+///
+/// Guards can be nested, for example to work with two environments in one test, and must be
+/// dropped in the reverse order. With the `cache` feature, cached writes are flushed when the
+/// guard is dropped, unless it is dropped by a panic, which fails the contract call anyway.
+#[must_use = "the environment is only current while the guard is held"]
+pub struct EnvGuard {
+    previous: Option<Env>,
+    store: store::State,
+}
+
+impl EnvGuard {
+    pub fn new(env: Env) -> Self {
+        let previous = replace_env(Some(env));
+        let store = store::take_state();
+        Self { previous, store }
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        #[cfg(not(target_family = "wasm"))]
+        let failed = std::thread::panicking();
+        #[cfg(target_family = "wasm")]
+        let failed = false;
+        if !failed {
+            flush_cache();
+        }
+        store::restore_state(core::mem::take(&mut self.store));
+        replace_env(self.previous.take());
+    }
 }
 
 impl<T> Lazy for T
 where
     T: IntoKey + TryFromVal<Env, Val> + IntoVal<Env, Val>,
 {
+    const STORAGE_KEYS: &'static [StorageKey] = T::STORAGE_KEYS;
+
     fn get_lazy() -> Option<Self> {
-        env().storage().persistent().get(&Self::into_key())
+        store::persistent().get(&Self::into_key())
     }
 
     fn set_lazy(self) {
-        env().storage().persistent().set(&Self::into_key(), &self);
+        store::persistent().set(&Self::into_key(), &self);
+    }
+
+    fn snapshot(&self) -> Option<Val> {
+        Some(self.into_val(env()))
     }
 }
 
 pub use loam_sdk_macro::{IntoKey, Lazy};
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use soroban_sdk::testutils::Ledger as _;
+
+    use super::*;
+
+    /// An environment told apart from others by its ledger sequence number.
+    fn env_at(sequence: u32) -> Env {
+        let env = Env::default();
+        env.ledger().set_sequence_number(sequence);
+        env
+    }
+
+    fn current_sequence() -> u32 {
+        env().ledger().sequence()
+    }
+
+    fn env_is_set() -> bool {
+        matches!(ENV.get_ref(), Some((_, true)))
+    }
+
+    #[test]
+    fn guards_nest_and_restore() {
+        let outer = EnvGuard::new(env_at(1));
+        assert_eq!(current_sequence(), 1);
+        {
+            let _inner = EnvGuard::new(env_at(2));
+            assert_eq!(current_sequence(), 2);
+        }
+        assert_eq!(current_sequence(), 1);
+        drop(outer);
+        assert!(!env_is_set());
+    }
+
+    #[test]
+    fn guard_restores_env_set_under_it() {
+        set_env(env_at(1));
+        {
+            let _guard = EnvGuard::new(env_at(2));
+            set_env(env_at(3));
+            assert_eq!(current_sequence(), 3);
+        }
+        assert_eq!(current_sequence(), 1);
+        replace_env(None);
+    }
+
+    /// Counts the writes of a value whose snapshot is a host object.
+    struct Tracked(Vec<u32>);
+
+    std::thread_local! {
+        static WRITES: core::cell::Cell<u32> = const { core::cell::Cell::new(0) };
+    }
+
+    impl Lazy for Tracked {
+        const STORAGE_KEYS: &'static [StorageKey] = &[];
+
+        fn get_lazy() -> Option<Self> {
+            None
+        }
+
+        fn set_lazy(self) {
+            WRITES.set(WRITES.get() + 1);
+        }
+
+        fn snapshot(&self) -> Option<Val> {
+            Some(self.0.to_val())
+        }
+    }
+
+    #[test]
+    fn unchanged_values_are_not_written_back() {
+        let _guard = EnvGuard::new(Env::default());
+        let env = env();
+        let tracked = Tracked(vec![env, 1, 2]);
+        let snapshot = tracked.snapshot();
+        // An equal value behind another object handle counts as unchanged.
+        Tracked(vec![env, 1, 2]).set_lazy_if_changed(snapshot);
+        assert_eq!(WRITES.get(), 0);
+        Tracked(vec![env, 1, 3]).set_lazy_if_changed(snapshot);
+        assert_eq!(WRITES.get(), 1);
+        Tracked(vec![env, 1, 2]).set_lazy_if_changed(None);
+        assert_eq!(WRITES.get(), 2);
+    }
+
+    #[test]
+    fn threads_see_their_own_env() {
+        let _guard = EnvGuard::new(env_at(1));
+        let other = std::thread::spawn(|| {
+            assert!(!env_is_set());
+            let _guard = EnvGuard::new(env_at(2));
+            current_sequence()
+        });
+        assert_eq!(other.join().unwrap(), 2);
+        assert_eq!(current_sequence(), 1);
+    }
+}
@@ -1,14 +1,128 @@
 #![allow(clippy::must_use_candidate, clippy::missing_errors_doc)]
-use core::marker::PhantomData;
+use core::{marker::PhantomData, ops::Range};
 
-use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val, Vec};
 
-use crate::env;
+use crate::{entry::Entry, env, store};
 
 pub trait LoamKey {
+    /// Lifetime policy the storage wrappers apply to this key's entry, if any.
+    const TTL: Option<TtlPolicy> = None;
+
     fn to_key(&self) -> Val;
 }
 
+/// Extends the TTL of an entry whenever it is read and/or written, so it does not have to be
+/// bumped by hand.
+///
+/// Set on a `#[loamstorage]` field with `#[ttl(threshold = .., extend_to = ..)]`. For instance
+/// storage the whole contract instance is extended, as with `extend_ttl`. `update` and
+/// `try_update` both read and write, so they extend under either policy.
+#[derive(Clone, Copy, Debug)]
+pub struct TtlPolicy {
+    pub threshold: u32,
+    pub extend_to: u32,
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+fn extend_on_read<W: LoamKey>(extend: impl FnOnce(u32, u32)) {
+    if let Some(TtlPolicy {
+        threshold,
+        extend_to,
+        on_read: true,
+        ..
+    }) = W::TTL
+    {
+        extend(threshold, extend_to);
+    }
+}
+
+fn extend_on_write<W: LoamKey>(extend: impl FnOnce(u32, u32)) {
+    if let Some(TtlPolicy {
+        threshold,
+        extend_to,
+        on_write: true,
+        ..
+    }) = W::TTL
+    {
+        extend(threshold, extend_to);
+    }
+}
+
+/// For paths that both read and write the entry, like `update`: extends under either policy.
+fn extend_on_update<W: LoamKey>(extend: impl FnOnce(u32, u32)) {
+    if let Some(TtlPolicy {
+        threshold,
+        extend_to,
+        on_read,
+        on_write,
+    }) = W::TTL
+    {
+        if on_read || on_write {
+            extend(threshold, extend_to);
+        }
+    }
+}
+
+/// Extends the TTL of everything a `#[loamstorage]` struct can reach without being given keys:
+/// the contract instance, if the struct keeps anything there, and its persistent and temporary
+/// items.
+///
+/// Implemented by `#[loamstorage]`, and for tuples of such structs. Entries of maps and vectors
+/// are left alone, since they cannot be listed; extend those with `extend_ttl_keys` or
+/// `extend_ttl`.
+pub trait ExtendTtl {
+    fn extend_ttl_all(threshold: u32, extend_to: u32);
+}
+
+impl ExtendTtl for () {
+    fn extend_ttl_all(_: u32, _: u32) {}
+}
+
+macro_rules! impl_extend_ttl_for_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: ExtendTtl),+> ExtendTtl for ($($t,)+) {
+            fn extend_ttl_all(threshold: u32, extend_to: u32) {
+                $($t::extend_ttl_all(threshold, extend_to);)+
+            }
+        }
+    };
+}
+
+impl_extend_ttl_for_tuple!(A);
+impl_extend_ttl_for_tuple!(A, B);
+impl_extend_ttl_for_tuple!(A, B, C);
+impl_extend_ttl_for_tuple!(A, B, C, D);
+impl_extend_ttl_for_tuple!(A, B, C, D, E);
+impl_extend_ttl_for_tuple!(A, B, C, D, E, F);
+
+/// Keys for collections that keep a positional index of their entries in storage.
+pub trait LoamIndexKey {
+    /// Key of the entry holding the number of indexed entries.
+    fn len_key() -> Val;
+
+    /// Key of the entry holding the element at `index`.
+    fn index_key(index: u32) -> Val;
+}
+
+/// Key wrapper of an iterable map, which also records where each key sits in the index.
+pub trait LoamIterableKey: LoamKey + LoamIndexKey {
+    fn position_key(&self) -> Val;
+}
+
+/// Key wrapper of a double map, which keeps an index of the second-level keys stored under each
+/// first-level key.
+pub trait LoamDoubleKey<K1>: LoamKey {
+    /// Key of the entry holding the number of entries under `first`.
+    fn len_key(first: &K1) -> Val;
+
+    /// Key of the entry holding the second-level key at `index` under `first`.
+    fn index_key(first: &K1, index: u32) -> Val;
+
+    fn position_key(&self) -> Val;
+}
+
 #[derive(Clone)]
 pub struct PersistentMap<K, V, W = K>
 where
@@ -30,46 +144,96 @@ where
     pub fn get(&self, key: K) -> Option<V> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().get(&k)
+        let storage = store::persistent();
+        let value = storage.get(&k);
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| {
+                storage.extend_ttl(&k, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The entry for `key`, to insert, modify or remove its value based on what is stored.
+    pub fn entry(&mut self, key: K) -> Entry<'_, Self>
+    where
+        K: Clone,
+    {
+        Entry::new(self, key)
+    }
+
+    /// The value for `key`, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, key: K, err: E) -> Result<V, E> {
+        self.get(key).ok_or(err)
+    }
+
+    /// The value for `key`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, key: K) -> V
+    where
+        V: Default,
+    {
+        self.get(key).unwrap_or_default()
     }
 
     pub fn set(&mut self, key: K, value: &V) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().set(&k, value);
+        let storage = store::persistent();
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
     }
 
     pub fn has(&self, key: K) -> bool {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().has(&k)
+        store::persistent().has(&k)
     }
 
     pub fn update(&self, key: K, f: impl FnOnce(Option<V>) -> V) -> V {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().update(&k, f)
+        let storage = store::persistent();
+        let value = storage.update(&k, f);
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
+        value
     }
 
     pub fn try_update<E>(&self, key: K, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().try_update(&k, f)
+        let storage = store::persistent();
+        let value = storage.try_update(&k, f)?;
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
+        Ok(value)
     }
 
     pub fn extend_ttl(&self, key: K, threshold: u32, extend_to: u32) {
         let w: W = key.into();
         let k = w.to_key();
-        env()
-            .storage()
-            .persistent()
-            .extend_ttl(&k, threshold, extend_to);
+        store::persistent().extend_ttl(&k, threshold, extend_to);
+    }
+
+    /// Extend the TTL of the entries for `keys`, skipping keys that have no entry.
+    pub fn extend_ttl_keys(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        threshold: u32,
+        extend_to: u32,
+    ) {
+        let storage = store::persistent();
+        for key in keys {
+            let w: W = key.into();
+            let k = w.to_key();
+            if storage.has(&k) {
+                storage.extend_ttl(&k, threshold, extend_to);
+            }
+        }
     }
 
     pub fn remove(&self, key: K) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().persistent().remove(&k);
+        store::persistent().remove(&k);
     }
 }
 
@@ -88,6 +252,264 @@ where
     }
 }
 
+/// A [`PersistentMap`] that also maintains an index of its keys in storage, so entries can be
+/// counted, listed and paginated.
+///
+/// Each key costs two extra ledger entries (its slot in the index and its position), and the
+/// index is kept dense: removing a key moves the last key into its slot. Iteration follows
+/// insertion order until the first removal.
+#[derive(Clone)]
+pub struct PersistentIterableMap<K, V, W = K>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    k: PhantomData<K>,
+    v: PhantomData<V>,
+    w: PhantomData<W>,
+}
+
+impl<K, V, W> PersistentIterableMap<K, V, W>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    pub fn get(&self, key: K) -> Option<V> {
+        let w: W = key.clone().into();
+        let value = store::persistent().get(&w.to_key());
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| self.extend_ttl(key, threshold, extend_to));
+        }
+        value
+    }
+
+    /// The entry for `key`, to insert, modify or remove its value based on what is stored.
+    pub fn entry(&mut self, key: K) -> Entry<'_, Self> {
+        Entry::new(self, key)
+    }
+
+    /// The value for `key`, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, key: K, err: E) -> Result<V, E> {
+        self.get(key).ok_or(err)
+    }
+
+    /// The value for `key`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, key: K) -> V
+    where
+        V: Default,
+    {
+        self.get(key).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, key: K, value: &V) {
+        let w: W = key.clone().into();
+        let k = w.to_key();
+        let storage = store::persistent();
+        if !storage.has(&k) {
+            let len = self.len();
+            storage.set(&W::index_key(len), &key);
+            storage.set(&w.position_key(), &len);
+            storage.set(&W::len_key(), &(len + 1));
+        }
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| self.extend_ttl(key, threshold, extend_to));
+    }
+
+    pub fn has(&self, key: K) -> bool {
+        let w: W = key.into();
+        let k = w.to_key();
+        store::persistent().has(&k)
+    }
+
+    pub fn update(&mut self, key: K, f: impl FnOnce(Option<V>) -> V) -> V {
+        let value = f(self.get(key.clone()));
+        self.set(key, &value);
+        value
+    }
+
+    pub fn try_update<E>(
+        &mut self,
+        key: K,
+        f: impl FnOnce(Option<V>) -> Result<V, E>,
+    ) -> Result<V, E> {
+        let value = f(self.get(key.clone()))?;
+        self.set(key, &value);
+        Ok(value)
+    }
+
+    /// Extend the TTL of the entry for `key`, along with its place in the index.
+    pub fn extend_ttl(&self, key: K, threshold: u32, extend_to: u32) {
+        let w: W = key.into();
+        let storage = store::persistent();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        storage.extend_ttl(&w.to_key(), threshold, extend_to);
+        storage.extend_ttl(&w.position_key(), threshold, extend_to);
+        storage.extend_ttl(&W::index_key(position), threshold, extend_to);
+        storage.extend_ttl(&W::len_key(), threshold, extend_to);
+    }
+
+    /// Extend the TTL of the entries for `keys`, skipping keys that have no entry.
+    pub fn extend_ttl_keys(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        threshold: u32,
+        extend_to: u32,
+    ) {
+        for key in keys {
+            self.extend_ttl(key, threshold, extend_to);
+        }
+    }
+
+    pub fn remove(&self, key: K) {
+        let w: W = key.into();
+        let storage = store::persistent();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        let last = self.len() - 1;
+        if position != last {
+            // A missing last index entry leaves the removed key's place empty, to be skipped.
+            if let Some(moved) = storage.get::<_, K>(&W::index_key(last)) {
+                let moved_w: W = moved.clone().into();
+                storage.set(&W::index_key(position), &moved);
+                storage.set(&moved_w.position_key(), &position);
+            } else {
+                storage.remove(&W::index_key(position));
+            }
+        }
+        storage.remove(&W::index_key(last));
+        if last == 0 {
+            storage.remove(&W::len_key());
+        } else {
+            storage.set(&W::len_key(), &last);
+        }
+        storage.remove(&w.position_key());
+        storage.remove(&w.to_key());
+    }
+
+    /// Number of entries in the map.
+    pub fn len(&self) -> u32 {
+        store::persistent().get(&W::len_key()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The keys at positions `start` to `start + limit` of the index. Index entries that are
+    /// missing, e.g. archived, are skipped, so fewer than `limit` keys can be returned.
+    pub fn keys(&self, start: u32, limit: u32) -> Vec<K> {
+        let storage = store::persistent();
+        let end = start.saturating_add(limit).min(self.len());
+        let mut keys = Vec::new(env());
+        for position in start..end {
+            if let Some(key) = storage.get(&W::index_key(position)) {
+                keys.push_back(key);
+            }
+        }
+        keys
+    }
+
+    /// The values of the keys at positions `start` to `start + limit` of the index, skipping
+    /// missing entries as [`Self::keys`] does.
+    pub fn values(&self, start: u32, limit: u32) -> Vec<V> {
+        let storage = store::persistent();
+        let mut values = Vec::new(env());
+        for key in self.keys(start, limit) {
+            let w: W = key.into();
+            if let Some(value) = storage.get(&w.to_key()) {
+                values.push_back(value);
+            }
+        }
+        values
+    }
+
+    /// Iterate over all entries in index order, skipping index entries or values that are missing,
+    /// e.g. archived. Entries are read lazily, as the iterator advances; use [`Self::keys`] or
+    /// [`Self::values`] to start at a position without reading the entries before it.
+    pub fn iter(&self) -> IterableMapIter<K, V, W> {
+        IterableMapIter {
+            next: 0,
+            len: self.len(),
+            k: PhantomData,
+            v: PhantomData,
+            w: PhantomData,
+        }
+    }
+}
+
+impl<K, V, W> Default for PersistentIterableMap<K, V, W>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    fn default() -> Self {
+        Self {
+            v: PhantomData,
+            k: PhantomData,
+            w: PhantomData,
+        }
+    }
+}
+
+impl<K, V, W> IntoIterator for &PersistentIterableMap<K, V, W>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Item = (K, V);
+    type IntoIter = IterableMapIter<K, V, W>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a [`PersistentIterableMap`].
+pub struct IterableMapIter<K, V, W> {
+    next: u32,
+    len: u32,
+    k: PhantomData<K>,
+    v: PhantomData<V>,
+    w: PhantomData<W>,
+}
+
+impl<K, V, W> Iterator for IterableMapIter<K, V, W>
+where
+    K: Into<W> + Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    W: LoamIterableKey,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let storage = store::persistent();
+        while self.next < self.len {
+            let position = self.next;
+            self.next += 1;
+            let Some(key) = storage.get::<_, K>(&W::index_key(position)) else {
+                continue;
+            };
+            let w: W = key.clone().into();
+            if let Some(value) = storage.get(&w.to_key()) {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Missing entries are skipped, so there can be fewer.
+        (0, Some((self.len - self.next) as usize))
+    }
+}
+
 #[derive(Clone)]
 pub struct PersistentItem<V, K>
 where
@@ -105,40 +527,69 @@ where
 {
     pub fn get(&self) -> Option<V> {
         let key = K::default().to_key();
-        env().storage().persistent().get(&key)
+        let storage = store::persistent();
+        let value = storage.get(&key);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| {
+                storage.extend_ttl(&key, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The value, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, err: E) -> Result<V, E> {
+        self.get().ok_or(err)
+    }
+
+    /// The value, or `V::default()` if there is none.
+    pub fn get_or_default(&self) -> V
+    where
+        V: Default,
+    {
+        self.get().unwrap_or_default()
     }
 
     pub fn set(&mut self, value: &V) {
         let key = K::default().to_key();
-        env().storage().persistent().set(&key, value);
+        let storage = store::persistent();
+        storage.set(&key, value);
+        extend_on_write::<K>(|threshold, extend_to| storage.extend_ttl(&key, threshold, extend_to));
     }
 
     pub fn has(&self) -> bool {
         let key = K::default().to_key();
-        env().storage().persistent().has(&key)
+        store::persistent().has(&key)
     }
 
     pub fn update(&self, f: impl FnOnce(Option<V>) -> V) -> V {
         let key = K::default().to_key();
-        env().storage().persistent().update(&key, f)
+        let storage = store::persistent();
+        let value = storage.update(&key, f);
+        extend_on_update::<K>(|threshold, extend_to| {
+            storage.extend_ttl(&key, threshold, extend_to);
+        });
+        value
     }
 
     pub fn try_update<E>(&self, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let key = K::default().to_key();
-        env().storage().persistent().try_update(&key, f)
+        let storage = store::persistent();
+        let value = storage.try_update(&key, f)?;
+        extend_on_update::<K>(|threshold, extend_to| {
+            storage.extend_ttl(&key, threshold, extend_to);
+        });
+        Ok(value)
     }
 
     pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
         let key = K::default().to_key();
-        env()
-            .storage()
-            .persistent()
-            .extend_ttl(&key, threshold, extend_to);
+        store::persistent().extend_ttl(&key, threshold, extend_to);
     }
 
     pub fn remove(&self) {
         let key = K::default().to_key();
-        env().storage().persistent().remove(&key);
+        store::persistent().remove(&key);
     }
 }
 
@@ -176,41 +627,77 @@ where
     pub fn get(&self, key: K) -> Option<V> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().get(&k)
+        let storage = store::instance();
+        let value = storage.get(&k);
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        }
+        value
+    }
+
+    /// The entry for `key`, to insert, modify or remove its value based on what is stored.
+    pub fn entry(&mut self, key: K) -> Entry<'_, Self>
+    where
+        K: Clone,
+    {
+        Entry::new(self, key)
+    }
+
+    /// The value for `key`, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, key: K, err: E) -> Result<V, E> {
+        self.get(key).ok_or(err)
+    }
+
+    /// The value for `key`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, key: K) -> V
+    where
+        V: Default,
+    {
+        self.get(key).unwrap_or_default()
     }
 
     pub fn set(&mut self, key: K, value: &V) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().set(&k, value);
+        let storage = store::instance();
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
     }
 
     pub fn has(&self, key: K) -> bool {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().has(&k)
+        store::instance().has(&k)
     }
 
     pub fn update(&self, key: K, f: impl FnOnce(Option<V>) -> V) -> V {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().update(&k, f)
+        let storage = store::instance();
+        let value = storage.update(&k, f);
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        value
     }
 
     pub fn try_update<E>(&self, key: K, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().try_update(&k, f)
+        let storage = store::instance();
+        let value = storage.try_update(&k, f)?;
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        Ok(value)
     }
 
+    /// Extend the TTL of the contract instance, which holds every entry of the map along with
+    /// the rest of instance storage.
     pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
-        env().storage().instance().extend_ttl(threshold, extend_to);
+        store::instance().extend_ttl(threshold, extend_to);
     }
 
     pub fn remove(&self, key: K) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().instance().remove(&k);
+        store::instance().remove(&k);
     }
 }
 
@@ -235,46 +722,96 @@ where
     pub fn get(&self, key: K) -> Option<V> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().get(&k)
+        let storage = store::temporary();
+        let value = storage.get(&k);
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| {
+                storage.extend_ttl(&k, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The entry for `key`, to insert, modify or remove its value based on what is stored.
+    pub fn entry(&mut self, key: K) -> Entry<'_, Self>
+    where
+        K: Clone,
+    {
+        Entry::new(self, key)
+    }
+
+    /// The value for `key`, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, key: K, err: E) -> Result<V, E> {
+        self.get(key).ok_or(err)
+    }
+
+    /// The value for `key`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, key: K) -> V
+    where
+        V: Default,
+    {
+        self.get(key).unwrap_or_default()
     }
 
     pub fn set(&mut self, key: K, value: &V) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().set(&k, value);
+        let storage = store::temporary();
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
     }
 
     pub fn has(&self, key: K) -> bool {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().has(&k)
+        store::temporary().has(&k)
     }
 
     pub fn update(&self, key: K, f: impl FnOnce(Option<V>) -> V) -> V {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().update(&k, f)
+        let storage = store::temporary();
+        let value = storage.update(&k, f);
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
+        value
     }
 
     pub fn try_update<E>(&self, key: K, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().try_update(&k, f)
+        let storage = store::temporary();
+        let value = storage.try_update(&k, f)?;
+        extend_on_update::<W>(|threshold, extend_to| storage.extend_ttl(&k, threshold, extend_to));
+        Ok(value)
     }
 
     pub fn extend_ttl(&self, key: K, threshold: u32, extend_to: u32) {
         let w: W = key.into();
         let k = w.to_key();
-        env()
-            .storage()
-            .temporary()
-            .extend_ttl(&k, threshold, extend_to);
+        store::temporary().extend_ttl(&k, threshold, extend_to);
+    }
+
+    /// Extend the TTL of the entries for `keys`, skipping keys that have no entry.
+    pub fn extend_ttl_keys(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        threshold: u32,
+        extend_to: u32,
+    ) {
+        let storage = store::temporary();
+        for key in keys {
+            let w: W = key.into();
+            let k = w.to_key();
+            if storage.has(&k) {
+                storage.extend_ttl(&k, threshold, extend_to);
+            }
+        }
     }
 
     pub fn remove(&self, key: K) {
         let w: W = key.into();
         let k = w.to_key();
-        env().storage().temporary().remove(&k);
+        store::temporary().remove(&k);
     }
 }
 
@@ -325,36 +862,62 @@ where
 {
     pub fn get(&self) -> Option<V> {
         let key = K::default().to_key();
-        env().storage().instance().get(&key)
+        let storage = store::instance();
+        let value = storage.get(&key);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        }
+        value
+    }
+
+    /// The value, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, err: E) -> Result<V, E> {
+        self.get().ok_or(err)
+    }
+
+    /// The value, or `V::default()` if there is none.
+    pub fn get_or_default(&self) -> V
+    where
+        V: Default,
+    {
+        self.get().unwrap_or_default()
     }
 
     pub fn set(&mut self, value: &V) {
         let key = K::default().to_key();
-        env().storage().instance().set(&key, value);
+        let storage = store::instance();
+        storage.set(&key, value);
+        extend_on_write::<K>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
     }
 
     pub fn has(&self) -> bool {
         let key = K::default().to_key();
-        env().storage().instance().has(&key)
+        store::instance().has(&key)
     }
 
     pub fn update(&self, f: impl FnOnce(Option<V>) -> V) -> V {
         let key = K::default().to_key();
-        env().storage().instance().update(&key, f)
+        let storage = store::instance();
+        let value = storage.update(&key, f);
+        extend_on_update::<K>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        value
     }
 
     pub fn try_update<E>(&self, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let key = K::default().to_key();
-        env().storage().instance().try_update(&key, f)
+        let storage = store::instance();
+        let value = storage.try_update(&key, f)?;
+        extend_on_update::<K>(|threshold, extend_to| storage.extend_ttl(threshold, extend_to));
+        Ok(value)
     }
 
     pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
-        env().storage().instance().extend_ttl(threshold, extend_to);
+        store::instance().extend_ttl(threshold, extend_to);
     }
 
     pub fn remove(&self) {
         let key = K::default().to_key();
-        env().storage().instance().remove(&key);
+        store::instance().remove(&key);
     }
 }
 
@@ -375,40 +938,69 @@ where
 {
     pub fn get(&self) -> Option<V> {
         let key = K::default().to_key();
-        env().storage().temporary().get(&key)
+        let storage = store::temporary();
+        let value = storage.get(&key);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| {
+                storage.extend_ttl(&key, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The value, or `err` if there is none, e.g. a `#[contracterror]` value.
+    pub fn get_or_err<E>(&self, err: E) -> Result<V, E> {
+        self.get().ok_or(err)
+    }
+
+    /// The value, or `V::default()` if there is none.
+    pub fn get_or_default(&self) -> V
+    where
+        V: Default,
+    {
+        self.get().unwrap_or_default()
     }
 
     pub fn set(&mut self, value: &V) {
         let key = K::default().to_key();
-        env().storage().temporary().set(&key, value);
+        let storage = store::temporary();
+        storage.set(&key, value);
+        extend_on_write::<K>(|threshold, extend_to| storage.extend_ttl(&key, threshold, extend_to));
     }
 
     pub fn has(&self) -> bool {
         let key = K::default().to_key();
-        env().storage().temporary().has(&key)
+        store::temporary().has(&key)
     }
 
     pub fn update(&self, f: impl FnOnce(Option<V>) -> V) -> V {
         let key = K::default().to_key();
-        env().storage().temporary().update(&key, f)
+        let storage = store::temporary();
+        let value = storage.update(&key, f);
+        extend_on_update::<K>(|threshold, extend_to| {
+            storage.extend_ttl(&key, threshold, extend_to);
+        });
+        value
     }
 
     pub fn try_update<E>(&self, f: impl FnOnce(Option<V>) -> Result<V, E>) -> Result<V, E> {
         let key = K::default().to_key();
-        env().storage().temporary().try_update(&key, f)
+        let storage = store::temporary();
+        let value = storage.try_update(&key, f)?;
+        extend_on_update::<K>(|threshold, extend_to| {
+            storage.extend_ttl(&key, threshold, extend_to);
+        });
+        Ok(value)
     }
 
     pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
         let key = K::default().to_key();
-        env()
-            .storage()
-            .temporary()
-            .extend_ttl(&key, threshold, extend_to);
+        store::temporary().extend_ttl(&key, threshold, extend_to);
     }
 
     pub fn remove(&self) {
         let key = K::default().to_key();
-        env().storage().temporary().remove(&key);
+        store::temporary().remove(&key);
     }
 }
 
@@ -437,3 +1029,1070 @@ where
         }
     }
 }
+
+/// A map keyed by two keys, such as an owner and a spender, which keeps an index of the
+/// second-level keys under each first-level key so they can be listed or cleared together.
+///
+/// Like [`PersistentIterableMap`], each entry costs two extra ledger entries and removal moves
+/// the last key under the same first-level key into the freed slot.
+#[derive(Clone)]
+pub struct PersistentDoubleMap<K1, K2, V, W = (K1, K2)>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    k1: PhantomData<K1>,
+    k2: PhantomData<K2>,
+    v: PhantomData<V>,
+    w: PhantomData<W>,
+}
+
+impl<K1, K2, V, W> PersistentDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    pub fn get(&self, first: K1, second: K2) -> Option<V> {
+        let w: W = (first.clone(), second.clone()).into();
+        let value = store::persistent().get(&w.to_key());
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| {
+                self.extend_ttl(first, second, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The entry for `(first, second)`, to insert, modify or remove its value based on what is
+    /// stored.
+    pub fn entry(&mut self, first: K1, second: K2) -> Entry<'_, Self> {
+        Entry::new(self, (first, second))
+    }
+
+    /// The value for `(first, second)`, or `err` if there is none, e.g. a `#[contracterror]`
+    /// value.
+    pub fn get_or_err<E>(&self, first: K1, second: K2, err: E) -> Result<V, E> {
+        self.get(first, second).ok_or(err)
+    }
+
+    /// The value for `(first, second)`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, first: K1, second: K2) -> V
+    where
+        V: Default,
+    {
+        self.get(first, second).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, first: K1, second: K2, value: &V) {
+        let w: W = (first.clone(), second.clone()).into();
+        let k = w.to_key();
+        let storage = store::persistent();
+        if !storage.has(&k) {
+            let len = self.len(first.clone());
+            storage.set(&W::index_key(&first, len), &second);
+            storage.set(&w.position_key(), &len);
+            storage.set(&W::len_key(&first), &(len + 1));
+        }
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| {
+            self.extend_ttl(first, second, threshold, extend_to);
+        });
+    }
+
+    pub fn has(&self, first: K1, second: K2) -> bool {
+        let w: W = (first, second).into();
+        store::persistent().has(&w.to_key())
+    }
+
+    pub fn update(&mut self, first: K1, second: K2, f: impl FnOnce(Option<V>) -> V) -> V {
+        let value = f(self.get(first.clone(), second.clone()));
+        self.set(first, second, &value);
+        value
+    }
+
+    pub fn try_update<E>(
+        &mut self,
+        first: K1,
+        second: K2,
+        f: impl FnOnce(Option<V>) -> Result<V, E>,
+    ) -> Result<V, E> {
+        let value = f(self.get(first.clone(), second.clone()))?;
+        self.set(first, second, &value);
+        Ok(value)
+    }
+
+    /// Extend the TTL of the entry for `first` and `second`, along with its place in the index.
+    pub fn extend_ttl(&self, first: K1, second: K2, threshold: u32, extend_to: u32) {
+        let w: W = (first.clone(), second).into();
+        let storage = store::persistent();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        storage.extend_ttl(&w.to_key(), threshold, extend_to);
+        storage.extend_ttl(&w.position_key(), threshold, extend_to);
+        storage.extend_ttl(&W::index_key(&first, position), threshold, extend_to);
+        storage.extend_ttl(&W::len_key(&first), threshold, extend_to);
+    }
+
+    /// Extend the TTL of the entries for `keys`, skipping keys that have no entry.
+    pub fn extend_ttl_keys(
+        &self,
+        keys: impl IntoIterator<Item = (K1, K2)>,
+        threshold: u32,
+        extend_to: u32,
+    ) {
+        for (first, second) in keys {
+            self.extend_ttl(first, second, threshold, extend_to);
+        }
+    }
+
+    pub fn remove(&self, first: K1, second: K2) {
+        let w: W = (first.clone(), second).into();
+        let storage = store::persistent();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        let last = self.len(first.clone()) - 1;
+        if position != last {
+            // A missing last index entry leaves the removed key's place empty, to be skipped.
+            if let Some(moved) = storage.get::<_, K2>(&W::index_key(&first, last)) {
+                let moved_w: W = (first.clone(), moved.clone()).into();
+                storage.set(&W::index_key(&first, position), &moved);
+                storage.set(&moved_w.position_key(), &position);
+            } else {
+                storage.remove(&W::index_key(&first, position));
+            }
+        }
+        storage.remove(&W::index_key(&first, last));
+        if last == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &last);
+        }
+        storage.remove(&w.position_key());
+        storage.remove(&w.to_key());
+    }
+
+    /// Remove up to `limit` of the entries stored under `first`, from the end of its index, and
+    /// return how many are left.
+    ///
+    /// Each entry costs a few storage writes, so clear a large map over several calls until this
+    /// returns 0. Index entries that are missing are skipped.
+    pub fn clear(&self, first: K1, limit: u32) -> u32 {
+        let storage = store::persistent();
+        let len = self.len(first.clone());
+        let left = len.saturating_sub(limit);
+        for position in (left..len).rev() {
+            let index_key = W::index_key(&first, position);
+            if let Some(second) = storage.get::<_, K2>(&index_key) {
+                let w: W = (first.clone(), second).into();
+                storage.remove(&w.to_key());
+                storage.remove(&w.position_key());
+            }
+            storage.remove(&index_key);
+        }
+        if left == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &left);
+        }
+        left
+    }
+
+    /// Number of entries stored under `first`.
+    pub fn len(&self, first: K1) -> u32 {
+        store::persistent()
+            .get(&W::len_key(&first))
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self, first: K1) -> bool {
+        self.len(first) == 0
+    }
+
+    /// The second-level keys stored under `first` at positions `start` to `start + limit`. Index
+    /// entries that are missing are skipped, as in [`Self::clear`], so fewer than `limit` keys can
+    /// be returned.
+    pub fn keys(&self, first: K1, start: u32, limit: u32) -> Vec<K2> {
+        let storage = store::persistent();
+        let end = start.saturating_add(limit).min(self.len(first.clone()));
+        let mut keys = Vec::new(env());
+        for position in start..end {
+            if let Some(key) = storage.get(&W::index_key(&first, position)) {
+                keys.push_back(key);
+            }
+        }
+        keys
+    }
+}
+
+impl<K1, K2, V, W> Default for PersistentDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    fn default() -> Self {
+        Self {
+            k1: PhantomData,
+            k2: PhantomData,
+            v: PhantomData,
+            w: PhantomData,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InstanceDoubleMap<K1, K2, V, W = (K1, K2)>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    k1: PhantomData<K1>,
+    k2: PhantomData<K2>,
+    v: PhantomData<V>,
+    w: PhantomData<W>,
+}
+
+impl<K1, K2, V, W> InstanceDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    pub fn get(&self, first: K1, second: K2) -> Option<V> {
+        let w: W = (first.clone(), second.clone()).into();
+        let value = store::instance().get(&w.to_key());
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+        }
+        value
+    }
+
+    /// The entry for `(first, second)`, to insert, modify or remove its value based on what is
+    /// stored.
+    pub fn entry(&mut self, first: K1, second: K2) -> Entry<'_, Self> {
+        Entry::new(self, (first, second))
+    }
+
+    /// The value for `(first, second)`, or `err` if there is none, e.g. a `#[contracterror]`
+    /// value.
+    pub fn get_or_err<E>(&self, first: K1, second: K2, err: E) -> Result<V, E> {
+        self.get(first, second).ok_or(err)
+    }
+
+    /// The value for `(first, second)`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, first: K1, second: K2) -> V
+    where
+        V: Default,
+    {
+        self.get(first, second).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, first: K1, second: K2, value: &V) {
+        let w: W = (first.clone(), second.clone()).into();
+        let k = w.to_key();
+        let storage = store::instance();
+        if !storage.has(&k) {
+            let len = self.len(first.clone());
+            storage.set(&W::index_key(&first, len), &second);
+            storage.set(&w.position_key(), &len);
+            storage.set(&W::len_key(&first), &(len + 1));
+        }
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+    }
+
+    pub fn has(&self, first: K1, second: K2) -> bool {
+        let w: W = (first, second).into();
+        store::instance().has(&w.to_key())
+    }
+
+    pub fn update(&mut self, first: K1, second: K2, f: impl FnOnce(Option<V>) -> V) -> V {
+        let value = f(self.get(first.clone(), second.clone()));
+        self.set(first, second, &value);
+        value
+    }
+
+    pub fn try_update<E>(
+        &mut self,
+        first: K1,
+        second: K2,
+        f: impl FnOnce(Option<V>) -> Result<V, E>,
+    ) -> Result<V, E> {
+        let value = f(self.get(first.clone(), second.clone()))?;
+        self.set(first, second, &value);
+        Ok(value)
+    }
+
+    /// Extend the TTL of the contract instance, which holds every entry of the map along with
+    /// the rest of instance storage.
+    pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
+        store::instance().extend_ttl(threshold, extend_to);
+    }
+
+    pub fn remove(&self, first: K1, second: K2) {
+        let w: W = (first.clone(), second).into();
+        let storage = store::instance();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        let last = self.len(first.clone()) - 1;
+        if position != last {
+            // A missing last index entry leaves the removed key's place empty, to be skipped.
+            if let Some(moved) = storage.get::<_, K2>(&W::index_key(&first, last)) {
+                let moved_w: W = (first.clone(), moved.clone()).into();
+                storage.set(&W::index_key(&first, position), &moved);
+                storage.set(&moved_w.position_key(), &position);
+            } else {
+                storage.remove(&W::index_key(&first, position));
+            }
+        }
+        storage.remove(&W::index_key(&first, last));
+        if last == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &last);
+        }
+        storage.remove(&w.position_key());
+        storage.remove(&w.to_key());
+    }
+
+    /// Remove up to `limit` of the entries stored under `first`, from the end of its index, and
+    /// return how many are left.
+    ///
+    /// Each entry costs a few storage writes, so clear a large map over several calls until this
+    /// returns 0. Index entries that are missing are skipped.
+    pub fn clear(&self, first: K1, limit: u32) -> u32 {
+        let storage = store::instance();
+        let len = self.len(first.clone());
+        let left = len.saturating_sub(limit);
+        for position in (left..len).rev() {
+            let index_key = W::index_key(&first, position);
+            if let Some(second) = storage.get::<_, K2>(&index_key) {
+                let w: W = (first.clone(), second).into();
+                storage.remove(&w.to_key());
+                storage.remove(&w.position_key());
+            }
+            storage.remove(&index_key);
+        }
+        if left == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &left);
+        }
+        left
+    }
+
+    /// Number of entries stored under `first`.
+    pub fn len(&self, first: K1) -> u32 {
+        store::instance()
+            .get(&W::len_key(&first))
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self, first: K1) -> bool {
+        self.len(first) == 0
+    }
+
+    /// The second-level keys stored under `first` at positions `start` to `start + limit`. Index
+    /// entries that are missing are skipped, as in [`Self::clear`], so fewer than `limit` keys can
+    /// be returned.
+    pub fn keys(&self, first: K1, start: u32, limit: u32) -> Vec<K2> {
+        let storage = store::instance();
+        let end = start.saturating_add(limit).min(self.len(first.clone()));
+        let mut keys = Vec::new(env());
+        for position in start..end {
+            if let Some(key) = storage.get(&W::index_key(&first, position)) {
+                keys.push_back(key);
+            }
+        }
+        keys
+    }
+}
+
+impl<K1, K2, V, W> Default for InstanceDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    fn default() -> Self {
+        Self {
+            k1: PhantomData,
+            k2: PhantomData,
+            v: PhantomData,
+            w: PhantomData,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TemporaryDoubleMap<K1, K2, V, W = (K1, K2)>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    k1: PhantomData<K1>,
+    k2: PhantomData<K2>,
+    v: PhantomData<V>,
+    w: PhantomData<W>,
+}
+
+impl<K1, K2, V, W> TemporaryDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    pub fn get(&self, first: K1, second: K2) -> Option<V> {
+        let w: W = (first.clone(), second.clone()).into();
+        let value = store::temporary().get(&w.to_key());
+        if value.is_some() {
+            extend_on_read::<W>(|threshold, extend_to| {
+                self.extend_ttl(first, second, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// The entry for `(first, second)`, to insert, modify or remove its value based on what is
+    /// stored.
+    pub fn entry(&mut self, first: K1, second: K2) -> Entry<'_, Self> {
+        Entry::new(self, (first, second))
+    }
+
+    /// The value for `(first, second)`, or `err` if there is none, e.g. a `#[contracterror]`
+    /// value.
+    pub fn get_or_err<E>(&self, first: K1, second: K2, err: E) -> Result<V, E> {
+        self.get(first, second).ok_or(err)
+    }
+
+    /// The value for `(first, second)`, or `V::default()` if there is none.
+    pub fn get_or_default(&self, first: K1, second: K2) -> V
+    where
+        V: Default,
+    {
+        self.get(first, second).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, first: K1, second: K2, value: &V) {
+        let w: W = (first.clone(), second.clone()).into();
+        let k = w.to_key();
+        let storage = store::temporary();
+        if !storage.has(&k) {
+            let len = self.len(first.clone());
+            storage.set(&W::index_key(&first, len), &second);
+            storage.set(&w.position_key(), &len);
+            storage.set(&W::len_key(&first), &(len + 1));
+        }
+        storage.set(&k, value);
+        extend_on_write::<W>(|threshold, extend_to| {
+            self.extend_ttl(first, second, threshold, extend_to);
+        });
+    }
+
+    pub fn has(&self, first: K1, second: K2) -> bool {
+        let w: W = (first, second).into();
+        store::temporary().has(&w.to_key())
+    }
+
+    pub fn update(&mut self, first: K1, second: K2, f: impl FnOnce(Option<V>) -> V) -> V {
+        let value = f(self.get(first.clone(), second.clone()));
+        self.set(first, second, &value);
+        value
+    }
+
+    pub fn try_update<E>(
+        &mut self,
+        first: K1,
+        second: K2,
+        f: impl FnOnce(Option<V>) -> Result<V, E>,
+    ) -> Result<V, E> {
+        let value = f(self.get(first.clone(), second.clone()))?;
+        self.set(first, second, &value);
+        Ok(value)
+    }
+
+    /// Extend the TTL of the entry for `first` and `second`, along with its place in the index.
+    pub fn extend_ttl(&self, first: K1, second: K2, threshold: u32, extend_to: u32) {
+        let w: W = (first.clone(), second).into();
+        let storage = store::temporary();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        storage.extend_ttl(&w.to_key(), threshold, extend_to);
+        storage.extend_ttl(&w.position_key(), threshold, extend_to);
+        storage.extend_ttl(&W::index_key(&first, position), threshold, extend_to);
+        storage.extend_ttl(&W::len_key(&first), threshold, extend_to);
+    }
+
+    /// Extend the TTL of the entries for `keys`, skipping keys that have no entry.
+    pub fn extend_ttl_keys(
+        &self,
+        keys: impl IntoIterator<Item = (K1, K2)>,
+        threshold: u32,
+        extend_to: u32,
+    ) {
+        for (first, second) in keys {
+            self.extend_ttl(first, second, threshold, extend_to);
+        }
+    }
+
+    pub fn remove(&self, first: K1, second: K2) {
+        let w: W = (first.clone(), second).into();
+        let storage = store::temporary();
+        let Some(position) = storage.get::<_, u32>(&w.position_key()) else {
+            return;
+        };
+        let last = self.len(first.clone()) - 1;
+        if position != last {
+            // A missing last index entry leaves the removed key's place empty, to be skipped.
+            if let Some(moved) = storage.get::<_, K2>(&W::index_key(&first, last)) {
+                let moved_w: W = (first.clone(), moved.clone()).into();
+                storage.set(&W::index_key(&first, position), &moved);
+                storage.set(&moved_w.position_key(), &position);
+            } else {
+                storage.remove(&W::index_key(&first, position));
+            }
+        }
+        storage.remove(&W::index_key(&first, last));
+        if last == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &last);
+        }
+        storage.remove(&w.position_key());
+        storage.remove(&w.to_key());
+    }
+
+    /// Remove up to `limit` of the entries stored under `first`, from the end of its index, and
+    /// return how many are left.
+    ///
+    /// Each entry costs a few storage writes, so clear a large map over several calls until this
+    /// returns 0. Index entries that are missing are skipped.
+    pub fn clear(&self, first: K1, limit: u32) -> u32 {
+        let storage = store::temporary();
+        let len = self.len(first.clone());
+        let left = len.saturating_sub(limit);
+        for position in (left..len).rev() {
+            let index_key = W::index_key(&first, position);
+            if let Some(second) = storage.get::<_, K2>(&index_key) {
+                let w: W = (first.clone(), second).into();
+                storage.remove(&w.to_key());
+                storage.remove(&w.position_key());
+            }
+            storage.remove(&index_key);
+        }
+        if left == 0 {
+            storage.remove(&W::len_key(&first));
+        } else {
+            storage.set(&W::len_key(&first), &left);
+        }
+        left
+    }
+
+    /// Number of entries stored under `first`.
+    pub fn len(&self, first: K1) -> u32 {
+        store::temporary()
+            .get(&W::len_key(&first))
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self, first: K1) -> bool {
+        self.len(first) == 0
+    }
+
+    /// The second-level keys stored under `first` at positions `start` to `start + limit`. Index
+    /// entries that are missing are skipped, as in [`Self::clear`], so fewer than `limit` keys can
+    /// be returned.
+    pub fn keys(&self, first: K1, start: u32, limit: u32) -> Vec<K2> {
+        let storage = store::temporary();
+        let end = start.saturating_add(limit).min(self.len(first.clone()));
+        let mut keys = Vec::new(env());
+        for position in start..end {
+            if let Some(key) = storage.get(&W::index_key(&first, position)) {
+                keys.push_back(key);
+            }
+        }
+        keys
+    }
+}
+
+impl<K1, K2, V, W> Default for TemporaryDoubleMap<K1, K2, V, W>
+where
+    K1: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K2: Clone + IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    (K1, K2): Into<W>,
+    W: LoamDoubleKey<K1>,
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    fn default() -> Self {
+        Self {
+            k1: PhantomData,
+            k2: PhantomData,
+            v: PhantomData,
+            w: PhantomData,
+        }
+    }
+}
+
+/// A list kept one element per ledger entry, next to an entry holding its length, so pushing
+/// an element does not rewrite the ones before it.
+#[derive(Clone)]
+pub struct PersistentVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    v: PhantomData<V>,
+    k: PhantomData<K>,
+}
+
+impl<V, K> PersistentVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    pub fn get(&self, index: u32) -> Option<V> {
+        let value = Self::load(index);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| {
+                self.extend_ttl(index, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// Replace the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn set(&mut self, index: u32, value: &V) {
+        assert!(index < self.len(), "index out of bounds");
+        store::persistent().set(&K::index_key(index), value);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(index, threshold, extend_to));
+    }
+
+    pub fn push(&self, value: &V) {
+        let storage = store::persistent();
+        let len = self.len();
+        storage.set(&K::index_key(len), value);
+        storage.set(&K::len_key(), &(len + 1));
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(len, threshold, extend_to));
+    }
+
+    pub fn pop(&self) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        let storage = store::persistent();
+        let value = storage.get(&K::index_key(last));
+        storage.remove(&K::index_key(last));
+        if last == 0 {
+            storage.remove(&K::len_key());
+        } else {
+            storage.set(&K::len_key(), &last);
+            extend_on_write::<K>(|threshold, extend_to| {
+                storage.extend_ttl(&K::len_key(), threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// Remove the element at `index`, moving the last element into its place.
+    pub fn swap_remove(&self, index: u32) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        if index > last {
+            return None;
+        }
+        if index == last {
+            return self.pop();
+        }
+        let storage = store::persistent();
+        let value = storage.get(&K::index_key(index));
+        let moved: V = storage.get(&K::index_key(last))?;
+        storage.set(&K::index_key(index), &moved);
+        storage.remove(&K::index_key(last));
+        storage.set(&K::len_key(), &last);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(index, threshold, extend_to));
+        value
+    }
+
+    /// Extend the TTL of the element at `index` and of the stored length.
+    pub fn extend_ttl(&self, index: u32, threshold: u32, extend_to: u32) {
+        let storage = store::persistent();
+        storage.extend_ttl(&K::index_key(index), threshold, extend_to);
+        storage.extend_ttl(&K::len_key(), threshold, extend_to);
+    }
+
+    pub fn len(&self) -> u32 {
+        store::persistent().get(&K::len_key()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> VecIter<V> {
+        self.range(0..self.len())
+    }
+
+    /// Iterate over the elements in `range`, clamped to the length of the vector.
+    pub fn range(&self, range: Range<u32>) -> VecIter<V> {
+        VecIter {
+            next: range.start,
+            end: range.end.min(self.len()),
+            load: Self::load,
+        }
+    }
+
+    fn load(index: u32) -> Option<V> {
+        store::persistent().get(&K::index_key(index))
+    }
+}
+
+impl<V, K> Default for PersistentVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    fn default() -> Self {
+        Self {
+            v: PhantomData,
+            k: PhantomData,
+        }
+    }
+}
+
+impl<V, K> IntoIterator for &PersistentVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    type Item = V;
+    type IntoIter = VecIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Clone)]
+pub struct InstanceVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    v: PhantomData<V>,
+    k: PhantomData<K>,
+}
+
+impl<V, K> InstanceVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    pub fn get(&self, index: u32) -> Option<V> {
+        let value = Self::load(index);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+        }
+        value
+    }
+
+    /// Replace the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn set(&mut self, index: u32, value: &V) {
+        assert!(index < self.len(), "index out of bounds");
+        store::instance().set(&K::index_key(index), value);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+    }
+
+    pub fn push(&self, value: &V) {
+        let storage = store::instance();
+        let len = self.len();
+        storage.set(&K::index_key(len), value);
+        storage.set(&K::len_key(), &(len + 1));
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+    }
+
+    pub fn pop(&self) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        let storage = store::instance();
+        let value = storage.get(&K::index_key(last));
+        storage.remove(&K::index_key(last));
+        if last == 0 {
+            storage.remove(&K::len_key());
+        } else {
+            storage.set(&K::len_key(), &last);
+        }
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+        value
+    }
+
+    /// Remove the element at `index`, moving the last element into its place.
+    pub fn swap_remove(&self, index: u32) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        if index > last {
+            return None;
+        }
+        if index == last {
+            return self.pop();
+        }
+        let storage = store::instance();
+        let value = storage.get(&K::index_key(index));
+        let moved: V = storage.get(&K::index_key(last))?;
+        storage.set(&K::index_key(index), &moved);
+        storage.remove(&K::index_key(last));
+        storage.set(&K::len_key(), &last);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(threshold, extend_to));
+        value
+    }
+
+    pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
+        store::instance().extend_ttl(threshold, extend_to);
+    }
+
+    pub fn len(&self) -> u32 {
+        store::instance().get(&K::len_key()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> VecIter<V> {
+        self.range(0..self.len())
+    }
+
+    /// Iterate over the elements in `range`, clamped to the length of the vector.
+    pub fn range(&self, range: Range<u32>) -> VecIter<V> {
+        VecIter {
+            next: range.start,
+            end: range.end.min(self.len()),
+            load: Self::load,
+        }
+    }
+
+    fn load(index: u32) -> Option<V> {
+        store::instance().get(&K::index_key(index))
+    }
+}
+
+impl<V, K> Default for InstanceVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    fn default() -> Self {
+        Self {
+            v: PhantomData,
+            k: PhantomData,
+        }
+    }
+}
+
+impl<V, K> IntoIterator for &InstanceVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    type Item = V;
+    type IntoIter = VecIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Clone)]
+pub struct TemporaryVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    v: PhantomData<V>,
+    k: PhantomData<K>,
+}
+
+impl<V, K> TemporaryVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    pub fn get(&self, index: u32) -> Option<V> {
+        let value = Self::load(index);
+        if value.is_some() {
+            extend_on_read::<K>(|threshold, extend_to| {
+                self.extend_ttl(index, threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// Replace the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn set(&mut self, index: u32, value: &V) {
+        assert!(index < self.len(), "index out of bounds");
+        store::temporary().set(&K::index_key(index), value);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(index, threshold, extend_to));
+    }
+
+    pub fn push(&self, value: &V) {
+        let storage = store::temporary();
+        let len = self.len();
+        storage.set(&K::index_key(len), value);
+        storage.set(&K::len_key(), &(len + 1));
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(len, threshold, extend_to));
+    }
+
+    pub fn pop(&self) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        let storage = store::temporary();
+        let value = storage.get(&K::index_key(last));
+        storage.remove(&K::index_key(last));
+        if last == 0 {
+            storage.remove(&K::len_key());
+        } else {
+            storage.set(&K::len_key(), &last);
+            extend_on_write::<K>(|threshold, extend_to| {
+                storage.extend_ttl(&K::len_key(), threshold, extend_to);
+            });
+        }
+        value
+    }
+
+    /// Remove the element at `index`, moving the last element into its place.
+    pub fn swap_remove(&self, index: u32) -> Option<V> {
+        let last = self.len().checked_sub(1)?;
+        if index > last {
+            return None;
+        }
+        if index == last {
+            return self.pop();
+        }
+        let storage = store::temporary();
+        let value = storage.get(&K::index_key(index));
+        let moved: V = storage.get(&K::index_key(last))?;
+        storage.set(&K::index_key(index), &moved);
+        storage.remove(&K::index_key(last));
+        storage.set(&K::len_key(), &last);
+        extend_on_write::<K>(|threshold, extend_to| self.extend_ttl(index, threshold, extend_to));
+        value
+    }
+
+    /// Extend the TTL of the element at `index` and of the stored length.
+    pub fn extend_ttl(&self, index: u32, threshold: u32, extend_to: u32) {
+        let storage = store::temporary();
+        storage.extend_ttl(&K::index_key(index), threshold, extend_to);
+        storage.extend_ttl(&K::len_key(), threshold, extend_to);
+    }
+
+    pub fn len(&self) -> u32 {
+        store::temporary().get(&K::len_key()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> VecIter<V> {
+        self.range(0..self.len())
+    }
+
+    /// Iterate over the elements in `range`, clamped to the length of the vector.
+    pub fn range(&self, range: Range<u32>) -> VecIter<V> {
+        VecIter {
+            next: range.start,
+            end: range.end.min(self.len()),
+            load: Self::load,
+        }
+    }
+
+    fn load(index: u32) -> Option<V> {
+        store::temporary().get(&K::index_key(index))
+    }
+}
+
+impl<V, K> Default for TemporaryVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    fn default() -> Self {
+        Self {
+            v: PhantomData,
+            k: PhantomData,
+        }
+    }
+}
+
+impl<V, K> IntoIterator for &TemporaryVec<V, K>
+where
+    V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    K: LoamKey + LoamIndexKey,
+{
+    type Item = V;
+    type IntoIter = VecIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of a `PersistentVec`, `InstanceVec` or `TemporaryVec`.
+pub struct VecIter<V> {
+    next: u32,
+    end: u32,
+    load: fn(u32) -> Option<V>,
+}
+
+impl<V> Iterator for VecIter<V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let value = (self.load)(self.next)?;
+        self.next += 1;
+        Some(value)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let n = u32::try_from(n).unwrap_or(u32::MAX);
+        self.next = self.next.saturating_add(n).min(self.end);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
@@ -0,0 +1,105 @@
+/// Storage layout of a `#[loamstorage(version = N)]` struct, which records in instance storage the
+/// version its state was last written with.
+pub trait Versioned {
+    /// Version of the layout this code was written against.
+    const VERSION: u32;
+
+    /// Version recorded in storage, `None` if the state predates versioning.
+    fn stored_version() -> Option<u32>;
+
+    fn set_stored_version(version: u32);
+
+    /// Record [`Versioned::VERSION`] unless a version is already stored.
+    ///
+    /// For state written by this code from the start, as in the constructor of a freshly deployed
+    /// contract. Writing the struct never records a version, so that state predating versioning
+    /// keeps counting as version 1 until its migrations run.
+    fn record_version() {
+        if Self::stored_version().is_none() {
+            Self::set_stored_version(Self::VERSION);
+        }
+    }
+}
+
+/// Migrations between the versions of a [`Versioned`] storage layout.
+///
+/// Migrations run when [`Migrate::run_migrations`] is called, which the admin does with the
+/// `migrate` method of the `Migrations` subcontract, in `loam-subcontract-core`, after redeploying
+/// the contract. Loading the struct never migrates it.
+///
+/// State without a stored version counts as version 1. Only [`Migrate::run_migrations`] and the
+/// constructor of the `Migrations` subcontract, which calls [`MigrateAll::record_versions`] when the
+/// contract is deployed, store a version.
+pub trait Migrate: Versioned {
+    /// Move the state from layout `from` to layout `from + 1`.
+    ///
+    /// Entries of the old layout can be read through a copy of the old struct, with the same
+    /// name, kept in its own module. Migrations must cope with entries that do not exist.
+    fn migrate(from: u32);
+
+    /// Run every pending migration and record the current version.
+    ///
+    /// # Panics
+    ///
+    /// If the stored version is newer than [`Versioned::VERSION`], which means the contract was
+    /// downgraded to code that cannot read its state.
+    fn run_migrations() {
+        let stored = Self::stored_version().unwrap_or(1);
+        if stored == Self::VERSION {
+            return;
+        }
+        assert!(
+            stored < Self::VERSION,
+            "storage was written by a newer version of this contract"
+        );
+        for from in stored..Self::VERSION {
+            Self::migrate(from);
+        }
+        Self::set_stored_version(Self::VERSION);
+    }
+}
+
+/// Runs the pending migrations of a [`Migrate`] struct, or of each struct in a tuple of them.
+pub trait MigrateAll {
+    fn migrate_all();
+
+    /// Record the current version of each struct, see [`Versioned::record_version`].
+    fn record_versions();
+}
+
+impl<T: Migrate> MigrateAll for T {
+    fn migrate_all() {
+        T::run_migrations();
+    }
+
+    fn record_versions() {
+        T::record_version();
+    }
+}
+
+impl MigrateAll for () {
+    fn migrate_all() {}
+
+    fn record_versions() {}
+}
+
+macro_rules! impl_migrate_all_for_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: Migrate),+> MigrateAll for ($($t,)+) {
+            fn migrate_all() {
+                $($t::run_migrations();)+
+            }
+
+            fn record_versions() {
+                $($t::record_version();)+
+            }
+        }
+    };
+}
+
+impl_migrate_all_for_tuple!(A);
+impl_migrate_all_for_tuple!(A, B);
+impl_migrate_all_for_tuple!(A, B, C);
+impl_migrate_all_for_tuple!(A, B, C, D);
+impl_migrate_all_for_tuple!(A, B, C, D, E);
+impl_migrate_all_for_tuple!(A, B, C, D, E, F);
@@ -0,0 +1,113 @@
+//! Storage entries listed in [`Lazy::STORAGE_KEYS`](crate::Lazy::STORAGE_KEYS), which
+//! `#[derive_contract]` compares to refuse subcontracts whose state would overlap.
+
+/// A storage entry a [`Lazy`](crate::Lazy) type reads or writes: the storage it is kept in and
+/// the key it is stored under. Keys built from the same name differ when they have a different
+/// [`KeyName`] kind, so a `Symbol` key never overlaps an enum variant of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageKey {
+    pub tier: StorageTier,
+    pub key: KeyName,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageTier {
+    Persistent,
+    Instance,
+    Temporary,
+}
+
+/// The host value an entry is stored under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyName {
+    /// `Symbol(name)`, as used by `#[derive(Lazy)]`.
+    Symbol(&'static str),
+    /// `String(name)`, as used by `#[derive(IntoKey)]`.
+    String(&'static str),
+    /// A `#[contracttype]` enum variant, a `Vec` starting with `Symbol(name)` and followed by the
+    /// variant's fields, as used by `#[loamstorage]`. Every value of the fields counts as the same
+    /// entry.
+    Variant(&'static str),
+}
+
+impl StorageKey {
+    #[must_use]
+    pub const fn new(tier: StorageTier, key: KeyName) -> Self {
+        Self { tier, key }
+    }
+
+    /// Whether `self` and `other` are the same entry.
+    #[must_use]
+    pub const fn overlaps(&self, other: &Self) -> bool {
+        if self.tier as u8 != other.tier as u8 {
+            return false;
+        }
+        match (self.key, other.key) {
+            (KeyName::Symbol(a), KeyName::Symbol(b))
+            | (KeyName::String(a), KeyName::String(b))
+            | (KeyName::Variant(a), KeyName::Variant(b)) => str_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Whether two [`Lazy::STORAGE_KEYS`](crate::Lazy::STORAGE_KEYS) lists share an entry.
+#[doc(hidden)]
+#[must_use]
+pub const fn keys_overlap(a: &[StorageKey], b: &[StorageKey]) -> bool {
+    let mut i = 0;
+    while i < a.len() {
+        let mut j = 0;
+        while j < b.len() {
+            if a[i].overlaps(&b[j]) {
+                return true;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const fn key(tier: StorageTier, key: KeyName) -> [StorageKey; 1] {
+        [StorageKey::new(tier, key)]
+    }
+
+    #[test]
+    fn same_name_overlaps_only_in_same_tier_and_kind() {
+        let admin = key(StorageTier::Instance, KeyName::Symbol("ADMIN"));
+        assert!(keys_overlap(&admin, &admin));
+        assert!(!keys_overlap(
+            &admin,
+            &key(StorageTier::Persistent, KeyName::Symbol("ADMIN"))
+        ));
+        assert!(!keys_overlap(
+            &admin,
+            &key(StorageTier::Instance, KeyName::Variant("ADMIN"))
+        ));
+        assert!(!keys_overlap(
+            &admin,
+            &key(StorageTier::Instance, KeyName::Symbol("ADMINS"))
+        ));
+        assert!(!keys_overlap(&admin, &[]));
+    }
+}
@@ -0,0 +1,291 @@
+//! Storage access used by the loam storage types and `Lazy` implementations.
+//!
+//! These wrap `env().storage()`. With the `cache` feature, entries are kept in contract memory
+//! for the length of a contract call: each is read from the ledger at most once, and written back
+//! once by [`flush_cache`], and only if it changed. Storage used directly through
+//! `env().storage()` does not see writes that are not flushed yet.
+//!
+//! Inside [`with_prefix`] every key is stored under a prefix, which is how `derive_contract` keeps
+//! the state of a subcontract mounted with `as prefix` apart from its other mounts.
+use soroban_sdk::{unwrap::UnwrapOptimized, Env, IntoVal, Symbol, TryFromVal, Val, Vec};
+
+use crate::{
+    env,
+    global::{global, Access},
+};
+
+#[cfg(feature = "cache")]
+mod cache;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tier {
+    Persistent,
+    Instance,
+    Temporary,
+}
+
+/// Persistent storage, see [`soroban_sdk::storage::Persistent`].
+#[derive(Clone, Copy)]
+pub struct Persistent;
+
+/// Instance storage, see [`soroban_sdk::storage::Instance`].
+#[derive(Clone, Copy)]
+pub struct Instance;
+
+/// Temporary storage, see [`soroban_sdk::storage::Temporary`].
+#[derive(Clone, Copy)]
+pub struct Temporary;
+
+pub fn persistent() -> Persistent {
+    Persistent
+}
+
+pub fn instance() -> Instance {
+    Instance
+}
+
+pub fn temporary() -> Temporary {
+    Temporary
+}
+
+global! {
+    /// Prefix set by [`with_prefix`].
+    static PREFIX: Symbol;
+}
+
+/// Run `f` with every key read or written through this module stored under `prefix`.
+///
+/// # Panics
+///
+/// If `prefix` is not a valid `Symbol`.
+pub fn with_prefix<R>(prefix: &str, f: impl FnOnce() -> R) -> R {
+    let previous = PREFIX.replace(Some(Symbol::new(env(), prefix)));
+    let result = f();
+    PREFIX.replace(previous);
+    result
+}
+
+/// The key as stored: itself, or paired with the current prefix.
+fn scoped<K: IntoVal<Env, Val>>(key: &K) -> Val {
+    let key = key.into_val(env());
+    match PREFIX.get() {
+        Some(prefix) => Vec::from_array(env(), [prefix.to_val(), key]).to_val(),
+        None => key,
+    }
+}
+
+/// The state of this module that belongs to one environment, set aside while an
+/// [`EnvGuard`](crate::EnvGuard) makes another one current.
+#[derive(Default)]
+pub(crate) struct State {
+    prefix: Option<Symbol>,
+    #[cfg(feature = "cache")]
+    cache: Option<cache::Cache>,
+}
+
+/// Set aside the state of the current environment, leaving none.
+pub(crate) fn take_state() -> State {
+    State {
+        prefix: PREFIX.replace(None),
+        #[cfg(feature = "cache")]
+        cache: cache::take(),
+    }
+}
+
+/// Bring back state set aside by [`take_state`], dropping any cached entries of the current
+/// environment that were not flushed.
+pub(crate) fn restore_state(state: State) {
+    PREFIX.replace(state.prefix);
+    #[cfg(feature = "cache")]
+    cache::restore(state.cache);
+}
+
+/// Write the entries cached for the current environment to the ledger. Contract methods and
+/// [`EnvGuard`](crate::EnvGuard) do this when they return; tests that use storage directly after
+/// [`set_env`](crate::set_env) need to call it themselves. Without the `cache` feature nothing is
+/// held back, and this does nothing.
+pub fn flush_cache() {
+    #[cfg(feature = "cache")]
+    cache::flush();
+}
+
+macro_rules! impl_store {
+    ($store:ident, $tier:expr) => {
+        impl $store {
+            pub fn get<K, V>(&self, key: &K) -> Option<V>
+            where
+                K: IntoVal<Env, Val>,
+                V: TryFromVal<Env, Val>,
+            {
+                get($tier, scoped(key))
+            }
+
+            pub fn set<K, V>(&self, key: &K, value: &V)
+            where
+                K: IntoVal<Env, Val>,
+                V: IntoVal<Env, Val>,
+            {
+                set($tier, scoped(key), Some(value.into_val(env())));
+            }
+
+            pub fn has<K>(&self, key: &K) -> bool
+            where
+                K: IntoVal<Env, Val>,
+            {
+                has($tier, scoped(key))
+            }
+
+            pub fn update<K, V>(&self, key: &K, f: impl FnOnce(Option<V>) -> V) -> V
+            where
+                K: IntoVal<Env, Val>,
+                V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+            {
+                let value = f(self.get(key));
+                self.set(key, &value);
+                value
+            }
+
+            pub fn try_update<K, V, E>(
+                &self,
+                key: &K,
+                f: impl FnOnce(Option<V>) -> Result<V, E>,
+            ) -> Result<V, E>
+            where
+                K: IntoVal<Env, Val>,
+                V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+            {
+                let value = f(self.get(key))?;
+                self.set(key, &value);
+                Ok(value)
+            }
+
+            pub fn remove<K>(&self, key: &K)
+            where
+                K: IntoVal<Env, Val>,
+            {
+                set($tier, scoped(key), None);
+            }
+        }
+    };
+}
+
+impl_store!(Persistent, Tier::Persistent);
+impl_store!(Instance, Tier::Instance);
+impl_store!(Temporary, Tier::Temporary);
+
+impl Persistent {
+    pub fn extend_ttl<K>(&self, key: &K, threshold: u32, extend_to: u32)
+    where
+        K: IntoVal<Env, Val>,
+    {
+        extend_ttl(Tier::Persistent, scoped(key), threshold, extend_to);
+    }
+}
+
+impl Temporary {
+    pub fn extend_ttl<K>(&self, key: &K, threshold: u32, extend_to: u32)
+    where
+        K: IntoVal<Env, Val>,
+    {
+        extend_ttl(Tier::Temporary, scoped(key), threshold, extend_to);
+    }
+}
+
+impl Instance {
+    /// Extend the TTL of the contract instance and its code.
+    pub fn extend_ttl(&self, threshold: u32, extend_to: u32) {
+        env().storage().instance().extend_ttl(threshold, extend_to);
+    }
+}
+
+fn get<V: TryFromVal<Env, Val>>(tier: Tier, key: Val) -> Option<V> {
+    #[cfg(feature = "cache")]
+    let value = cache::get(tier, key);
+    #[cfg(not(feature = "cache"))]
+    let value = ledger::get(tier, key);
+    value.map(|value| V::try_from_val(env(), &value).unwrap_optimized())
+}
+
+fn has(tier: Tier, key: Val) -> bool {
+    #[cfg(feature = "cache")]
+    return cache::has(tier, key);
+    #[cfg(not(feature = "cache"))]
+    ledger::has(tier, key)
+}
+
+/// Set the entry, or remove it if `value` is `None`.
+fn set(tier: Tier, key: Val, value: Option<Val>) {
+    #[cfg(feature = "cache")]
+    cache::set(tier, key, value);
+    #[cfg(not(feature = "cache"))]
+    match value {
+        Some(value) => ledger::set(tier, key, value),
+        None => ledger::remove(tier, key),
+    }
+}
+
+fn extend_ttl(tier: Tier, key: Val, threshold: u32, extend_to: u32) {
+    // The entry has to be on the ledger for its TTL to be extended.
+    #[cfg(feature = "cache")]
+    cache::flush_key(tier, key);
+    ledger::extend_ttl(tier, key, threshold, extend_to);
+}
+
+/// Storage on the ledger, bypassing the cache.
+mod ledger {
+    use soroban_sdk::Val;
+
+    use super::Tier;
+    use crate::env;
+
+    pub(super) fn get(tier: Tier, key: Val) -> Option<Val> {
+        let storage = env().storage();
+        match tier {
+            Tier::Persistent => storage.persistent().get(&key),
+            Tier::Instance => storage.instance().get(&key),
+            Tier::Temporary => storage.temporary().get(&key),
+        }
+    }
+
+    pub(super) fn set(tier: Tier, key: Val, value: Val) {
+        let storage = env().storage();
+        match tier {
+            Tier::Persistent => storage.persistent().set(&key, &value),
+            Tier::Instance => storage.instance().set(&key, &value),
+            Tier::Temporary => storage.temporary().set(&key, &value),
+        }
+    }
+
+    pub(super) fn has(tier: Tier, key: Val) -> bool {
+        let storage = env().storage();
+        match tier {
+            Tier::Persistent => storage.persistent().has(&key),
+            Tier::Instance => storage.instance().has(&key),
+            Tier::Temporary => storage.temporary().has(&key),
+        }
+    }
+
+    pub(super) fn remove(tier: Tier, key: Val) {
+        let storage = env().storage();
+        match tier {
+            Tier::Persistent => storage.persistent().remove(&key),
+            Tier::Instance => storage.instance().remove(&key),
+            Tier::Temporary => storage.temporary().remove(&key),
+        }
+    }
+
+    pub(super) fn extend_ttl(tier: Tier, key: Val, threshold: u32, extend_to: u32) {
+        let storage = env().storage();
+        match tier {
+            Tier::Persistent => storage.persistent().extend_ttl(&key, threshold, extend_to),
+            Tier::Instance => storage.instance().extend_ttl(threshold, extend_to),
+            Tier::Temporary => storage.temporary().extend_ttl(&key, threshold, extend_to),
+        }
+    }
+}
+
+/// Whether `a` and `b` hold the same value. Host values only compare by value through an object,
+/// so compare them inside vectors.
+pub(crate) fn same(a: Val, b: Val) -> bool {
+    a.shallow_eq(&b) || Vec::from_array(env(), [a]) == Vec::from_array(env(), [b])
+}
@@ -0,0 +1,169 @@
+//! The cache behind the `cache` feature: entries read or written during a contract call, kept in
+//! contract memory until [`flush`].
+//!
+//! It holds a fixed number of entries in plain slots rather than a host `Map`, whose every change
+//! would clone it. Once the slots are full, further keys go straight to the ledger.
+use soroban_sdk::{TryFromVal, Val, Vec};
+
+use super::{ledger, same, Tier};
+use crate::{
+    env,
+    global::{global, Access},
+};
+
+const SLOTS: usize = 16;
+
+/// What the ledger holds for a cached key.
+#[derive(Clone, Copy)]
+enum Stored {
+    /// The key was written without being read.
+    Unknown,
+    Missing,
+    Value(Val),
+}
+
+impl From<Option<Val>> for Stored {
+    fn from(value: Option<Val>) -> Self {
+        value.map_or(Self::Missing, Self::Value)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    tier: Tier,
+    key: Val,
+    stored: Stored,
+    /// The entry as the contract sees it, `None` once removed.
+    value: Option<Val>,
+}
+
+impl Slot {
+    fn is_changed(&self) -> bool {
+        match (self.stored, self.value) {
+            (Stored::Value(stored), Some(value)) => !same(stored, value),
+            (Stored::Missing, None) => false,
+            _ => true,
+        }
+    }
+
+    /// Write the entry to the ledger if it changed.
+    fn write(&mut self) {
+        if self.is_changed() {
+            match self.value {
+                Some(value) => ledger::set(self.tier, self.key, value),
+                None => ledger::remove(self.tier, self.key),
+            }
+        }
+        self.stored = self.value.into();
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Cache {
+    slots: [Option<Slot>; SLOTS],
+}
+
+global! {
+    /// Entries cached for the current environment.
+    static CACHE: Cache;
+}
+
+/// Whether two keys are the same. Keys built from the same value usually hold different objects,
+/// so objects of the same kind are compared by value.
+fn same_key(a: Val, b: Val) -> bool {
+    if a.shallow_eq(&b) {
+        return true;
+    }
+    if !a.is_object() || a.get_tag() != b.get_tag() {
+        return false;
+    }
+    let env = env();
+    match (
+        Vec::<Val>::try_from_val(env, &a),
+        Vec::<Val>::try_from_val(env, &b),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => same(a, b),
+    }
+}
+
+/// Run `f` on the slot of `key`, or on a free slot if it has none. `f` gets `None` if every slot
+/// is taken by another key.
+fn with_slot<R>(tier: Tier, key: Val, f: impl FnOnce(Option<&mut Option<Slot>>) -> R) -> R {
+    CACHE.with_mut(|cache| {
+        let slots = &mut cache.get_or_insert_with(Cache::default).slots;
+        let index = slots
+            .iter()
+            .position(|slot| slot.is_some_and(|slot| slot.tier == tier && same_key(slot.key, key)))
+            .or_else(|| slots.iter().position(Option::is_none));
+        f(index.map(|index| &mut slots[index]))
+    })
+}
+
+pub(super) fn get(tier: Tier, key: Val) -> Option<Val> {
+    with_slot(tier, key, |slot| match slot {
+        Some(Some(slot)) => slot.value,
+        Some(free) => {
+            let value = ledger::get(tier, key);
+            *free = Some(Slot {
+                tier,
+                key,
+                stored: value.into(),
+                value,
+            });
+            value
+        }
+        None => ledger::get(tier, key),
+    })
+}
+
+pub(super) fn has(tier: Tier, key: Val) -> bool {
+    with_slot(tier, key, |slot| match slot {
+        Some(Some(slot)) => slot.value.is_some(),
+        _ => ledger::has(tier, key),
+    })
+}
+
+pub(super) fn set(tier: Tier, key: Val, value: Option<Val>) {
+    with_slot(tier, key, |slot| match slot {
+        Some(Some(slot)) => slot.value = value,
+        Some(free) => {
+            *free = Some(Slot {
+                tier,
+                key,
+                stored: Stored::Unknown,
+                value,
+            });
+        }
+        None => match value {
+            Some(value) => ledger::set(tier, key, value),
+            None => ledger::remove(tier, key),
+        },
+    });
+}
+
+/// Write a pending change to `key` to the ledger, keeping it cached.
+pub(super) fn flush_key(tier: Tier, key: Val) {
+    with_slot(tier, key, |slot| {
+        if let Some(Some(slot)) = slot {
+            slot.write();
+        }
+    });
+}
+
+/// Write every changed entry to the ledger and empty the cache.
+pub(super) fn flush() {
+    if let Some(cache) = CACHE.replace(None) {
+        for mut slot in cache.slots.into_iter().flatten() {
+            slot.write();
+        }
+    }
+}
+
+pub(super) fn take() -> Option<Cache> {
+    CACHE.replace(None)
+}
+
+pub(super) fn restore(cache: Option<Cache>) {
+    CACHE.replace(cache);
+}
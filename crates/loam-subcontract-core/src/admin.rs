@@ -1,26 +1,13 @@
 use loam_sdk::{
-    soroban_sdk::{self, contracttype, env, symbol_short, Address, BytesN, Lazy, Symbol},
+    soroban_sdk::{self, contracttype, env, Address, BytesN, Lazy},
     subcontract,
 };
 
 #[contracttype(export = false)]
-#[derive(Default)]
+#[derive(Default, Lazy)]
+#[lazy(instance, key = "ADMIN")]
 pub struct Admin(Kind);
 
-fn admin_key() -> Symbol {
-    symbol_short!("ADMIN")
-}
-
-impl Lazy for Admin {
-    fn get_lazy() -> Option<Self> {
-        env().storage().instance().get(&admin_key())
-    }
-
-    fn set_lazy(self) {
-        env().storage().instance().set(&admin_key(), &self);
-    }
-}
-
 /// Work around not having `Option` in `contracttype`
 #[contracttype(export = false)]
 #[derive(Default)]
@@ -46,7 +33,6 @@ impl IsCore for Admin {
     }
 
     fn redeploy(&self, wasm_hash: BytesN<32>) {
-        self.admin_get().unwrap().require_auth();
         env().deployer().update_current_contract_wasm(wasm_hash);
     }
 }
@@ -56,10 +42,11 @@ pub trait IsCore {
     /// Get current admin
     fn admin_get(&self) -> Option<loam_sdk::soroban_sdk::Address>;
     /// Transfer to new admin
-    /// Should be called in the same transaction as deploying the contract to ensure that
-    /// a different account try to become admin
+    /// Should be called in the same transaction as deploying the contract, or from a constructor
+    /// hook, to ensure that a different account cannot become admin first
     fn admin_set(&mut self, new_admin: loam_sdk::soroban_sdk::Address);
 
     /// Admin can redeploy the contract with given hash.
+    #[only_admin]
     fn redeploy(&self, wasm_hash: loam_sdk::soroban_sdk::BytesN<32>);
 }
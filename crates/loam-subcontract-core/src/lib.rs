@@ -1,3 +1,7 @@
 #![no_std]
 pub mod admin;
+pub mod migrations;
+pub mod ttl;
 pub use admin::{Admin, Core};
+pub use migrations::{Migrations, Migrator};
+pub use ttl::{Ttl, TtlExtender};
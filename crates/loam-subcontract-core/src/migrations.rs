@@ -0,0 +1,56 @@
+use core::marker::PhantomData;
+
+use loam_sdk::{
+    soroban_sdk::{Lazy, MigrateAll, StorageKey},
+    subcontract,
+};
+
+/// Runs the storage migrations of the versioned `#[loamstorage]` structs `S`, which can be a
+/// single struct or a tuple of them.
+///
+/// Mount it with the structs to migrate, and call `migrate` after each `redeploy`. Mount it from
+/// the first deploy of a versioned struct, so that its constructor records the version:
+///
+/// ```ignore
+/// #[derive_contract(Core(Admin), Migrations(Migrator<(Token, Registry)>))]
+/// pub struct Contract;
+/// ```
+pub struct Migrator<S = ()>(PhantomData<S>);
+
+impl<S> Default for Migrator<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S> Lazy for Migrator<S> {
+    const STORAGE_KEYS: &'static [StorageKey] = &[];
+
+    fn get_lazy() -> Option<Self> {
+        Some(Self::default())
+    }
+
+    fn set_lazy(self) {}
+}
+
+impl<S: MigrateAll> IsMigrations for Migrator<S> {
+    fn migrate(&self) {
+        S::migrate_all();
+    }
+
+    fn migrations_init(&mut self) {
+        S::record_versions();
+    }
+}
+
+#[subcontract]
+pub trait IsMigrations {
+    /// Admin runs the storage migrations pending since the contract was redeployed.
+    #[only_admin]
+    fn migrate(&self);
+
+    /// Record the current storage versions when the contract is deployed, as its state is
+    /// written by this code from the start
+    #[constructor]
+    fn migrations_init(&mut self);
+}
@@ -0,0 +1,48 @@
+use core::marker::PhantomData;
+
+use loam_sdk::{
+    soroban_sdk::{store, ExtendTtl, Lazy, StorageKey},
+    subcontract,
+};
+
+/// Extends the TTL of the contract instance and code, and of the `#[loamstorage]` structs `S`,
+/// which can be a single struct or a tuple of them.
+///
+/// Mount it with the structs to extend:
+///
+/// ```ignore
+/// #[derive_contract(Core(Admin), Ttl(TtlExtender<(Token, Registry)>))]
+/// pub struct Contract;
+/// ```
+pub struct TtlExtender<S = ()>(PhantomData<S>);
+
+impl<S> Default for TtlExtender<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S> Lazy for TtlExtender<S> {
+    const STORAGE_KEYS: &'static [StorageKey] = &[];
+
+    fn get_lazy() -> Option<Self> {
+        Some(Self::default())
+    }
+
+    fn set_lazy(self) {}
+}
+
+impl<S: ExtendTtl> IsTtl for TtlExtender<S> {
+    fn extend_ttl(&self, threshold: u32, extend_to: u32) {
+        store::instance().extend_ttl(threshold, extend_to);
+        S::extend_ttl_all(threshold, extend_to);
+    }
+}
+
+#[subcontract]
+pub trait IsTtl {
+    /// Admin can extend the TTL of the contract instance and code, and of the storage items of
+    /// the contract's subcontracts.
+    #[only_admin]
+    fn extend_ttl(&self, threshold: u32, extend_to: u32);
+}
@@ -0,0 +1,72 @@
+//! Events of the token interface, as published by the Stellar Asset contract.
+use loam_sdk::{
+    loam_event,
+    soroban_sdk::{self, Address},
+};
+
+/// `spender` may spend `amount` of the tokens of `from` until `live_until_ledger`.
+#[loam_event]
+pub struct Approve {
+    #[topic]
+    pub from: Address,
+    #[topic]
+    pub spender: Address,
+    pub amount: i128,
+    pub live_until_ledger: u32,
+}
+
+/// `amount` was moved from `from` to `to`.
+#[loam_event]
+pub struct Transfer {
+    #[topic]
+    pub from: Address,
+    #[topic]
+    pub to: Address,
+    pub amount: i128,
+}
+
+/// `amount` of the tokens of `from` were burned.
+#[loam_event]
+pub struct Burn {
+    #[topic]
+    pub from: Address,
+    pub amount: i128,
+}
+
+/// `admin` minted `amount` to `to`.
+#[loam_event]
+pub struct Mint {
+    #[topic]
+    pub admin: Address,
+    #[topic]
+    pub to: Address,
+    pub amount: i128,
+}
+
+/// `admin` took back `amount` from `from`.
+#[loam_event]
+pub struct Clawback {
+    #[topic]
+    pub admin: Address,
+    #[topic]
+    pub from: Address,
+    pub amount: i128,
+}
+
+/// `admin` set whether `id` is authorized to use the token.
+#[loam_event]
+pub struct SetAuthorized {
+    #[topic]
+    pub admin: Address,
+    #[topic]
+    pub id: Address,
+    pub authorize: bool,
+}
+
+/// `admin` handed the token over to `new_admin`.
+#[loam_event]
+pub struct SetAdmin {
+    #[topic]
+    pub admin: Address,
+    pub new_admin: Address,
+}
@@ -1,6 +1,8 @@
 #![no_std]
 use loam_sdk::{soroban_sdk::Lazy, subcontract};
 
+pub mod events;
+
 /// SEP-41: Fungible Token Interface
 ///
 /// This trait defines a standard contract interface for fungible tokens on the Stellar network.
@@ -13,6 +15,11 @@ use loam_sdk::{soroban_sdk::Lazy, subcontract};
 ///
 /// For full specification, see: https://github.com/stellar/stellar-protocol/blob/master/ecosystem/sep-0041.md
 #[subcontract]
+#[events(
+    loam_subcontract_ft::events::Approve,
+    loam_subcontract_ft::events::Transfer,
+    loam_subcontract_ft::events::Burn
+)]
 pub trait IsSep41 {
     /// Returns the allowance for `spender` to transfer from `from`.
     fn allowance(
@@ -22,6 +29,7 @@ pub trait IsSep41 {
     ) -> i128;
 
     /// Set the allowance by `amount` for `spender` to transfer/burn from `from`.
+    #[require_auth(from)]
     fn approve(
         &mut self,
         from: loam_sdk::soroban_sdk::Address,
@@ -34,6 +42,8 @@ pub trait IsSep41 {
     fn balance(&self, id: loam_sdk::soroban_sdk::Address) -> i128;
 
     /// Transfer `amount` from `from` to `to`.
+    #[when_not_paused]
+    #[require_auth(from)]
     fn transfer(
         &mut self,
         from: loam_sdk::soroban_sdk::Address,
@@ -42,6 +52,8 @@ pub trait IsSep41 {
     );
 
     /// Transfer `amount` from `from` to `to`, consuming the allowance of `spender`.
+    #[when_not_paused]
+    #[require_auth(spender)]
     fn transfer_from(
         &mut self,
         spender: loam_sdk::soroban_sdk::Address,
@@ -51,9 +63,13 @@ pub trait IsSep41 {
     );
 
     /// Burn `amount` from `from`.
+    #[when_not_paused]
+    #[require_auth(from)]
     fn burn(&mut self, from: loam_sdk::soroban_sdk::Address, amount: i128);
 
     /// Burn `amount` from `from`, consuming the allowance of `spender`.
+    #[when_not_paused]
+    #[require_auth(spender)]
     fn burn_from(
         &mut self,
         spender: loam_sdk::soroban_sdk::Address,
@@ -72,8 +88,15 @@ pub trait IsSep41 {
 }
 
 #[subcontract]
+#[events(
+    loam_subcontract_ft::events::Mint,
+    loam_subcontract_ft::events::Clawback,
+    loam_subcontract_ft::events::SetAuthorized,
+    loam_subcontract_ft::events::SetAdmin
+)]
 pub trait IsFungible: IsSep41 {
     /// Increases the allowance that one address can spend on behalf of another address.
+    #[require_auth(from)]
     fn increase_allowance(
         &mut self,
         from: loam_sdk::soroban_sdk::Address,
@@ -82,6 +105,7 @@ pub trait IsFungible: IsSep41 {
     );
 
     /// Decreases the allowance that one address can spend on behalf of another address.
+    #[require_auth(from)]
     fn decrease_allowance(
         &mut self,
         from: loam_sdk::soroban_sdk::Address,
@@ -99,6 +123,7 @@ pub trait IsFungible: IsSep41 {
     fn set_authorized(&mut self, id: loam_sdk::soroban_sdk::Address, authorize: bool);
 
     /// Mints a specified amount of tokens to a specific address.
+    #[when_not_paused]
     fn mint(&mut self, to: loam_sdk::soroban_sdk::Address, amount: i128);
 
     /// Retrieves a specified amount of tokens from a specific address (clawback).
@@ -110,7 +135,8 @@ pub trait IsFungible: IsSep41 {
 
 #[subcontract]
 pub trait IsInitable {
-    /// Initialize ft Subcontract
+    /// Initialize ft Subcontract when the contract is deployed
+    #[constructor]
     fn ft_init(
         &mut self,
         admin: loam_sdk::soroban_sdk::Address,
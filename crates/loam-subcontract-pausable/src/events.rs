@@ -0,0 +1,19 @@
+//! Events published when the contract is paused or unpaused.
+use loam_sdk::{
+    loam_event,
+    soroban_sdk::{self, Address},
+};
+
+/// `admin` paused the contract.
+#[loam_event]
+pub struct Paused {
+    #[topic]
+    pub admin: Address,
+}
+
+/// `admin` unpaused the contract.
+#[loam_event]
+pub struct Unpaused {
+    #[topic]
+    pub admin: Address,
+}
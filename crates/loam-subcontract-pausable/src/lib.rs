@@ -0,0 +1,91 @@
+#![no_std]
+use core::marker::PhantomData;
+
+use loam_sdk::{
+    soroban_sdk::{self, contracttype, Address, Event, HasAdmin, Lazy, Val},
+    subcontract,
+};
+
+pub mod events;
+
+/// Whether the contract is paused.
+#[contracttype(export = false)]
+#[derive(Default, Lazy)]
+#[lazy(instance, key = "PAUSED")]
+pub struct PauseState(bool);
+
+/// The `Pausable` implementation, whose events name the admin `C` gives. Mount it as
+/// `Pausable(Pause<Self>)`, so that `C` is the contract, or the prefix it is mounted under.
+pub struct Pause<C>(PauseState, PhantomData<C>);
+
+impl<C> Default for Pause<C> {
+    fn default() -> Self {
+        Self(PauseState::default(), PhantomData)
+    }
+}
+
+impl<C> Lazy for Pause<C> {
+    const STORAGE_KEYS: &'static [soroban_sdk::StorageKey] = PauseState::STORAGE_KEYS;
+
+    fn get_lazy() -> Option<Self> {
+        PauseState::get_lazy().map(|state| Self(state, PhantomData))
+    }
+
+    fn set_lazy(self) {
+        self.0.set_lazy();
+    }
+
+    fn snapshot(&self) -> Option<Val> {
+        self.0.snapshot()
+    }
+}
+
+impl<C: HasAdmin> IsPausable for Pause<C> {
+    fn pause(&mut self) {
+        assert!(!self.0 .0, "contract is already paused");
+        self.0 .0 = true;
+        events::Paused {
+            admin: admin::<C>(),
+        }
+        .publish();
+    }
+
+    fn unpause(&mut self) {
+        assert!(self.0 .0, "contract is not paused");
+        self.0 .0 = false;
+        events::Unpaused {
+            admin: admin::<C>(),
+        }
+        .publish();
+    }
+
+    fn paused(&self) -> bool {
+        self.0 .0
+    }
+}
+
+/// The admin `#[only_admin]` has already checked.
+fn admin<C: HasAdmin>() -> Address {
+    C::admin().expect("admin is not set")
+}
+
+/// Emergency stop for a contract. Subcontract methods marked `#[when_not_paused]` fail while the
+/// `Pausable` subcontract mounted under the same prefix, or else at the root, is paused.
+#[subcontract]
+#[events(
+    loam_subcontract_pausable::events::Paused,
+    loam_subcontract_pausable::events::Unpaused
+)]
+pub trait IsPausable {
+    /// Admin can pause the contract, rejecting calls to the methods that check it is not paused.
+    #[only_admin]
+    fn pause(&mut self);
+
+    /// Admin can unpause the contract.
+    #[only_admin]
+    fn unpause(&mut self);
+
+    /// Whether the contract is paused.
+    #[pause_state]
+    fn paused(&self) -> bool;
+}
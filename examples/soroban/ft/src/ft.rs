@@ -1,11 +1,14 @@
 #![allow(deprecated)]
 
 use loam_sdk::{
-    soroban_sdk::{self, contracttype, env, Address, Lazy, Map, String},
+    soroban_sdk::{self, contracttype, env, Address, Event, Map, String},
     IntoKey,
 };
 use loam_subcontract_core::Core;
-use loam_subcontract_ft::{IsFungible, IsInitable, IsSep41};
+use loam_subcontract_ft::{
+    events::{Approve, Burn, Clawback, Mint, SetAdmin, SetAuthorized, Transfer},
+    IsFungible, IsInitable, IsSep41,
+};
 
 use crate::Contract;
 
@@ -58,40 +61,39 @@ impl Default for MyFungibleToken {
 
 impl IsInitable for MyFungibleToken {
     fn ft_init(&mut self, admin: Address, name: String, symbol: String, decimals: u32) {
-        Contract::admin_get().unwrap().require_auth();
-        MyFungibleToken::set_lazy(MyFungibleToken::new(admin, name, symbol, decimals));
+        Contract::admin_set(admin.clone());
+        *self = MyFungibleToken::new(admin, name, symbol, decimals);
     }
 }
 
 impl IsSep41 for MyFungibleToken {
     fn allowance(&self, from: Address, spender: Address) -> i128 {
-        let allowance = self.allowances.get(Txn(from, spender));
-        match allowance {
-            Some(a) => {
-                if env().ledger().sequence() <= a.live_until_ledger {
-                    a.amount
-                } else {
-                    0
-                }
-            }
-            None => 0,
+        match self.allowances.get(Txn(from, spender)) {
+            Some(a) if env().ledger().sequence() <= a.live_until_ledger => a.amount,
+            _ => 0,
         }
     }
 
     fn approve(&mut self, from: Address, spender: Address, amount: i128, live_until_ledger: u32) {
-        from.require_auth();
         let current_ledger = env().ledger().sequence();
         assert!(
             !(live_until_ledger < current_ledger && amount != 0),
             "live_until_ledger must be greater than or equal to the current ledger number"
         );
         self.allowances.set(
-            Txn(from, spender),
+            Txn(from.clone(), spender.clone()),
             Allowance {
                 amount,
                 live_until_ledger,
             },
         );
+        Approve {
+            from,
+            spender,
+            amount,
+            live_until_ledger,
+        }
+        .publish();
     }
 
     fn balance(&self, id: Address) -> i128 {
@@ -99,15 +101,14 @@ impl IsSep41 for MyFungibleToken {
     }
 
     fn transfer(&mut self, from: Address, to: Address, amount: i128) {
-        from.require_auth();
         let from_balance = self.balance(from.clone()) - amount;
         let to_balance = self.balance(to.clone()) + amount;
-        self.balances.set(from, from_balance);
-        self.balances.set(to, to_balance);
+        self.balances.set(from.clone(), from_balance);
+        self.balances.set(to.clone(), to_balance);
+        Transfer { from, to, amount }.publish();
     }
 
     fn transfer_from(&mut self, spender: Address, from: Address, to: Address, amount: i128) {
-        spender.require_auth();
         let allowance = self.allowance(from.clone(), spender.clone());
         if allowance >= amount {
             self.transfer(from.clone(), to, amount);
@@ -116,13 +117,12 @@ impl IsSep41 for MyFungibleToken {
     }
 
     fn burn(&mut self, from: Address, amount: i128) {
-        from.require_auth();
         let balance = self.balance(from.clone()) - amount;
-        self.balances.set(from, balance);
+        self.balances.set(from.clone(), balance);
+        Burn { from, amount }.publish();
     }
 
     fn burn_from(&mut self, spender: Address, from: Address, amount: i128) {
-        spender.require_auth();
         let allowance = self.allowance(from.clone(), spender.clone());
         if allowance >= amount {
             self.burn(from.clone(), amount);
@@ -145,7 +145,6 @@ impl IsSep41 for MyFungibleToken {
 
 impl IsFungible for MyFungibleToken {
     fn increase_allowance(&mut self, from: Address, spender: Address, amount: i128) {
-        from.require_auth();
         let current_allowance = self.allowance(from.clone(), spender.clone());
         let new_amount = current_allowance + amount;
         let current_ledger = env().ledger().sequence();
@@ -159,7 +158,6 @@ impl IsFungible for MyFungibleToken {
     }
 
     fn decrease_allowance(&mut self, from: Address, spender: Address, amount: i128) {
-        from.require_auth();
         let current_allowance = self.allowance(from.clone(), spender.clone());
         let new_amount = current_allowance.checked_sub(amount).unwrap_or(0);
         let current_ledger = env().ledger().sequence();
@@ -181,24 +179,42 @@ impl IsFungible for MyFungibleToken {
     }
 
     fn set_authorized(&mut self, id: Address, authorize: bool) {
-        self.admin.require_auth();
-        self.authorized.set(id, authorize);
+        let admin = self.admin.clone();
+        admin.require_auth();
+        self.authorized.set(id.clone(), authorize);
+        SetAuthorized {
+            admin,
+            id,
+            authorize,
+        }
+        .publish();
     }
 
     fn mint(&mut self, to: Address, amount: i128) {
-        self.admin.require_auth();
+        let admin = self.admin.clone();
+        admin.require_auth();
         let balance = self.balance(to.clone()) + amount;
-        self.balances.set(to, balance);
+        self.balances.set(to.clone(), balance);
+        Mint { admin, to, amount }.publish();
     }
 
     fn clawback(&mut self, from: Address, amount: i128) {
-        self.admin.require_auth();
+        let admin = self.admin.clone();
+        admin.require_auth();
         let balance = self.balance(from.clone()) - amount;
-        self.balances.set(from, balance);
+        self.balances.set(from.clone(), balance);
+        Clawback {
+            admin,
+            from,
+            amount,
+        }
+        .publish();
     }
 
     fn set_admin(&mut self, new_admin: Address) {
-        self.admin.require_auth();
-        self.admin = new_admin;
+        let admin = self.admin.clone();
+        admin.require_auth();
+        self.admin = new_admin.clone();
+        SetAdmin { admin, new_admin }.publish();
     }
 }
@@ -4,6 +4,7 @@ use loam_subcontract_core::{admin::Admin, Core};
 use loam_subcontract_ft::{Fungible, Initable, Sep41};
 
 pub mod ft;
+mod test;
 
 use ft::MyFungibleToken;
 
@@ -0,0 +1,90 @@
+#![cfg(test)]
+extern crate std;
+
+use loam_sdk::{
+    soroban_sdk::{Address, Env, Lazy, String},
+    testutils,
+};
+use loam_subcontract_ft::IsSep41;
+
+use crate::{ft::MyFungibleToken, Contract, ContractClient};
+
+fn create_token<'a>(env: &'a Env, admin: &Address) -> ContractClient<'a> {
+    Contract::register_test(
+        env,
+        admin.clone(),
+        String::from_str(env, "Token"),
+        String::from_str(env, "TKN"),
+        7,
+    )
+}
+
+#[test]
+fn constructor_sets_admin_and_metadata() {
+    let env = testutils::env_with_mocked_auths();
+    let [admin] = testutils::addresses(&env);
+    let token = create_token(&env, &admin);
+
+    assert_eq!(token.admin_get(), Some(admin));
+    assert_eq!(token.name(), String::from_str(&env, "Token"));
+    assert_eq!(token.symbol(), String::from_str(&env, "TKN"));
+    assert_eq!(token.decimals(), 7);
+}
+
+#[test]
+fn transfer_requires_auth_of_sender() {
+    let env = Env::default();
+    let [admin, user1, user2] = testutils::addresses(&env);
+    let token = create_token(&env, &admin);
+
+    testutils::mock_auth(&env, &admin, &token.address, "mint", (&user1, 1000_i128));
+    token.mint(&user1, &1000);
+
+    assert!(token.try_transfer(&user1, &user2, &100).is_err());
+    testutils::mock_auth(
+        &env,
+        &user2,
+        &token.address,
+        "transfer",
+        (&user1, &user2, 100_i128),
+    );
+    assert!(token.try_transfer(&user1, &user2, &100).is_err());
+
+    testutils::mock_auth(
+        &env,
+        &user1,
+        &token.address,
+        "transfer",
+        (&user1, &user2, 100_i128),
+    );
+    token.transfer(&user1, &user2, &100);
+    assert_eq!(token.balance(&user1), 900);
+    assert_eq!(token.balance(&user2), 100);
+}
+
+#[test]
+fn allowance_expires_after_its_ledger() {
+    let env = testutils::env_with_mocked_auths();
+    let [admin, user1, user2] = testutils::addresses(&env);
+    let token = create_token(&env, &admin);
+    let live_until_ledger = env.ledger().sequence() + 10;
+
+    token.approve(&user1, &user2, &500, &live_until_ledger);
+    testutils::advance_ledgers(&env, 10);
+    assert_eq!(token.allowance(&user1, &user2), 500);
+    testutils::advance_ledgers(&env, 1);
+    assert_eq!(token.allowance(&user1, &user2), 0);
+}
+
+#[test]
+fn balances_are_stored_in_the_token() {
+    let env = testutils::env_with_mocked_auths();
+    let [admin, user] = testutils::addresses(&env);
+    let token = create_token(&env, &admin);
+    token.mint(&user, &1000);
+
+    let balance = testutils::as_contract(&env, &token.address, || {
+        MyFungibleToken::get_lazy().unwrap().balance(user.clone())
+    });
+    assert_eq!(balance, 1000);
+}